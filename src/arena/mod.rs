@@ -0,0 +1,291 @@
+pub mod stats;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::{Color, Move, PieceKind};
+use crate::evaluation::Evaluator;
+use crate::search::{SearchLine, Searcher};
+pub use stats::{MatchStats, SprtDecision, SprtSettings};
+
+/// Short openings given as moves from the start position, each one is played with both colours.
+pub const DEFAULT_OPENINGS: [&str; 8] = [
+    "e2e4 e7e5 g1f3 b8c6",
+    "e2e4 c7c5 g1f3 d7d6",
+    "e2e4 e7e6 d2d4 d7d5",
+    "e2e4 c7c6 d2d4 d7d5",
+    "d2d4 d7d5 c2c4 e7e6",
+    "d2d4 g8f6 c2c4 g7g6",
+    "c2c4 e7e5 b1c3 g8f6",
+    "g1f3 d7d5 g2g3 g8f6",
+];
+
+/// One side of a match: an evaluator with the searcher (and its limits) that uses it.
+pub struct EngineConfig {
+    pub name: String,
+    pub evaluator: Box<dyn Evaluator>,
+    pub searcher: Box<dyn Searcher>,
+}
+
+impl EngineConfig {
+    pub fn new(name: &str, evaluator: Box<dyn Evaluator>, searcher: Box<dyn Searcher>) -> Self {
+        Self { name: name.to_string(), evaluator, searcher }
+    }
+
+    fn think(&self, board: &BitboardBoard) -> Option<SearchLine> {
+        self.searcher.search_lines(board, self.evaluator.as_ref(), 1, &mut |_| {}).into_iter().next()
+    }
+}
+
+/// Ends a game once both engines agree on the score for long enough.
+#[derive(Debug, Clone, Copy)]
+pub struct Adjudication {
+    pub win_score: i32,
+    pub win_plies: usize,
+    pub draw_score: i32,
+    pub draw_plies: usize,
+    /// Draws are only adjudicated from this full move on.
+    pub draw_move_number: u16,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self { win_score: 1000, win_plies: 6, draw_score: 10, draw_plies: 16, draw_move_number: 40 }
+    }
+}
+
+pub struct MatchSettings {
+    /// Upper bound on the number of games, games are played in pairs per opening.
+    pub games: usize,
+    pub openings: Vec<String>,
+    pub max_plies: usize,
+    pub adjudication: Option<Adjudication>,
+    pub sprt: Option<SprtSettings>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            games: 2 * DEFAULT_OPENINGS.len(),
+            openings: DEFAULT_OPENINGS.iter().map(|o| o.to_string()).collect(),
+            max_plies: 400,
+            adjudication: Some(Adjudication::default()),
+            sprt: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+    Adjudication,
+    MaxPlies,
+    /// The engine to move returned no move or one that isn't legal.
+    Forfeit,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub opening: usize,
+    pub first_is_white: bool,
+    pub result: GameResult,
+    pub termination: Termination,
+    pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+    /// 1, 0.5 or 0 from the first engine's point of view.
+    pub fn first_engine_score(&self) -> f64 {
+        match (self.result, self.first_is_white) {
+            (GameResult::Draw, _) => 0.5,
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+pub fn load_openings<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Sets up an opening written either as a FEN/EPD line or as UCI moves from the start position.
+pub fn opening_board(opening: &str) -> Option<BitboardBoard> {
+    let mut board = BitboardBoard::new_startpos();
+    if opening.contains('/') {
+        let fields: Vec<&str> = opening.split_whitespace().collect();
+        if fields.len() < 4 {
+            return None;
+        }
+        // EPD lines carry operations instead of the move counters
+        let has_counters = fields.len() >= 6 && fields[4].parse::<u32>().is_ok() && fields[5].parse::<u32>().is_ok();
+        let fen = if has_counters { fields[..6].join(" ") } else { format!("{} 0 1", fields[..4].join(" ")) };
+        board.load_fen(&fen);
+        return Some(board);
+    }
+
+    for uci in opening.split_whitespace() {
+        let mv = board.generate_moves().into_iter().find(|m| board.move_to_uci(m) == uci)?;
+        board.make_move(&mv);
+    }
+    Some(board)
+}
+
+/// Tracks how long both engines have agreed on a decisive or dead drawn score.
+#[derive(Default)]
+struct AdjudicationState {
+    /// Latest score of the engine playing each colour, from White's point of view.
+    white_scores: [Option<i32>; 2],
+    win_plies: usize,
+    winner: Option<Color>,
+    draw_plies: usize,
+}
+
+impl AdjudicationState {
+    /// Takes the score of the engine to move. Plies only count once both engines have reported
+    /// and their latest scores agree.
+    fn update(&mut self, rules: &Adjudication, board: &BitboardBoard, score: i32) -> Option<GameResult> {
+        let mover = board.side_to_move();
+        let white_score = if mover == Color::White { score } else { -score };
+        self.white_scores[if mover == Color::White { 0 } else { 1 }] = Some(white_score);
+        let [Some(white_view), Some(black_view)] = self.white_scores else { return None };
+
+        let leader = if white_view >= rules.win_score && black_view >= rules.win_score {
+            Some(Color::White)
+        } else if white_view <= -rules.win_score && black_view <= -rules.win_score {
+            Some(Color::Black)
+        } else {
+            None
+        };
+        self.win_plies = match leader {
+            Some(_) if self.winner == leader => self.win_plies + 1,
+            Some(_) => 1,
+            None => 0,
+        };
+        self.winner = leader;
+
+        let drawn = white_view.abs() <= rules.draw_score && black_view.abs() <= rules.draw_score;
+        if board.fullmove_number >= rules.draw_move_number && drawn {
+            self.draw_plies += 1;
+        } else {
+            self.draw_plies = 0;
+        }
+
+        if self.win_plies >= rules.win_plies {
+            return Some(if leader == Some(Color::White) { GameResult::WhiteWins } else { GameResult::BlackWins });
+        }
+        (self.draw_plies >= rules.draw_plies).then_some(GameResult::Draw)
+    }
+}
+
+fn loss_for(color: Color) -> GameResult {
+    if color == Color::White { GameResult::BlackWins } else { GameResult::WhiteWins }
+}
+
+/// Plays one game from `opening` and reports how it ended.
+pub fn play_game(white: &EngineConfig, black: &EngineConfig, opening: &BitboardBoard, settings: &MatchSettings) -> (GameResult, Termination, Vec<Move>) {
+    let mut board = opening.clone();
+    let mut keys = vec![board.hash()];
+    let mut moves = Vec::new();
+    let mut adjudication = AdjudicationState::default();
+    white.searcher.clear();
+    black.searcher.clear();
+
+    loop {
+        let legal_moves = board.generate_moves();
+        if legal_moves.is_empty() {
+            if board.is_in_check(board.side_to_move()) {
+                return (loss_for(board.side_to_move()), Termination::Checkmate, moves);
+            }
+            return (GameResult::Draw, Termination::Stalemate, moves);
+        }
+        if board.halfmove_clock >= 100 {
+            return (GameResult::Draw, Termination::FiftyMoves, moves);
+        }
+        if board.scores().insufficient_material() {
+            return (GameResult::Draw, Termination::InsufficientMaterial, moves);
+        }
+        let key = keys[keys.len() - 1];
+        if keys.iter().filter(|&&k| k == key).count() >= 3 {
+            return (GameResult::Draw, Termination::Repetition, moves);
+        }
+        if moves.len() >= settings.max_plies {
+            return (GameResult::Draw, Termination::MaxPlies, moves);
+        }
+
+        let engine = if board.side_to_move() == Color::White { white } else { black };
+        let Some(line) = engine.think(&board) else {
+            return (loss_for(board.side_to_move()), Termination::Forfeit, moves);
+        };
+        let Some(mv) = line.best_move().filter(|mv| legal_moves.contains(mv)) else {
+            return (loss_for(board.side_to_move()), Termination::Forfeit, moves);
+        };
+        if let Some(rules) = &settings.adjudication
+            && let Some(result) = adjudication.update(rules, &board, line.score) {
+            return (result, Termination::Adjudication, moves);
+        }
+
+        let irreversible = mv.captures.is_some() || mv.piece.kind == PieceKind::Pawn;
+        board.make_move(&mv);
+        if irreversible {
+            keys.clear();
+        }
+        keys.push(board.hash());
+        moves.push(mv);
+    }
+}
+
+/// Plays `first` against `second`, swapping colours after every game of an opening pair.
+/// `on_game` sees every finished game with the running totals. Stops early when the SPRT
+/// reaches a decision.
+pub fn run_match(
+    first: &EngineConfig,
+    second: &EngineConfig,
+    settings: &MatchSettings,
+    on_game: &mut dyn FnMut(&GameRecord, &MatchStats),
+) -> MatchStats {
+    let mut stats = MatchStats::default();
+    let openings: Vec<BitboardBoard> = settings.openings.iter().filter_map(|o| opening_board(o)).collect();
+    if openings.is_empty() {
+        return stats;
+    }
+
+    for game in 0..settings.games {
+        let opening = (game / 2) % openings.len();
+        let first_is_white = game % 2 == 0;
+        let (white, black) = if first_is_white { (first, second) } else { (second, first) };
+        let (result, termination, moves) = play_game(white, black, &openings[opening], settings);
+
+        let record = GameRecord { opening, first_is_white, result, termination, moves };
+        let score = record.first_engine_score();
+        if score > 0.5 {
+            stats.wins += 1;
+        } else if score < 0.5 {
+            stats.losses += 1;
+        } else {
+            stats.draws += 1;
+        }
+        on_game(&record, &stats);
+
+        if settings.sprt.is_some_and(|sprt| sprt.decision(&stats) != SprtDecision::Continue) {
+            break;
+        }
+    }
+    stats
+}
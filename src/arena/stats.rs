@@ -0,0 +1,103 @@
+/// Results of a match from the first engine's point of view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// Floor on the per game score variance used by the LLR. A run of identical results has no
+/// variance at all, which would make the LLR infinite after the first game.
+const MIN_VARIANCE: f64 = 0.05;
+
+/// Sequential probability ratio test between two Elo hypotheses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SprtSettings {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtDecision {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+/// Expected score for a given Elo difference.
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Elo difference for an expected score, infinite at 0 and 1.
+pub fn elo_from_score(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+impl MatchStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Per game variance of the score.
+    fn variance(&self) -> f64 {
+        let n = self.games() as f64;
+        let score = self.score();
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2)) / n
+    }
+
+    /// Elo difference with the half width of its 95% confidence interval. `None` until the
+    /// result is neither a clean sweep nor empty.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        if self.games() == 0 || self.wins + self.draws == 0 || self.losses + self.draws == 0 {
+            return None;
+        }
+        let score = self.score();
+        let deviation = (self.variance() / self.games() as f64).sqrt();
+        let low = elo_from_score((score - 1.96 * deviation).max(1e-6));
+        let high = elo_from_score((score + 1.96 * deviation).min(1.0 - 1e-6));
+        Some((elo_from_score(score), (high - low) / 2.0))
+    }
+
+    /// Log-likelihood ratio of H1 over H0 using the normal approximation of the score.
+    pub fn llr(&self, sprt: &SprtSettings) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let variance = self.variance().max(MIN_VARIANCE);
+        let s0 = expected_score(sprt.elo0);
+        let s1 = expected_score(sprt.elo1);
+        self.games() as f64 * (s1 - s0) * (2.0 * self.score() - s0 - s1) / (2.0 * variance)
+    }
+}
+
+impl SprtSettings {
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Self { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    /// Lower and upper LLR bounds.
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    pub fn decision(&self, stats: &MatchStats) -> SprtDecision {
+        let llr = stats.llr(self);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtDecision::AcceptH1
+        } else if llr <= lower {
+            SprtDecision::AcceptH0
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
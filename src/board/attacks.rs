@@ -0,0 +1,122 @@
+use super::types::{Color, Square};
+
+const NOT_A_FILE: u64 = 0xfefefefefefefefe;
+const NOT_H_FILE: u64 = 0x7f7f7f7f7f7f7f7f;
+
+const KNIGHT_ATTACKS: [u64; 64] = step_table(&[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)]);
+const KING_ATTACKS: [u64; 64] = step_table(&[(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)]);
+static BETWEEN: [[u64; 64]; 64] = line_table(false);
+static LINE: [[u64; 64]; 64] = line_table(true);
+
+const fn step_table(steps: &[(i8, i8)]) -> [u64; 64] {
+    let mut table = [0u64; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (rank, file) = ((square / 8) as i8, (square % 8) as i8);
+        let mut i = 0;
+        while i < steps.len() {
+            let (r, f) = (rank + steps[i].0, file + steps[i].1);
+            if r >= 0 && r < 8 && f >= 0 && f < 8 {
+                table[square] |= 1u64 << (r * 8 + f);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/// Squares strictly between two aligned squares, or with `full` the whole line through them.
+const fn line_table(full: bool) -> [[u64; 64]; 64] {
+    let mut table = [[0u64; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut b = 0;
+        while b < 64 {
+            let (ar, af) = ((a / 8) as i8, (a % 8) as i8);
+            let (dr, df) = ((b / 8) as i8 - ar, (b % 8) as i8 - af);
+            let aligned = a != b && (dr == 0 || df == 0 || dr == df || dr == -df);
+            if aligned {
+                let (sr, sf) = (dr.signum(), df.signum());
+                let mut squares = 0u64;
+                if full {
+                    // walk back to the edge, then across the whole board
+                    let (mut r, mut f) = (ar, af);
+                    while r - sr >= 0 && r - sr < 8 && f - sf >= 0 && f - sf < 8 {
+                        r -= sr;
+                        f -= sf;
+                    }
+                    while r >= 0 && r < 8 && f >= 0 && f < 8 {
+                        squares |= 1u64 << (r * 8 + f);
+                        r += sr;
+                        f += sf;
+                    }
+                } else {
+                    let (mut r, mut f) = (ar + sr, af + sf);
+                    while r * 8 + f != b as i8 {
+                        squares |= 1u64 << (r * 8 + f);
+                        r += sr;
+                        f += sf;
+                    }
+                }
+                table[a][b] = squares;
+            }
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+}
+
+pub fn knight_attacks(square: Square) -> u64 {
+    KNIGHT_ATTACKS[square.0 as usize]
+}
+
+pub fn king_attacks(square: Square) -> u64 {
+    KING_ATTACKS[square.0 as usize]
+}
+
+/// Squares a pawn of `color` on `square` attacks.
+pub fn pawn_attacks(square: Square, color: Color) -> u64 {
+    let bb = 1u64 << square.0;
+    match color {
+        Color::White => ((bb << 7) & NOT_H_FILE) | ((bb << 9) & NOT_A_FILE),
+        Color::Black => ((bb >> 7) & NOT_A_FILE) | ((bb >> 9) & NOT_H_FILE),
+    }
+}
+
+fn slide(square: Square, occupied: u64, directions: [(i8, i8); 4]) -> u64 {
+    let mut attacks = 0u64;
+    let (rank, file) = ((square.0 / 8) as i8, (square.0 % 8) as i8);
+    for (dr, df) in directions {
+        let (mut r, mut f) = (rank + dr, file + df);
+        while (0..8).contains(&r) && (0..8).contains(&f) {
+            let mask = 1u64 << (r * 8 + f);
+            attacks |= mask;
+            if occupied & mask != 0 {
+                break;
+            }
+            r += dr;
+            f += df;
+        }
+    }
+    attacks
+}
+
+pub fn bishop_attacks(square: Square, occupied: u64) -> u64 {
+    slide(square, occupied, [(1, 1), (1, -1), (-1, 1), (-1, -1)])
+}
+
+pub fn rook_attacks(square: Square, occupied: u64) -> u64 {
+    slide(square, occupied, [(1, 0), (-1, 0), (0, 1), (0, -1)])
+}
+
+/// Squares strictly between `a` and `b` when they share a rank, file or diagonal.
+pub fn between(a: Square, b: Square) -> u64 {
+    BETWEEN[a.0 as usize][b.0 as usize]
+}
+
+/// The full rank, file or diagonal through `a` and `b`, empty when they are not aligned.
+pub fn line(a: Square, b: Square) -> u64 {
+    LINE[a.0 as usize][b.0 as usize]
+}
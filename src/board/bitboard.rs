@@ -1,19 +1,32 @@
-use crate::move_generation::BitboardMoveGenerator;
+use std::borrow::Cow;
+use crate::evaluation::pst::Scores;
+use crate::book::polyglot::{castling_key, en_passant_key, piece_key, polyglot_key, turn_key};
+use crate::move_generation::{BitboardMoveGenerator, GenerationMode, MoveList};
 use crate::move_generation::naive_move_generator::NaiveMoveGenerator;
 use super::Board;
-use super::types::{Color, Move, Piece, PieceKind, Square};
+use super::{attacks, chess960};
+use super::types::{Color, CompactMove, Move, Piece, PieceKind, Square};
 
 pub const CASTLE_WHITE_KINGSIDE: u8 = 1;
 pub const CASTLE_WHITE_QUEENSIDE: u8 = 2;
 pub const CASTLE_BLACK_KINGSIDE: u8 = 4;
 pub const CASTLE_BLACK_QUEENSIDE: u8 = 8;
 
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Castling rights in the order of their flags, used to index `castling_rooks`.
+pub const CASTLING_RIGHTS: [u8; 4] = [CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE, CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE];
+const HISTORY_CAPACITY: usize = 256;
+pub(super) const DEFAULT_CASTLING_ROOKS: [Square; 4] = [Square(7), Square(0), Square(63), Square(56)];
+
 
 #[derive(Clone, Copy)]
 pub struct BoardState {
     pub castling_rights: u8,
     pub en_passant_square: Option<Square>,
     pub halfmove_clock: u8,
+    pub hash: u64,
+    pub scores: Scores,
 }
 
 pub struct BitboardBoard {
@@ -24,141 +37,261 @@ pub struct BitboardBoard {
     pub en_passant_square: Option<Square>,
     pub halfmove_clock: u8,
     pub fullmove_number: u16,
+    /// Starting squares of the castling rooks, indexed like `CASTLING_RIGHTS`.
+    pub castling_rooks: [Square; 4],
+    /// Writes castling moves in UCI as king takes rook.
+    pub chess960: bool,
+    /// Castling rights lost when a move starts or ends on each square.
+    castling_masks: [u8; 64],
+    /// Polyglot key of the position, kept up to date by make/unmake.
+    hash: u64,
+    /// Material and piece-square totals, kept up to date by make/unmake.
+    scores: Scores,
+    /// Piece on each square, kept in sync with the bitboards.
+    mailbox: [Option<Piece>; 64],
     history: Vec<BoardState>,
 }
 
 impl BitboardBoard {
     pub fn new_empty(move_generator: Option<Box<dyn BitboardMoveGenerator>>) -> Self {
-        match move_generator {
-            None => {
-                Self {
-                    bitboards: [0; 12],
-                    side_to_move: Color::White,
-                    move_generator: Box::new(NaiveMoveGenerator::new()),
-                    castling_rights: 0,
-                    en_passant_square: None,
-                    halfmove_clock: 0,
-                    fullmove_number: 1,
-                    history: Vec::new(),
-                }
-            }
-            Some(generator) => {
-                Self {
-                    bitboards: [0; 12],
-                    side_to_move: Color::White,
-                    move_generator: generator,
-                    castling_rights: 0,
-                    en_passant_square: None,
-                    halfmove_clock: 0,
-                    fullmove_number: 1,
-                    history: Vec::new(),
-                }
-            }
+        Self {
+            bitboards: [0; 12],
+            side_to_move: Color::White,
+            move_generator: move_generator.unwrap_or_else(|| Box::new(NaiveMoveGenerator::new())),
+            castling_rights: 0,
+            en_passant_square: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            castling_rooks: DEFAULT_CASTLING_ROOKS,
+            chess960: false,
+            castling_masks: chess960::castling_masks(DEFAULT_CASTLING_ROOKS, [Square(4), Square(60)]),
+            hash: turn_key(Color::White),
+            scores: Scores::default(),
+            mailbox: [None; 64],
+            history: Vec::with_capacity(HISTORY_CAPACITY),
         }
-
     }
 
     pub fn new_startpos() -> Self {
         let mut board = Self::new_empty(None);
-        board.load_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        board.load_fen(START_FEN);
         board
     }
-    pub fn switch_side(&mut self) {
-        self.side_to_move = if self.side_to_move == Color::White { Color::Black } else { Color::White };
+    /// Legal moves, filtered by making and unmaking each pseudo-legal move on this board.
+    pub fn generate_legal_moves(&mut self) -> MoveList {
+        self.generate_legal_moves_of(GenerationMode::All)
     }
 
-    pub fn to_fen(&self) -> String {
-        let mut fen = String::new();
-        for rank in (0..8).rev() {
-            let mut empty_squares = 0;
-            for file in 0..8 {
-                let square = Square::from_coords(file, rank);
-                if let Some(piece) = self.piece_at(square) {
-                    if empty_squares > 0 {
-                        fen.push_str(&empty_squares.to_string());
-                        empty_squares = 0;
-                    }
-                    fen.push(Self::piece_to_fen_char(piece));
-                } else {
-                    empty_squares += 1;
-                }
-            }
-            if empty_squares > 0 {
-                fen.push_str(&empty_squares.to_string());
-            }
-            if rank > 0 {
-                fen.push('/');
+    /// Whether a stored move, from a hash table or killer slot, could be generated in this
+    /// position. The king may still be left in check, see `is_legal`.
+    pub fn is_pseudo_legal(&self, mv: CompactMove) -> bool {
+        let side = self.side_to_move;
+        let (from, to, flag) = (mv.from(), mv.to(), mv.flag());
+        let Some(piece) = self.piece_at(from) else { return false };
+        // flags 6 and 7 are unused
+        if piece.color != side || from == to || flag == 6 || flag == 7 {
+            return false;
+        }
+        let own = self.get_mask_for_color(side);
+        let enemy = self.get_mask_for_color(side.opposite());
+        let to_mask = 1u64 << to.0;
+
+        if mv.is_castling() {
+            let right = match (side, flag == CompactMove::KING_CASTLE) {
+                (Color::White, true) => CASTLE_WHITE_KINGSIDE,
+                (Color::White, false) => CASTLE_WHITE_QUEENSIDE,
+                (Color::Black, true) => CASTLE_BLACK_KINGSIDE,
+                (Color::Black, false) => CASTLE_BLACK_QUEENSIDE,
+            };
+            let rooks = self.bitboards[self.get_bitboard_index(side, PieceKind::Rook)];
+            if piece.kind != PieceKind::King || self.castling_rights & right == 0
+                || self.castling_rook(right) != to || rooks & to_mask == 0 {
+                return false;
             }
+            let king_to = mv.king_destination();
+            let rook_to = Move::new(from, king_to, piece, None, None).castling_rook_destination();
+            let squares = [from.0, to.0, king_to.0, rook_to.0];
+            let (low, high) = (*squares.iter().min().unwrap(), *squares.iter().max().unwrap());
+            let span = (u64::MAX >> (63 - high)) & (u64::MAX << low);
+            return span & self.get_all_pieces_mask() & !(1u64 << from.0) & !to_mask == 0;
+        }
+        if own & to_mask != 0 || mv.is_capture() != (enemy & to_mask != 0 || mv.is_en_passant()) {
+            return false;
         }
 
-        fen.push(' ');
-        fen.push(if self.side_to_move == Color::White { 'w' } else { 'b' });
+        if piece.kind != PieceKind::Pawn {
+            if flag != CompactMove::QUIET && flag != CompactMove::CAPTURE {
+                return false;
+            }
+            let attacks = match piece.kind {
+                PieceKind::Knight => attacks::knight_attacks(from),
+                PieceKind::Bishop => self.get_bishop_attacks(from),
+                PieceKind::Rook => self.get_rook_attacks(from),
+                PieceKind::Queen => self.get_bishop_attacks(from) | self.get_rook_attacks(from),
+                _ => attacks::king_attacks(from),
+            };
+            return attacks & to_mask != 0;
+        }
 
-        fen.push(' ');
-        let mut castling_str = String::new();
-        if self.castling_rights & CASTLE_WHITE_KINGSIDE != 0 { castling_str.push('K'); }
-        if self.castling_rights & CASTLE_WHITE_QUEENSIDE != 0 { castling_str.push('Q'); }
-        if self.castling_rights & CASTLE_BLACK_KINGSIDE != 0 { castling_str.push('k'); }
-        if self.castling_rights & CASTLE_BLACK_QUEENSIDE != 0 { castling_str.push('q'); }
-        if castling_str.is_empty() {
-            fen.push('-');
-        } else {
-            fen.push_str(&castling_str);
+        let (forward, start_rank, last_rank) = match side {
+            Color::White => (8i8, 1, 7),
+            Color::Black => (-8i8, 6, 0),
+        };
+        if (to.0 / 8 == last_rank) != mv.promotion().is_some() {
+            return false;
+        }
+        let empty = !self.get_all_pieces_mask();
+        let one_step = from.0 as i8 + forward;
+        match flag {
+            CompactMove::DOUBLE_PUSH => {
+                from.0 / 8 == start_rank && to.0 as i8 == one_step + forward
+                    && empty & (1u64 << one_step) != 0 && empty & to_mask != 0
+            }
+            CompactMove::EN_PASSANT => {
+                self.en_passant_square == Some(to) && attacks::pawn_attacks(from, side) & to_mask != 0
+            }
+            _ if mv.is_capture() => attacks::pawn_attacks(from, side) & to_mask != 0,
+            _ => to.0 as i8 == one_step && empty & to_mask != 0,
         }
+    }
 
-        fen.push(' ');
-        if let Some(ep_square) = self.en_passant_square {
-            fen.push_str(&ep_square.to_notation());
-        } else {
-            fen.push('-');
+    /// Whether a stored move is playable here without leaving the king in check.
+    pub fn is_legal(&mut self, mv: CompactMove) -> bool {
+        if !self.is_pseudo_legal(mv) {
+            return false;
         }
+        match self.expand_move(mv) {
+            Some(full) => self.keeps_king_safe(&full),
+            None => false,
+        }
+    }
 
-        fen.push(' ');
-        fen.push_str(&self.halfmove_clock.to_string());
+    pub fn generate_moves_of(&self, mode: GenerationMode) -> MoveList {
+        self.scratch_copy().generate_legal_moves_of(mode)
+    }
 
-        fen.push(' ');
-        fen.push_str(&self.fullmove_number.to_string());
+    /// Copy for move filtering, without the move history.
+    fn scratch_copy(&self) -> Self {
+        self.copy_with_history(Vec::with_capacity(1))
+    }
 
-        fen
+    pub fn switch_side(&mut self) {
+        self.hash ^= en_passant_key(self) ^ turn_key(self.side_to_move);
+        self.side_to_move = self.side_to_move.opposite();
+        self.hash ^= en_passant_key(self) ^ turn_key(self.side_to_move);
+    }
+
+    fn castling_field(&self, shredder: bool) -> String {
+        chess960::castling_field(self, self.castling_rights, self.castling_rooks, shredder)
+    }
+
+    pub fn to_shredder_fen(&self) -> String {
+        let fen = self.to_fen();
+        let mut parts: Vec<&str> = fen.split(' ').collect();
+        let castling = self.castling_field(true);
+        parts[2] = if castling.is_empty() { "-" } else { &castling };
+        parts.join(" ")
+    }
+
+    /// The same position with the colours swapped and the board flipped top to bottom. The move
+    /// history is not carried over.
+    pub fn mirror(&self) -> Self {
+        let fen = self.to_shredder_fen();
+        let parts: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |s: &str| -> String {
+            s.chars().map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect()
+        };
+        let ranks: Vec<String> = parts[0].split('/').rev().map(swap_case).collect();
+        let side = if self.side_to_move == Color::White { "b" } else { "w" };
+        let en_passant = self.en_passant_square.map_or("-".to_string(), |sq| Square(sq.0 ^ 56).to_notation());
+        self.with_fen(&format!("{} {} {} {} {} {}", ranks.join("/"), side, swap_case(parts[2]), en_passant, parts[4], parts[5]))
+    }
+
+    /// The position reflected between the a and h files. Castling rights are dropped since the
+    /// king and rooks no longer stand where castling expects them.
+    pub fn flip_horizontal(&self) -> Self {
+        let fen = self.to_fen();
+        let parts: Vec<&str> = fen.split(' ').collect();
+        let ranks: Vec<String> = parts[0].split('/').map(|rank| rank.chars().rev().collect()).collect();
+        let en_passant = self.en_passant_square.map_or("-".to_string(), |sq| Square(sq.0 ^ 7).to_notation());
+        self.with_fen(&format!("{} {} - {} {} {}", ranks.join("/"), parts[1], en_passant, parts[4], parts[5]))
+    }
+
+    /// Sets up a position without going through FEN. `castling_rooks` lists the rooks that may
+    /// still castle, their colour is taken from the back rank they stand on.
+    pub fn set_position(
+        &mut self,
+        pieces: &[(Square, Piece)],
+        side_to_move: Color,
+        castling_rooks: &[Square],
+        en_passant_square: Option<Square>,
+        halfmove_clock: u8,
+        fullmove_number: u16,
+    ) {
+        self.bitboards = [0u64; 12];
+        self.mailbox = [None; 64];
+        self.history.clear();
+        for &(square, piece) in pieces {
+            self.bitboards[self.get_bitboard_index(piece.color, piece.kind)] |= 1u64 << square.0;
+            self.mailbox[square.0 as usize] = Some(piece);
+        }
+        self.side_to_move = side_to_move;
+
+        self.castling_rights = 0;
+        self.castling_rooks = DEFAULT_CASTLING_ROOKS;
+        for &rook in castling_rooks {
+            let color = if rook.0 < 8 { Color::White } else { Color::Black };
+            let king_file = self.king_square(color).map_or(4, |king| king.0 % 8);
+            let index = if color == Color::White { 0 } else { 2 } + if rook.0 % 8 > king_file { 0 } else { 1 };
+            self.castling_rights |= CASTLING_RIGHTS[index];
+            self.castling_rooks[index] = rook;
+        }
+
+        self.en_passant_square = en_passant_square;
+        self.halfmove_clock = halfmove_clock;
+        self.fullmove_number = fullmove_number;
+        self.finish_setup();
     }
 
-    fn piece_to_fen_char(piece: Piece) -> char {
-        let c = match piece.kind {
-            PieceKind::Pawn => 'p',
-            PieceKind::Knight => 'n',
-            PieceKind::Bishop => 'b',
-            PieceKind::Rook => 'r',
-            PieceKind::Queen => 'q',
-            PieceKind::King => 'k',
+    /// Derives the castling masks, hash and scores once the pieces and state are in place.
+    fn finish_setup(&mut self) {
+        let king_square = |color| {
+            let king = self.bitboards[self.get_bitboard_index(color, PieceKind::King)];
+            if king == 0 { Square(if color == Color::White { 4 } else { 60 }) } else { Square(king.trailing_zeros() as u8) }
         };
-        if piece.color == Color::White {
-            c.to_ascii_uppercase()
+        self.castling_masks = chess960::castling_masks(self.castling_rooks, [king_square(Color::White), king_square(Color::Black)]);
+        self.hash = polyglot_key(self);
+        self.scores = Scores::compute(self);
+    }
+
+    /// A new board for `fen` that keeps this board's move generator and Chess960 setting.
+    fn with_fen(&self, fen: &str) -> Self {
+        let mut board = Self::new_empty(Some(self.move_generator.box_clone()));
+        board.chess960 = self.chess960;
+        board.load_fen(fen);
+        board
+    }
+
+    /// Rook starting square for one of the `CASTLE_*` flags.
+    pub fn castling_rook(&self, right: u8) -> Square {
+        self.castling_rooks[CASTLING_RIGHTS.iter().position(|&r| r == right).unwrap()]
+    }
+
+    /// Square of the captured piece, behind the destination for en passant.
+    fn capture_mask(mv: &Move, en_passant: bool) -> u64 {
+        if !en_passant {
+            1u64 << mv.to.0
+        } else if mv.piece.color == Color::White {
+            1u64 << (mv.to.0 - 8)
         } else {
-            c
+            1u64 << (mv.to.0 + 8)
         }
     }
 }
 
 impl Board for BitboardBoard {
-
     fn piece_at(&self, sq: Square) -> Option<Piece> {
-        let mask = 1u64 << sq.0;
-        for (i, &bb) in self.bitboards.iter().enumerate() {
-            if bb & mask != 0 {
-                let color = if i < 6 { Color::White } else { Color::Black };
-                let kind = match i % 6 {
-                    0 => PieceKind::Pawn,
-                    1 => PieceKind::Knight,
-                    2 => PieceKind::Bishop,
-                    3 => PieceKind::Rook,
-                    4 => PieceKind::Queen,
-                    5 => PieceKind::King,
-                    _ => unreachable!(),
-                };
-                return Some(Piece { color, kind });
-            }
-        }
-        None
+        self.mailbox[sq.0 as usize]
     }
 
     fn get_all_pieces(&self) -> Vec<(Square, Piece)> {
@@ -184,46 +317,18 @@ impl Board for BitboardBoard {
         out
     }
 
-    fn generate_moves(&self) -> Vec<Move> {
-        let pseudo_legal_moves = self.move_generator.generate_moves(self);
-        let mut legal_moves = Vec::new();
-
-        for mv in pseudo_legal_moves {
-            let mut board_clone = self.clone();
-            board_clone.make_move(&mv);
-
-            if !board_clone.is_in_check(self.side_to_move()) {
-                if mv.piece.kind == PieceKind::King {
-                    let diff = mv.to.0 as i8 - mv.from.0 as i8;
-                    if diff.abs() == 2 {
-                        let mut is_legal = true;
-                        let (start, end) = if diff > 0 { (mv.from.0, mv.to.0) } else { (mv.to.0, mv.from.0) };
-                        let attack_map = self.generate_attack_map(self.side_to_move().opposite());
-                        for i in start..=end {
-                            if (attack_map & (1u64 << i)) != 0 {
-                                is_legal = false;
-                                break;
-                            }
-                        }
-                        if is_legal {
-                            legal_moves.push(mv);
-                        }
-                    } else {
-                        legal_moves.push(mv);
-                    }
-                } else {
-                    legal_moves.push(mv);
-                }
-            }
-        }
-        legal_moves
+    fn generate_moves(&self) -> MoveList {
+        self.scratch_copy().generate_legal_moves()
     }
     fn make_move(&mut self, mv: &Move) {
         self.history.push(BoardState {
             castling_rights: self.castling_rights,
             en_passant_square: self.en_passant_square,
             halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+            scores: self.scores,
         });
+        self.hash ^= castling_key(self.castling_rights) ^ en_passant_key(self) ^ turn_key(self.side_to_move);
 
         self.halfmove_clock += 1;
         if self.side_to_move == Color::Black {
@@ -237,58 +342,57 @@ impl Board for BitboardBoard {
         let to_mask = 1u64 << mv.to.0;
 
         let piece_index = self.get_bitboard_index(mv.piece.color, mv.piece.kind);
+        let en_passant = mv.piece.kind == PieceKind::Pawn && self.en_passant_square == Some(mv.to);
         self.en_passant_square = None;
 
         // remove from starting position
         self.bitboards[piece_index] &= !from_mask;
+        self.hash ^= piece_key(mv.piece, mv.from);
+        self.scores.remove(mv.piece, mv.from);
+        self.mailbox[mv.from.0 as usize] = None;
+
+        // castling, the rook may start on the king's destination in Chess960
+        if let Some(rook_from) = mv.castling {
+            let rook = Piece { color: mv.piece.color, kind: PieceKind::Rook };
+            let rook_to = mv.castling_rook_destination();
+            let rook_index = self.get_bitboard_index(mv.piece.color, PieceKind::Rook);
+            self.bitboards[rook_index] &= !(1u64 << rook_from.0);
+            self.bitboards[rook_index] |= 1u64 << rook_to.0;
+            self.hash ^= piece_key(rook, rook_from) ^ piece_key(rook, rook_to);
+            self.scores.remove(rook, rook_from);
+            self.scores.add(rook, rook_to);
+            self.mailbox[rook_from.0 as usize] = None;
+            self.mailbox[rook_to.0 as usize] = Some(rook);
+        }
 
         // capture
-        if let Some(captured) = &mv.captures {
+        if let Some(captured) = mv.captures {
+            let capture_mask = Self::capture_mask(mv, en_passant);
             let capture_index = self.get_bitboard_index(captured.color, captured.kind);
-            self.bitboards[capture_index] &= !to_mask;
+            self.bitboards[capture_index] &= !capture_mask;
+            let capture_square = Square(capture_mask.trailing_zeros() as u8);
+            self.hash ^= piece_key(captured, capture_square);
+            self.scores.remove(captured, capture_square);
+            self.mailbox[capture_square.0 as usize] = None;
         }
 
         // promotion
         if let Some(promoted_kind) = mv.promotion {
             let promo_index = self.get_bitboard_index(mv.piece.color, promoted_kind);
             self.bitboards[promo_index] |= to_mask;
+            let promoted = Piece { color: mv.piece.color, kind: promoted_kind };
+            self.hash ^= piece_key(promoted, mv.to);
+            self.scores.add(promoted, mv.to);
+            self.mailbox[mv.to.0 as usize] = Some(promoted);
         } else {
             self.bitboards[piece_index] |= to_mask;
+            self.hash ^= piece_key(mv.piece, mv.to);
+            self.scores.add(mv.piece, mv.to);
+            self.mailbox[mv.to.0 as usize] = Some(mv.piece);
         }
 
-        // castling
-        if mv.piece.kind == PieceKind::King {
-            let diff = mv.to.0 as i8 - mv.from.0 as i8;
-            if diff.abs() == 2 {
-                let (rook_from, rook_to) = if diff > 0 {
-                    (mv.to.0 + 1, mv.to.0 - 1)
-                } else {
-                    (mv.to.0 - 2, mv.to.0 + 1)
-                };
-                let rook_index = self.get_bitboard_index(mv.piece.color, PieceKind::Rook);
-                self.bitboards[rook_index] &= !(1u64 << rook_from);
-                self.bitboards[rook_index] |= 1u64 << rook_to;
-            }
-            if mv.piece.color == Color::White {
-                self.castling_rights &= !CASTLE_WHITE_KINGSIDE;
-                self.castling_rights &= !CASTLE_WHITE_QUEENSIDE;
-            } else {
-                self.castling_rights &= !CASTLE_BLACK_KINGSIDE;
-                self.castling_rights &= !CASTLE_BLACK_QUEENSIDE;
-            }
-        }
-
-        if mv.piece.kind == PieceKind::Rook {
-            if mv.from.0 == 0 {
-                self.castling_rights &= !CASTLE_WHITE_QUEENSIDE;
-            } else if mv.from.0 == 7 {
-                self.castling_rights &= !CASTLE_WHITE_KINGSIDE;
-            } else if mv.from.0 == 56 {
-                self.castling_rights &= !CASTLE_BLACK_QUEENSIDE;
-            } else if mv.from.0 == 63 {
-                self.castling_rights &= !CASTLE_BLACK_KINGSIDE;
-            }
-        }
+        // moving the king or a castling rook, or capturing that rook, loses the right
+        self.castling_rights &= !(self.castling_masks[mv.from.0 as usize] | self.castling_masks[mv.to.0 as usize]);
 
         if mv.piece.kind == PieceKind::Pawn {
             let diff = mv.to.0 as i8 - mv.from.0 as i8;
@@ -296,6 +400,9 @@ impl Board for BitboardBoard {
                 self.en_passant_square = Some(Square((mv.from.0 as i8 + diff / 2) as u8));
             }
         }
+
+        self.side_to_move = self.side_to_move.opposite();
+        self.hash ^= castling_key(self.castling_rights) ^ en_passant_key(self) ^ turn_key(self.side_to_move);
     }
 
 
@@ -304,8 +411,11 @@ impl Board for BitboardBoard {
             self.castling_rights = prev_state.castling_rights;
             self.en_passant_square = prev_state.en_passant_square;
             self.halfmove_clock = prev_state.halfmove_clock;
+            self.hash = prev_state.hash;
+            self.scores = prev_state.scores;
         }
-        if self.side_to_move == Color::White {
+        self.side_to_move = self.side_to_move.opposite();
+        if self.side_to_move == Color::Black {
             self.fullmove_number -= 1;
         }
 
@@ -326,33 +436,64 @@ impl Board for BitboardBoard {
 
         // capture
         if let Some(captured) = &mv.captures {
+            let en_passant = mv.piece.kind == PieceKind::Pawn && self.en_passant_square == Some(mv.to);
             let capture_index = self.get_bitboard_index(captured.color, captured.kind);
-            self.bitboards[capture_index] |= to_mask;
+            self.bitboards[capture_index] |= Self::capture_mask(mv, en_passant);
         }
 
         // castling
-        if mv.piece.kind == PieceKind::King {
-            let diff = mv.to.0 as i8 - mv.from.0 as i8;
-            if diff.abs() == 2 {
-                let (rook_from, rook_to) = if diff > 0 {
-                    (mv.to.0 + 1, mv.to.0 - 1)
-                } else {
-                    (mv.to.0 - 2, mv.to.0 + 1)
-                };
-                let rook_index = self.get_bitboard_index(mv.piece.color, PieceKind::Rook);
-                self.bitboards[rook_index] |= 1u64 << rook_from;
-                self.bitboards[rook_index] &= !(1u64 << rook_to);
-            }
+        if let Some(rook_from) = mv.castling {
+            let rook_index = self.get_bitboard_index(mv.piece.color, PieceKind::Rook);
+            self.bitboards[rook_index] &= !(1u64 << mv.castling_rook_destination().0);
+            self.bitboards[rook_index] |= 1u64 << rook_from.0;
+        }
+
+        // clear the destinations first, in Chess960 the king and rook may swap squares
+        self.mailbox[mv.to.0 as usize] = None;
+        if mv.castling.is_some() {
+            self.mailbox[mv.castling_rook_destination().0 as usize] = None;
+        }
+        self.mailbox[mv.from.0 as usize] = Some(mv.piece);
+        if let Some(rook_from) = mv.castling {
+            self.mailbox[rook_from.0 as usize] = Some(Piece { color: mv.piece.color, kind: PieceKind::Rook });
+        }
+        if let Some(captured) = mv.captures {
+            let en_passant = mv.piece.kind == PieceKind::Pawn && self.en_passant_square == Some(mv.to);
+            self.mailbox[Self::capture_mask(mv, en_passant).trailing_zeros() as usize] = Some(captured);
         }
     }
 
     fn hash(&self) -> u64 {
-        0
+        self.hash
     }
     fn side_to_move(&self) -> Color {
         self.side_to_move
     }
 
+    fn castling_rights(&self) -> u8 {
+        self.castling_rights
+    }
+
+    fn en_passant_square(&self) -> Option<Square> {
+        self.en_passant_square
+    }
+
+    fn halfmove_clock(&self) -> u8 {
+        self.halfmove_clock
+    }
+
+    fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    fn to_bitboard(&self) -> Cow<'_, BitboardBoard> {
+        Cow::Borrowed(self)
+    }
+
     fn load_fen(&mut self, fen: &str) {
         let parts: Vec<&str> = fen.split_whitespace().collect();
         if parts.len() < 6 {
@@ -360,6 +501,7 @@ impl Board for BitboardBoard {
         }
 
         self.bitboards = [0u64; 12];
+        self.mailbox = [None; 64];
         self.castling_rights = 0;
         self.en_passant_square = None;
         self.history.clear();
@@ -376,9 +518,10 @@ impl Board for BitboardBoard {
                 if let Some(digit) = c.to_digit(10) {
                     file_idx += digit as u8;
                 } else {
-                    if let Some(bb_index) = Self::fen_char_to_bitboard_index(c) {
+                    if let Some(piece) = Piece::from_fen_char(c) {
                         let square_index = (7 - rank_idx) * 8 + (file_idx as usize);
-                        self.bitboards[bb_index] |= 1u64 << square_index;
+                        self.bitboards[self.get_bitboard_index(piece.color, piece.kind)] |= 1u64 << square_index;
+                        self.mailbox[square_index] = Some(piece);
                     }
                     file_idx += 1;
                 }
@@ -391,18 +534,7 @@ impl Board for BitboardBoard {
             _ => panic!("Invalid FEN side to move"),
         };
 
-        let castling_part = parts[2];
-        if castling_part != "-" {
-            for c in castling_part.chars() {
-                match c {
-                    'K' => self.castling_rights |= CASTLE_WHITE_KINGSIDE,
-                    'Q' => self.castling_rights |= CASTLE_WHITE_QUEENSIDE,
-                    'k' => self.castling_rights |= CASTLE_BLACK_KINGSIDE,
-                    'q' => self.castling_rights |= CASTLE_BLACK_QUEENSIDE,
-                    _ => {}
-                }
-            }
-        }
+        (self.castling_rights, self.castling_rooks) = chess960::parse_castling_field(self, parts[2], DEFAULT_CASTLING_ROOKS);
 
         let en_passant_part = parts[3];
         if en_passant_part != "-" {
@@ -411,6 +543,129 @@ impl Board for BitboardBoard {
 
         self.halfmove_clock = parts[4].parse().unwrap_or(0);
         self.fullmove_number = parts[5].parse().unwrap_or(1);
+        self.finish_setup();
+    }
+
+    /// Legal moves of one generation mode.
+    fn generate_legal_moves_of(&mut self, mode: GenerationMode) -> MoveList {
+        let mut moves = self.move_generator.generate_moves_of(self, mode);
+        moves.retain(|mv| self.keeps_king_safe(mv));
+        moves
+    }
+
+    /// Passes the turn. Refused, leaving the board untouched, when the side to move is in check.
+    fn make_null_move(&mut self) -> bool {
+        if self.is_in_check(self.side_to_move) {
+            return false;
+        }
+        self.history.push(BoardState {
+            castling_rights: self.castling_rights,
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+            scores: self.scores,
+        });
+        self.hash ^= en_passant_key(self) ^ turn_key(self.side_to_move);
+        self.halfmove_clock += 1;
+        if self.side_to_move == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.en_passant_square = None;
+        self.side_to_move = self.side_to_move.opposite();
+        self.hash ^= turn_key(self.side_to_move);
+        true
+    }
+
+    fn unmake_null_move(&mut self) {
+        if let Some(prev_state) = self.history.pop() {
+            self.en_passant_square = prev_state.en_passant_square;
+            self.halfmove_clock = prev_state.halfmove_clock;
+            self.hash = prev_state.hash;
+        }
+        self.side_to_move = self.side_to_move.opposite();
+        if self.side_to_move == Color::Black {
+            self.fullmove_number -= 1;
+        }
+    }
+
+    fn scores(&self) -> &Scores {
+        &self.scores
+    }
+
+    fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty_squares = 0;
+            for file in 0..8 {
+                let square = Square::from_coords(file, rank);
+                if let Some(piece) = self.piece_at(square) {
+                    if empty_squares > 0 {
+                        fen.push_str(&empty_squares.to_string());
+                        empty_squares = 0;
+                    }
+                    fen.push(piece.to_fen_char());
+                } else {
+                    empty_squares += 1;
+                }
+            }
+            if empty_squares > 0 {
+                fen.push_str(&empty_squares.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push(' ');
+        fen.push(if self.side_to_move == Color::White { 'w' } else { 'b' });
+
+        fen.push(' ');
+        let castling_str = self.castling_field(false);
+        if castling_str.is_empty() {
+            fen.push('-');
+        } else {
+            fen.push_str(&castling_str);
+        }
+
+        fen.push(' ');
+        if let Some(ep_square) = self.en_passant_square {
+            fen.push_str(&ep_square.to_notation());
+        } else {
+            fen.push('-');
+        }
+
+        fen.push(' ');
+        fen.push_str(&self.halfmove_clock.to_string());
+
+        fen.push(' ');
+        fen.push_str(&self.fullmove_number.to_string());
+
+        fen
+    }
+
+    fn is_in_check(&self, color: Color) -> bool {
+        match self.king_square(color) {
+            Some(king) => self.is_square_attacked(king, color.opposite()),
+            None => true, // King is captured, this is an illegal state
+        }
+    }
+
+    /// Whether any piece of `by` attacks `square`, checking the cheapest pieces first.
+    fn is_square_attacked(&self, square: Square, by: Color) -> bool {
+        let piece = |kind| self.bitboards[self.get_bitboard_index(by, kind)];
+        if attacks::pawn_attacks(square, by.opposite()) & piece(PieceKind::Pawn) != 0
+            || attacks::knight_attacks(square) & piece(PieceKind::Knight) != 0
+            || attacks::king_attacks(square) & piece(PieceKind::King) != 0 {
+            return true;
+        }
+        let occupied = self.get_all_pieces_mask();
+        let queens = piece(PieceKind::Queen);
+        attacks::bishop_attacks(square, occupied) & (piece(PieceKind::Bishop) | queens) != 0
+            || attacks::rook_attacks(square, occupied) & (piece(PieceKind::Rook) | queens) != 0
+    }
+
+    fn gives_check(&mut self, mv: &Move) -> bool {
+        BitboardBoard::gives_check(self, mv)
     }
 }
 
@@ -461,32 +716,122 @@ impl BitboardBoard {
         }
     }
 
-    fn fen_char_to_bitboard_index(c: char) -> Option<usize> {
-        match c {
-            'P' => Some(0),
-            'N' => Some(1),
-            'B' => Some(2),
-            'R' => Some(3),
-            'Q' => Some(4),
-            'K' => Some(5),
-            'p' => Some(6),
-            'n' => Some(7),
-            'b' => Some(8),
-            'r' => Some(9),
-            'q' => Some(10),
-            'k' => Some(11),
-            _ => None,
+    fn bitboard_index_to_piece(index: usize) -> Piece {
+        let color = if index < 6 { Color::White } else { Color::Black };
+        let kind = [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King][index % 6];
+        Piece { color, kind }
+    }
+
+    /// Checks the mailbox against the bitboards, naming the first square where they disagree.
+    pub fn check_mailbox(&self) -> Result<(), String> {
+        for sq in 0..64u8 {
+            let mask = 1u64 << sq;
+            let mut owners = (0..12).filter(|&i| self.bitboards[i] & mask != 0);
+            let expected = owners.next().map(Self::bitboard_index_to_piece);
+            if owners.next().is_some() {
+                return Err(format!("{} is set on several bitboards", Square(sq).to_notation()));
+            }
+            if self.mailbox[sq as usize] != expected {
+                return Err(format!("{} holds {:?} in the mailbox but {:?} on the bitboards",
+                    Square(sq).to_notation(), self.mailbox[sq as usize], expected));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        let king = self.bitboards[self.get_bitboard_index(color, PieceKind::King)];
+        (king != 0).then(|| Square(king.trailing_zeros() as u8))
+    }
+
+    /// Pieces of both colours attacking `square`, with sliders seeing through everything
+    /// missing from `occupied`.
+    pub fn attackers_to(&self, square: Square, occupied: u64) -> u64 {
+        let piece = |color, kind| self.bitboards[self.get_bitboard_index(color, kind)];
+        let both = |kind| piece(Color::White, kind) | piece(Color::Black, kind);
+        let diagonal = both(PieceKind::Bishop) | both(PieceKind::Queen);
+        let straight = both(PieceKind::Rook) | both(PieceKind::Queen);
+
+        (attacks::pawn_attacks(square, Color::Black) & piece(Color::White, PieceKind::Pawn))
+            | (attacks::pawn_attacks(square, Color::White) & piece(Color::Black, PieceKind::Pawn))
+            | (attacks::knight_attacks(square) & both(PieceKind::Knight))
+            | (attacks::king_attacks(square) & both(PieceKind::King))
+            | (attacks::bishop_attacks(square, occupied) & diagonal)
+            | (attacks::rook_attacks(square, occupied) & straight)
+    }
+
+    /// Enemy pieces giving check to the side to move.
+    pub fn checkers(&self) -> u64 {
+        match self.king_square(self.side_to_move) {
+            Some(king) => self.attackers_to(king, self.get_all_pieces_mask()) & self.get_enemy_bitboard(),
+            None => 0,
+        }
+    }
+
+    /// Pieces of `color` that are the only blocker between their king and an enemy slider.
+    pub fn pinned(&self, color: Color) -> u64 {
+        let Some(king) = self.king_square(color) else { return 0 };
+        let enemy = |kind| self.bitboards[self.get_bitboard_index(color.opposite(), kind)];
+        let queens = enemy(PieceKind::Queen);
+        let snipers = (attacks::bishop_attacks(king, 0) & (enemy(PieceKind::Bishop) | queens))
+            | (attacks::rook_attacks(king, 0) & (enemy(PieceKind::Rook) | queens));
+        let occupied = self.get_all_pieces_mask();
+        let own = self.get_mask_for_color(color);
+
+        let mut pinned = 0u64;
+        let mut remaining = snipers;
+        while remaining != 0 {
+            let sniper = Square(remaining.trailing_zeros() as u8);
+            remaining &= remaining - 1;
+            let blockers = attacks::between(king, sniper) & occupied;
+            if blockers.count_ones() == 1 && blockers & own != 0 {
+                pinned |= blockers;
+            }
         }
+        pinned
     }
 
-    pub fn is_in_check(&self, color: Color) -> bool {
-        let king_bb = self.bitboards[self.get_bitboard_index(color, PieceKind::King)];
-        if king_bb == 0 {
-            return true; // King is captured, this is an illegal state
+    /// Whether a move by the side to move checks the enemy king, directly or by discovery,
+    /// without playing it.
+    pub fn gives_check(&self, mv: &Move) -> bool {
+        let side = mv.piece.color;
+        let Some(king) = self.king_square(side.opposite()) else { return false };
+        let king_mask = 1u64 << king.0;
+        let own = |kind| self.bitboards[self.get_bitboard_index(side, kind)];
+
+        let mut occupied = self.get_all_pieces_mask() & !(1u64 << mv.from.0);
+        let mut moved_away = 1u64 << mv.from.0;
+        if mv.captures.is_some() && mv.piece.kind == PieceKind::Pawn && self.en_passant_square == Some(mv.to) {
+            occupied &= !Self::capture_mask(mv, true);
+        }
+
+        // the piece that ends up giving check directly: the castling rook, or the mover
+        let (checker, checker_square) = if let Some(rook_from) = mv.castling {
+            let rook_to = mv.castling_rook_destination();
+            occupied = (occupied & !(1u64 << rook_from.0)) | (1u64 << rook_to.0);
+            moved_away |= 1u64 << rook_from.0;
+            (PieceKind::Rook, rook_to)
+        } else {
+            (mv.promotion.unwrap_or(mv.piece.kind), mv.to)
+        };
+        occupied |= 1u64 << mv.to.0;
+
+        let direct = match checker {
+            PieceKind::Pawn => attacks::pawn_attacks(checker_square, side),
+            PieceKind::Knight => attacks::knight_attacks(checker_square),
+            PieceKind::Bishop => attacks::bishop_attacks(checker_square, occupied),
+            PieceKind::Rook => attacks::rook_attacks(checker_square, occupied),
+            PieceKind::Queen => attacks::bishop_attacks(checker_square, occupied) | attacks::rook_attacks(checker_square, occupied),
+            PieceKind::King => 0,
+        };
+        if direct & king_mask != 0 {
+            return true;
         }
-        let king_square = king_bb.trailing_zeros() as u8;
-        let attack_map = self.generate_attack_map(color.opposite());
-        (attack_map & (1u64 << king_square)) != 0
+
+        let queens = own(PieceKind::Queen);
+        let diagonal = (own(PieceKind::Bishop) | queens) & !moved_away;
+        let straight = (own(PieceKind::Rook) | queens) & !moved_away;
+        attacks::bishop_attacks(king, occupied) & diagonal != 0 || attacks::rook_attacks(king, occupied) & straight != 0
     }
 
     pub fn generate_attack_map(&self, color: Color) -> u64 {
@@ -509,7 +854,7 @@ impl BitboardBoard {
             let knight_offsets = [15, 17, 10, 6, -15, -17, -10, -6];
             for &offset in knight_offsets.iter() {
                 let target = sq as i8 + offset;
-                if target < 0 || target >= 64 { continue; }
+                if !(0..64).contains(&target) { continue; }
 
                 let from_file = sq % 8;
                 let to_file = target as u8 % 8;
@@ -527,7 +872,7 @@ impl BitboardBoard {
             let king_offsets = [8, -8, 1, -1, 9, 7, -9, -7];
             for &offset in king_offsets.iter() {
                 let target = sq as i8 + offset;
-                if target < 0 || target >= 64 { continue; }
+                if !(0..64).contains(&target) { continue; }
 
                 let from_file = sq % 8;
                 let to_file = target as u8 % 8;
@@ -563,79 +908,40 @@ impl BitboardBoard {
     }
 
     fn get_bishop_attacks(&self, square: Square) -> u64 {
-        let mut attacks = 0u64;
-        let (r, f) = (square.0 / 8, square.0 % 8);
-        let occupied = self.get_all_pieces_mask();
-
-        for i in 1..8 {
-            if r + i > 7 || f + i > 7 { break; }
-            let mask = 1u64 << ((r + i) * 8 + (f + i));
-            attacks |= mask;
-            if occupied & mask != 0 { break; }
-        }
-        for i in 1..8 {
-            if r + i > 7 || f < i { break; }
-            let mask = 1u64 << ((r + i) * 8 + (f - i));
-            attacks |= mask;
-            if occupied & mask != 0 { break; }
-        }
-        for i in 1..8 {
-            if r < i || f + i > 7 { break; }
-            let mask = 1u64 << ((r - i) * 8 + (f + i));
-            attacks |= mask;
-            if occupied & mask != 0 { break; }
-        }
-        for i in 1..8 {
-            if r < i || f < i { break; }
-            let mask = 1u64 << ((r - i) * 8 + (f - i));
-            attacks |= mask;
-            if occupied & mask != 0 { break; }
-        }
-
-        attacks
+        attacks::bishop_attacks(square, self.get_all_pieces_mask())
     }
 
     fn get_rook_attacks(&self, square: Square) -> u64 {
-        let mut attacks = 0u64;
-        let (r, f) = (square.0 / 8, square.0 % 8);
-        let occupied = self.get_all_pieces_mask();
-
-        for i in (r + 1)..8 {
-            let mask = 1u64 << (i * 8 + f);
-            attacks |= mask;
-            if occupied & mask != 0 { break; }
-        }
-        for i in (0..r).rev() {
-            let mask = 1u64 << (i * 8 + f);
-            attacks |= mask;
-            if occupied & mask != 0 { break; }
-        }
-        for i in (f + 1)..8 {
-            let mask = 1u64 << (r * 8 + i);
-            attacks |= mask;
-            if occupied & mask != 0 { break; }
-        }
-        for i in (0..f).rev() {
-            let mask = 1u64 << (r * 8 + i);
-            attacks |= mask;
-            if occupied & mask != 0 { break; }
-        }
-
-        attacks
+        attacks::rook_attacks(square, self.get_all_pieces_mask())
     }
 }
 
 impl Clone for BitboardBoard {
     fn clone(&self) -> Self {
+        // room for a search's worth of moves so make_move does not reallocate
+        let mut history = Vec::with_capacity(self.history.len() + HISTORY_CAPACITY);
+        history.extend_from_slice(&self.history);
+        self.copy_with_history(history)
+    }
+}
+
+impl BitboardBoard {
+    fn copy_with_history(&self, history: Vec<BoardState>) -> Self {
         Self {
-            bitboards: self.bitboards.clone(),
+            bitboards: self.bitboards,
             side_to_move: self.side_to_move,
-            move_generator: Box::new(NaiveMoveGenerator::new()),
+            move_generator: self.move_generator.box_clone(),
             castling_rights: self.castling_rights,
             en_passant_square: self.en_passant_square,
-            history: self.history.clone(),
+            history,
             halfmove_clock: self.halfmove_clock,
             fullmove_number: self.fullmove_number,
+            castling_rooks: self.castling_rooks,
+            chess960: self.chess960,
+            castling_masks: self.castling_masks,
+            hash: self.hash,
+            scores: self.scores,
+            mailbox: self.mailbox,
         }
     }
 }
@@ -0,0 +1,124 @@
+use super::{BitboardBoard, Board};
+use super::bitboard::CASTLING_RIGHTS;
+use super::types::{Color, Piece, PieceKind, Square};
+
+/// Knight placements among the five squares left after the bishops and queen, by index.
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+/// The back rank of Chess960 start position `index` (0..960, Scharnagl numbering), white's
+/// pieces from the a-file to the h-file. Index 518 is the standard start position.
+pub fn back_rank(index: u16) -> Option<[char; 8]> {
+    if index >= 960 {
+        return None;
+    }
+    let mut rank = [' '; 8];
+    let mut n = index as usize;
+
+    rank[2 * (n % 4) + 1] = 'B';
+    n /= 4;
+    rank[2 * (n % 4)] = 'B';
+    n /= 4;
+
+    let empty = |rank: &[char; 8]| -> Vec<usize> { (0..8).filter(|&f| rank[f] == ' ').collect() };
+    let queen = empty(&rank)[n % 6];
+    rank[queen] = 'Q';
+    n /= 6;
+
+    let (first, second) = KNIGHT_PLACEMENTS[n];
+    let free = empty(&rank);
+    rank[free[first]] = 'N';
+    rank[free[second]] = 'N';
+
+    for (file, piece) in empty(&rank).into_iter().zip(['R', 'K', 'R']) {
+        rank[file] = piece;
+    }
+    Some(rank)
+}
+
+pub fn start_fen(index: u16) -> Option<String> {
+    let white: String = back_rank(index)?.iter().collect();
+    Some(format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", white.to_lowercase(), white))
+}
+
+/// Castling rights lost when a move starts or ends on each square, from the castling rooks
+/// (indexed like `CASTLING_RIGHTS`) and the White and Black king squares.
+pub fn castling_masks(rooks: [Square; 4], kings: [Square; 2]) -> [u8; 64] {
+    let mut masks = [0u8; 64];
+    for (i, &right) in CASTLING_RIGHTS.iter().enumerate() {
+        masks[rooks[i].0 as usize] |= right;
+        masks[kings[i / 2].0 as usize] |= right;
+    }
+    masks
+}
+
+fn king_square<B: Board>(board: &B, color: Color) -> Option<Square> {
+    let king = Some(Piece { color, kind: PieceKind::King });
+    (0..64).map(Square).find(|&sq| board.piece_at(sq) == king)
+}
+
+/// The rook furthest from the king on its side of the back rank.
+pub fn outermost_rook<B: Board>(board: &B, color: Color, kingside: bool) -> Option<Square> {
+    let base = if color == Color::White { 0 } else { 56 };
+    let king_file = king_square(board, color).filter(|king| king.0 / 8 == base / 8)?.0 % 8;
+    let rook = Some(Piece { color, kind: PieceKind::Rook });
+    let files: Vec<u8> = if kingside { (king_file + 1..8).rev().collect() } else { (0..king_file).collect() };
+    files.into_iter().map(|file| Square(base + file)).find(|&sq| board.piece_at(sq) == rook)
+}
+
+/// Reads a FEN castling field into castling rights and their rook squares, indexed like
+/// `CASTLING_RIGHTS`. KQkq pick the outermost rook (X-FEN), file letters name the rook
+/// (Shredder-FEN). Rooks that aren't found keep the square from `rooks`.
+pub fn parse_castling_field<B: Board>(board: &B, field: &str, mut rooks: [Square; 4]) -> (u8, [Square; 4]) {
+    let mut rights = 0;
+    for c in field.chars().filter(|_| field != "-") {
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let base = if color == Color::White { 0 } else { 56 };
+        let king_file = king_square(board, color).map_or(0, |king| king.0 % 8);
+        let (kingside, rook) = match c.to_ascii_lowercase() {
+            'k' => (true, outermost_rook(board, color, true)),
+            'q' => (false, outermost_rook(board, color, false)),
+            file @ 'a'..='h' => {
+                let file = file as u8 - b'a';
+                (file > king_file, Some(Square(base + file)))
+            }
+            _ => continue,
+        };
+        let index = if color == Color::White { 0 } else { 2 } + if kingside { 0 } else { 1 };
+        rights |= CASTLING_RIGHTS[index];
+        if let Some(rook) = rook {
+            rooks[index] = rook;
+        }
+    }
+    (rights, rooks)
+}
+
+/// Writes castling rights as X-FEN, where a file letter is only used when the castling rook
+/// is not the outermost one, or as Shredder-FEN with file letters throughout.
+pub fn castling_field<B: Board>(board: &B, rights: u8, rooks: [Square; 4], shredder: bool) -> String {
+    let mut field = String::new();
+    for (i, &right) in CASTLING_RIGHTS.iter().enumerate() {
+        if rights & right == 0 {
+            continue;
+        }
+        let rook = rooks[i];
+        let white = i < 2;
+        let outermost = outermost_rook(board, if white { Color::White } else { Color::Black }, i % 2 == 0);
+        let c = if !shredder && outermost == Some(rook) {
+            if i % 2 == 0 { 'k' } else { 'q' }
+        } else {
+            (b'a' + rook.0 % 8) as char
+        };
+        field.push(if white { c.to_ascii_uppercase() } else { c });
+    }
+    field
+}
+
+impl BitboardBoard {
+    /// Chess960 start position `index`, with castling written as king takes rook.
+    pub fn new_chess960(index: u16) -> Option<Self> {
+        let mut board = Self::new_empty(None);
+        board.load_fen(&start_fen(index)?);
+        board.chess960 = true;
+        Some(board)
+    }
+}
@@ -0,0 +1,172 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use super::{BitboardBoard, Board};
+use super::san::parse_san;
+use super::types::Move;
+
+/// One operation of an EPD record, e.g. `bm Nf3 Nc3` or `id "WAC.001"`. Quotes are removed
+/// from string operands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpdOperation {
+    pub opcode: String,
+    pub operands: Vec<String>,
+}
+
+/// A position in Extended Position Description form: the first four FEN fields followed by
+/// semicolon terminated operations.
+#[derive(Clone)]
+pub struct EpdRecord {
+    pub board: BitboardBoard,
+    pub operations: Vec<EpdOperation>,
+}
+
+impl EpdRecord {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let mut fields = Vec::new();
+        let mut rest = line;
+        for _ in 0..4 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("EPD needs four position fields: {}", line));
+            }
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        if fields[0].split('/').count() != 8 {
+            return Err(format!("EPD board must have 8 ranks: {}", fields[0]));
+        }
+        if fields[1] != "w" && fields[1] != "b" {
+            return Err(format!("Invalid side to move: {}", fields[1]));
+        }
+
+        let operations = Self::parse_operations(rest)?;
+        let mut record = Self { board: BitboardBoard::new_empty(None), operations };
+        let counter = |opcode| record.operand(opcode).and_then(|value| value.parse::<u16>().ok());
+        let halfmove = counter("hmvc").unwrap_or(0);
+        let fullmove = counter("fmvn").unwrap_or(1);
+        record.board.load_fen(&format!("{} {} {}", fields.join(" "), halfmove, fullmove));
+        Ok(record)
+    }
+
+    fn parse_operations(text: &str) -> Result<Vec<EpdOperation>, String> {
+        let mut operations = Vec::new();
+        let mut tokens = Vec::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                ';' => {
+                    if !tokens.is_empty() {
+                        let opcode = tokens.remove(0);
+                        operations.push(EpdOperation { opcode, operands: std::mem::take(&mut tokens) });
+                    }
+                }
+                '"' => {
+                    let mut token = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => token.push(c),
+                            None => return Err(format!("Unterminated string in EPD operations: {}", text)),
+                        }
+                    }
+                    tokens.push(token);
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let mut token = c.to_string();
+                    while let Some(&next) = chars.peek() {
+                        if next.is_whitespace() || next == ';' {
+                            break;
+                        }
+                        token.push(next);
+                        chars.next();
+                    }
+                    tokens.push(token);
+                }
+            }
+        }
+        // the last semicolon is often left out
+        if !tokens.is_empty() {
+            let opcode = tokens.remove(0);
+            operations.push(EpdOperation { opcode, operands: tokens });
+        }
+        Ok(operations)
+    }
+
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter().find(|op| op.opcode == opcode).map(|op| op.operands.as_slice())
+    }
+
+    /// The first operand of an operation.
+    pub fn operand(&self, opcode: &str) -> Option<&str> {
+        self.operation(opcode)?.first().map(String::as_str)
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operand("id")
+    }
+
+    /// Comments `c0` to `c9`.
+    pub fn comment(&self, index: u8) -> Option<&str> {
+        self.operand(&format!("c{}", index))
+    }
+
+    /// Analysis count depth.
+    pub fn acd(&self) -> Option<u8> {
+        self.operand("acd")?.parse().ok()
+    }
+
+    /// Centipawn evaluation from the side to move's point of view.
+    pub fn ce(&self) -> Option<i32> {
+        self.operand("ce")?.parse().ok()
+    }
+
+    fn moves(&self, opcode: &str) -> Vec<Move> {
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|san| parse_san(&self.board, san))
+            .collect()
+    }
+
+    pub fn best_moves(&self) -> Vec<Move> {
+        self.moves("bm")
+    }
+
+    pub fn avoid_moves(&self) -> Vec<Move> {
+        self.moves("am")
+    }
+
+    /// Point weighted moves as written by the Strategic Test Suite in `c0`, for example
+    /// `"Nf3=10, Nd2=7, e4=3"`. Without such a comment the best moves are worth 10 points.
+    pub fn move_points(&self) -> Vec<(Move, u32)> {
+        let weighted: Vec<(Move, u32)> = self.comment(0)
+            .into_iter()
+            .flat_map(|comment| comment.split(','))
+            .filter_map(|entry| {
+                let (san, points) = entry.trim().rsplit_once('=')?;
+                Some((parse_san(&self.board, san)?, points.trim().parse().ok()?))
+            })
+            .collect();
+        if weighted.is_empty() {
+            self.best_moves().into_iter().map(|mv| (mv, 10)).collect()
+        } else {
+            weighted
+        }
+    }
+}
+
+/// Reads every record of an EPD file, skipping blank lines and `#` comments.
+pub fn load_epd<P: AsRef<Path>>(path: P) -> io::Result<Vec<EpdRecord>> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            EpdRecord::parse(line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, err)))
+        })
+        .collect()
+}
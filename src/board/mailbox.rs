@@ -0,0 +1,510 @@
+use std::borrow::Cow;
+use crate::book::polyglot::{castling_key, en_passant_key, piece_key, polyglot_key, turn_key};
+use crate::evaluation::pst::Scores;
+use crate::move_generation::{is_tactical, GenerationMode, MoveList};
+use super::{chess960, BitboardBoard, Board};
+use super::bitboard::{BoardState, CASTLING_RIGHTS, DEFAULT_CASTLING_ROOKS};
+use super::types::{Color, Move, Piece, PieceKind, Square};
+
+/// Index of every square in the 10x12 board, where two guard ranks and one guard file on each
+/// side catch steps that would leave the board.
+const TO_120: [usize; 64] = {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        table[sq] = 21 + (sq / 8) * 10 + sq % 8;
+        sq += 1;
+    }
+    table
+};
+
+/// The square for each 10x12 index, -1 on the guard squares.
+const FROM_120: [i8; 120] = {
+    let mut table = [-1; 120];
+    let mut sq = 0;
+    while sq < 64 {
+        table[TO_120[sq]] = sq as i8;
+        sq += 1;
+    }
+    table
+};
+
+const KNIGHT_OFFSETS: [i8; 8] = [-21, -19, -12, -8, 8, 12, 19, 21];
+const BISHOP_OFFSETS: [i8; 4] = [-11, -9, 9, 11];
+const ROOK_OFFSETS: [i8; 4] = [-10, -1, 1, 10];
+const KING_OFFSETS: [i8; 8] = [-11, -10, -9, -1, 1, 9, 10, 11];
+const PROMOTIONS: [PieceKind; 4] = [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight];
+
+fn step(square: Square, offset: i8) -> Option<Square> {
+    let target = FROM_120[(TO_120[square.0 as usize] as i8 + offset) as usize];
+    (target >= 0).then_some(Square(target as u8))
+}
+
+/// Square-indexed board that walks a 10x12 mailbox to generate moves. It is much slower than
+/// `BitboardBoard` and is kept as an independent implementation to test it against.
+#[derive(Clone)]
+pub struct MailboxBoard {
+    squares: [Option<Piece>; 64],
+    side_to_move: Color,
+    castling_rights: u8,
+    en_passant_square: Option<Square>,
+    halfmove_clock: u8,
+    fullmove_number: u16,
+    /// Starting squares of the castling rooks, indexed like `CASTLING_RIGHTS`.
+    castling_rooks: [Square; 4],
+    /// Castling rights lost when a move starts or ends on each square.
+    castling_masks: [u8; 64],
+    /// Writes castling moves in UCI as king takes rook.
+    pub chess960: bool,
+    /// King squares, White first.
+    kings: [Square; 2],
+    hash: u64,
+    scores: Scores,
+    history: Vec<BoardState>,
+}
+
+impl Default for MailboxBoard {
+    fn default() -> Self {
+        Self::new_empty()
+    }
+}
+
+impl MailboxBoard {
+    pub fn new_empty() -> Self {
+        Self {
+            squares: [None; 64],
+            side_to_move: Color::White,
+            castling_rights: 0,
+            en_passant_square: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            castling_rooks: DEFAULT_CASTLING_ROOKS,
+            castling_masks: chess960::castling_masks(DEFAULT_CASTLING_ROOKS, [Square(4), Square(60)]),
+            chess960: false,
+            kings: [Square(4), Square(60)],
+            hash: turn_key(Color::White),
+            scores: Scores::default(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn new_startpos() -> Self {
+        let mut board = Self::new_empty();
+        board.load_fen(super::bitboard::START_FEN);
+        board
+    }
+
+    fn king_index(color: Color) -> usize {
+        if color == Color::White { 0 } else { 1 }
+    }
+
+    fn add(&mut self, piece: Piece, square: Square) {
+        self.squares[square.0 as usize] = Some(piece);
+        self.hash ^= piece_key(piece, square);
+        self.scores.add(piece, square);
+        if piece.kind == PieceKind::King {
+            self.kings[Self::king_index(piece.color)] = square;
+        }
+    }
+
+    fn remove(&mut self, piece: Piece, square: Square) {
+        self.squares[square.0 as usize] = None;
+        self.hash ^= piece_key(piece, square);
+        self.scores.remove(piece, square);
+    }
+
+    /// Puts a piece back during unmake, where hash and scores come from the history.
+    fn restore(&mut self, piece: Piece, square: Square) {
+        self.squares[square.0 as usize] = Some(piece);
+        if piece.kind == PieceKind::King {
+            self.kings[Self::king_index(piece.color)] = square;
+        }
+    }
+
+    /// Square of the captured piece, behind the destination for en passant.
+    fn capture_square(mv: &Move, en_passant: bool) -> Square {
+        match (en_passant, mv.piece.color) {
+            (false, _) => mv.to,
+            (true, Color::White) => Square(mv.to.0 - 8),
+            (true, Color::Black) => Square(mv.to.0 + 8),
+        }
+    }
+
+    fn pseudo_legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::with_en_passant(self.en_passant_square);
+        let side = self.side_to_move;
+        for from in (0..64).map(Square) {
+            let Some(piece) = self.squares[from.0 as usize] else { continue };
+            if piece.color != side {
+                continue;
+            }
+            match piece.kind {
+                PieceKind::Pawn => self.pawn_moves(piece, from, &mut moves),
+                PieceKind::Knight => self.step_moves(piece, from, &KNIGHT_OFFSETS, false, &mut moves),
+                PieceKind::Bishop => self.step_moves(piece, from, &BISHOP_OFFSETS, true, &mut moves),
+                PieceKind::Rook => self.step_moves(piece, from, &ROOK_OFFSETS, true, &mut moves),
+                PieceKind::Queen => self.step_moves(piece, from, &KING_OFFSETS, true, &mut moves),
+                PieceKind::King => {
+                    self.step_moves(piece, from, &KING_OFFSETS, false, &mut moves);
+                    self.castling_moves(piece, from, &mut moves);
+                }
+            }
+        }
+        moves
+    }
+
+    fn step_moves(&self, piece: Piece, from: Square, offsets: &[i8], slider: bool, moves: &mut MoveList) {
+        for &offset in offsets {
+            let mut current = step(from, offset);
+            while let Some(to) = current {
+                let target = self.squares[to.0 as usize];
+                if target.is_some_and(|t| t.color == piece.color) {
+                    break;
+                }
+                moves.push(Move::new(from, to, piece, None, target));
+                if target.is_some() || !slider {
+                    break;
+                }
+                current = step(to, offset);
+            }
+        }
+    }
+
+    fn pawn_moves(&self, piece: Piece, from: Square, moves: &mut MoveList) {
+        let (forward, start_rank, last_rank) = match piece.color {
+            Color::White => (10, 1, 7),
+            Color::Black => (-10, 6, 0),
+        };
+        let mut push = |to: Square, captures: Option<Piece>| {
+            if to.0 / 8 == last_rank {
+                for kind in PROMOTIONS {
+                    moves.push(Move::new(from, to, piece, Some(kind), captures));
+                }
+            } else {
+                moves.push(Move::new(from, to, piece, None, captures));
+            }
+        };
+
+        if let Some(one) = step(from, forward)
+            && self.squares[one.0 as usize].is_none() {
+            push(one, None);
+            if from.0 / 8 == start_rank
+                && let Some(two) = step(one, forward)
+                && self.squares[two.0 as usize].is_none() {
+                push(two, None);
+            }
+        }
+        for side_step in [-1, 1] {
+            let Some(to) = step(from, forward + side_step) else { continue };
+            match self.squares[to.0 as usize] {
+                Some(target) if target.color != piece.color => push(to, Some(target)),
+                None if self.en_passant_square == Some(to) => {
+                    push(to, Some(Piece { color: piece.color.opposite(), kind: PieceKind::Pawn }));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn castling_moves(&self, piece: Piece, from: Square, moves: &mut MoveList) {
+        let (base, first_right) = if piece.color == Color::White { (0, 0) } else { (56, 2) };
+        if from.0 / 8 != base / 8 {
+            return;
+        }
+        let rook = Some(Piece { color: piece.color, kind: PieceKind::Rook });
+        for (i, &right) in CASTLING_RIGHTS.iter().enumerate().skip(first_right).take(2) {
+            let rook_from = self.castling_rooks[i];
+            if self.castling_rights & right == 0 || self.squares[rook_from.0 as usize] != rook {
+                continue;
+            }
+            let king_to = Square(base + if i % 2 == 0 { 6 } else { 2 });
+            let castle = Move { castling: Some(rook_from), ..Move::new(from, king_to, piece, None, None) };
+            // everything the king and rook cross or land on is empty, apart from the two of them
+            let squares = [from.0, rook_from.0, king_to.0, castle.castling_rook_destination().0];
+            let (low, high) = (*squares.iter().min().unwrap(), *squares.iter().max().unwrap());
+            if (low..=high).all(|sq| sq == from.0 || sq == rook_from.0 || self.squares[sq as usize].is_none()) {
+                moves.push(castle);
+            }
+        }
+    }
+}
+
+impl Board for MailboxBoard {
+    fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.squares[square.0 as usize]
+    }
+
+    fn get_all_pieces(&self) -> Vec<(Square, Piece)> {
+        (0..64).filter_map(|sq| self.squares[sq].map(|piece| (Square(sq as u8), piece))).collect()
+    }
+
+    fn generate_moves(&self) -> MoveList {
+        self.clone().generate_legal_moves_of(GenerationMode::All)
+    }
+
+    fn generate_legal_moves_of(&mut self, mode: GenerationMode) -> MoveList {
+        let mut moves = self.pseudo_legal_moves();
+        match mode {
+            // in check every legal move is an evasion
+            GenerationMode::All | GenerationMode::Evasions => {}
+            GenerationMode::Captures => moves.retain(is_tactical),
+            GenerationMode::Quiets => moves.retain(|m| !is_tactical(m)),
+            GenerationMode::QuietChecks => moves.retain(|m| !is_tactical(m) && self.gives_check(m)),
+        }
+        moves.retain(|mv| self.keeps_king_safe(mv));
+        moves
+    }
+
+    fn make_move(&mut self, mv: &Move) {
+        self.history.push(BoardState {
+            castling_rights: self.castling_rights,
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+            scores: self.scores,
+        });
+        self.hash ^= castling_key(self.castling_rights) ^ en_passant_key(self) ^ turn_key(self.side_to_move);
+
+        self.halfmove_clock += 1;
+        if self.side_to_move == Color::Black {
+            self.fullmove_number += 1;
+        }
+        if mv.piece.kind == PieceKind::Pawn || mv.captures.is_some() {
+            self.halfmove_clock = 0;
+        }
+
+        let en_passant = mv.piece.kind == PieceKind::Pawn && self.en_passant_square == Some(mv.to);
+        self.en_passant_square = None;
+
+        self.remove(mv.piece, mv.from);
+        if let Some(rook_from) = mv.castling {
+            let rook = Piece { color: mv.piece.color, kind: PieceKind::Rook };
+            self.remove(rook, rook_from);
+            self.add(rook, mv.castling_rook_destination());
+        }
+        if let Some(captured) = mv.captures {
+            self.remove(captured, Self::capture_square(mv, en_passant));
+        }
+        let kind = mv.promotion.unwrap_or(mv.piece.kind);
+        self.add(Piece { color: mv.piece.color, kind }, mv.to);
+
+        self.castling_rights &= !(self.castling_masks[mv.from.0 as usize] | self.castling_masks[mv.to.0 as usize]);
+        if mv.piece.kind == PieceKind::Pawn && mv.from.0.abs_diff(mv.to.0) == 16 {
+            self.en_passant_square = Some(Square((mv.from.0 + mv.to.0) / 2));
+        }
+
+        self.side_to_move = self.side_to_move.opposite();
+        self.hash ^= castling_key(self.castling_rights) ^ en_passant_key(self) ^ turn_key(self.side_to_move);
+    }
+
+    fn unmake_move(&mut self, mv: &Move) {
+        if let Some(prev_state) = self.history.pop() {
+            self.castling_rights = prev_state.castling_rights;
+            self.en_passant_square = prev_state.en_passant_square;
+            self.halfmove_clock = prev_state.halfmove_clock;
+            self.hash = prev_state.hash;
+            self.scores = prev_state.scores;
+        }
+        self.side_to_move = self.side_to_move.opposite();
+        if self.side_to_move == Color::Black {
+            self.fullmove_number -= 1;
+        }
+
+        self.squares[mv.to.0 as usize] = None;
+        if let Some(rook_from) = mv.castling {
+            self.squares[mv.castling_rook_destination().0 as usize] = None;
+            self.restore(Piece { color: mv.piece.color, kind: PieceKind::Rook }, rook_from);
+        }
+        self.restore(mv.piece, mv.from);
+        if let Some(captured) = mv.captures {
+            let en_passant = mv.piece.kind == PieceKind::Pawn && self.en_passant_square == Some(mv.to);
+            self.restore(captured, Self::capture_square(mv, en_passant));
+        }
+    }
+
+    fn make_null_move(&mut self) -> bool {
+        if self.is_in_check(self.side_to_move) {
+            return false;
+        }
+        self.history.push(BoardState {
+            castling_rights: self.castling_rights,
+            en_passant_square: self.en_passant_square,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+            scores: self.scores,
+        });
+        self.hash ^= en_passant_key(self) ^ turn_key(self.side_to_move);
+        self.halfmove_clock += 1;
+        if self.side_to_move == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.en_passant_square = None;
+        self.side_to_move = self.side_to_move.opposite();
+        self.hash ^= turn_key(self.side_to_move);
+        true
+    }
+
+    fn unmake_null_move(&mut self) {
+        if let Some(prev_state) = self.history.pop() {
+            self.en_passant_square = prev_state.en_passant_square;
+            self.halfmove_clock = prev_state.halfmove_clock;
+            self.hash = prev_state.hash;
+        }
+        self.side_to_move = self.side_to_move.opposite();
+        if self.side_to_move == Color::Black {
+            self.fullmove_number -= 1;
+        }
+    }
+
+    fn is_in_check(&self, color: Color) -> bool {
+        self.is_square_attacked(self.kings[Self::king_index(color)], color.opposite())
+    }
+
+    fn is_square_attacked(&self, square: Square, by: Color) -> bool {
+        let is = |sq: Option<Square>, kinds: &[PieceKind]| {
+            sq.and_then(|sq| self.squares[sq.0 as usize]).is_some_and(|p| p.color == by && kinds.contains(&p.kind))
+        };
+        // a pawn attacks diagonally forward, so look diagonally backward from the square
+        let pawn_offsets = if by == Color::White { [-11, -9] } else { [9, 11] };
+        if pawn_offsets.iter().any(|&o| is(step(square, o), &[PieceKind::Pawn]))
+            || KNIGHT_OFFSETS.iter().any(|&o| is(step(square, o), &[PieceKind::Knight]))
+            || KING_OFFSETS.iter().any(|&o| is(step(square, o), &[PieceKind::King])) {
+            return true;
+        }
+        let slides = |offsets: &[i8], kinds: &[PieceKind]| {
+            offsets.iter().any(|&offset| {
+                let mut current = step(square, offset);
+                while let Some(sq) = current {
+                    if self.squares[sq.0 as usize].is_some() {
+                        return is(Some(sq), kinds);
+                    }
+                    current = step(sq, offset);
+                }
+                false
+            })
+        };
+        slides(&BISHOP_OFFSETS, &[PieceKind::Bishop, PieceKind::Queen])
+            || slides(&ROOK_OFFSETS, &[PieceKind::Rook, PieceKind::Queen])
+    }
+
+    fn gives_check(&mut self, mv: &Move) -> bool {
+        self.make_move(mv);
+        let check = self.is_in_check(self.side_to_move);
+        self.unmake_move(mv);
+        check
+    }
+
+    fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    fn scores(&self) -> &Scores {
+        &self.scores
+    }
+
+    fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    fn castling_rights(&self) -> u8 {
+        self.castling_rights
+    }
+
+    fn en_passant_square(&self) -> Option<Square> {
+        self.en_passant_square
+    }
+
+    fn halfmove_clock(&self) -> u8 {
+        self.halfmove_clock
+    }
+
+    fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    fn load_fen(&mut self, fen: &str) {
+        let parts: Vec<&str> = fen.split_whitespace().collect();
+        if parts.len() < 6 {
+            panic!("Invalid FEN string: not enough parts");
+        }
+
+        *self = Self { chess960: self.chess960, ..Self::new_empty() };
+        let ranks: Vec<&str> = parts[0].split('/').collect();
+        if ranks.len() != 8 {
+            panic!("FEN must have 8 ranks");
+        }
+        for (rank_idx, rank_str) in ranks.iter().enumerate() {
+            let mut file = 0;
+            for c in rank_str.chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    file += digit as u8;
+                } else {
+                    if let Some(piece) = Piece::from_fen_char(c) {
+                        self.restore(piece, Square((7 - rank_idx as u8) * 8 + file));
+                    }
+                    file += 1;
+                }
+            }
+        }
+
+        self.side_to_move = match parts[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => panic!("Invalid FEN side to move"),
+        };
+        (self.castling_rights, self.castling_rooks) = chess960::parse_castling_field(self, parts[2], DEFAULT_CASTLING_ROOKS);
+        self.castling_masks = chess960::castling_masks(self.castling_rooks, self.kings);
+        self.en_passant_square = Square::from_notation(parts[3]);
+        self.halfmove_clock = parts[4].parse().unwrap_or(0);
+        self.fullmove_number = parts[5].parse().unwrap_or(1);
+        self.hash = polyglot_key(self);
+        self.scores = Scores::compute(self);
+    }
+
+    fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty_squares = 0;
+            for file in 0..8 {
+                match self.squares[rank * 8 + file] {
+                    Some(piece) => {
+                        if empty_squares > 0 {
+                            fen.push_str(&empty_squares.to_string());
+                            empty_squares = 0;
+                        }
+                        fen.push(piece.to_fen_char());
+                    }
+                    None => empty_squares += 1,
+                }
+            }
+            if empty_squares > 0 {
+                fen.push_str(&empty_squares.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        let castling = chess960::castling_field(self, self.castling_rights, self.castling_rooks, false);
+        format!(
+            "{} {} {} {} {} {}",
+            fen,
+            if self.side_to_move == Color::White { 'w' } else { 'b' },
+            if castling.is_empty() { "-".to_string() } else { castling },
+            self.en_passant_square.map_or("-".to_string(), |sq| sq.to_notation()),
+            self.halfmove_clock,
+            self.fullmove_number,
+        )
+    }
+
+    fn to_bitboard(&self) -> Cow<'_, BitboardBoard> {
+        let mut board = BitboardBoard::new_empty(None);
+        board.load_fen(&self.to_fen());
+        board.chess960 = self.chess960;
+        Cow::Owned(board)
+    }
+}
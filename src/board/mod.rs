@@ -1,22 +1,140 @@
+pub mod attacks;
 pub mod bitboard;
+pub mod chess960;
+pub mod epd;
+pub mod mailbox;
+pub mod packed;
+pub mod san;
 pub mod types;
 
-use types::{Piece, Square, Move};
+use std::borrow::Cow;
+use types::{Color, CompactMove, Piece, Square, Move};
+use crate::evaluation::pst::Scores;
+use crate::move_generation::{GenerationMode, MoveList};
 
-pub trait Board {
+pub trait Board: Clone {
     fn piece_at(&self, square: Square) -> Option<Piece>;
     fn get_all_pieces(&self) -> Vec<(Square, Piece)>;
 
-    fn generate_moves(&self) -> Vec<Move>;
+    fn generate_moves(&self) -> MoveList;
+    /// Legal moves of one generation mode, filtered with make/unmake on this board.
+    fn generate_legal_moves_of(&mut self, mode: GenerationMode) -> MoveList;
     fn make_move(&mut self, mv: &Move);
     fn unmake_move(&mut self, mv: &Move);
+    /// Passes the turn. Refused, leaving the board untouched, when the side to move is in check.
+    fn make_null_move(&mut self) -> bool;
+    fn unmake_null_move(&mut self);
 
+    fn is_in_check(&self, color: Color) -> bool;
+    /// Whether any piece of `by` attacks `square`.
+    fn is_square_attacked(&self, square: Square, by: Color) -> bool;
+    /// Whether a move of the side to move checks the enemy king, directly or by discovery.
+    fn gives_check(&mut self, mv: &Move) -> bool;
     fn hash(&self) -> u64;
+    /// Material and piece-square totals, kept up to date by make/unmake.
+    fn scores(&self) -> &Scores;
 
     fn side_to_move(&self) -> Color;
+    fn castling_rights(&self) -> u8;
+    fn en_passant_square(&self) -> Option<Square>;
+    fn halfmove_clock(&self) -> u8;
+    fn fullmove_number(&self) -> u16;
+    fn is_chess960(&self) -> bool;
 
+    /// Sets up a FEN position. Panics on a malformed FEN, see `try_load_fen` for untrusted input.
     fn load_fen(&mut self, fen: &str);
+    fn to_fen(&self) -> String;
+    /// The position as a `BitboardBoard`, for the opening book and tablebases.
+    fn to_bitboard(&self) -> Cow<'_, BitboardBoard>;
+
+    /// Sets up a FEN position after checking it, leaving the board untouched when it is malformed.
+    fn try_load_fen(&mut self, fen: &str) -> Result<(), String> {
+        check_fen(fen)?;
+        self.load_fen(fen);
+        Ok(())
+    }
+
+    /// Whether a pseudo-legal move leaves the mover's king out of check.
+    fn keeps_king_safe(&mut self, mv: &Move) -> bool {
+        let side = self.side_to_move();
+        // the king may not start in, pass through or land on an attacked square
+        if mv.castling.is_some() {
+            let (start, end) = (mv.from.0.min(mv.to.0), mv.from.0.max(mv.to.0));
+            if (start..=end).any(|i| self.is_square_attacked(Square(i), side.opposite())) {
+                return false;
+            }
+        }
+        self.make_move(mv);
+        let in_check = self.is_in_check(side);
+        self.unmake_move(mv);
+        !in_check
+    }
+
+    /// Packs a move generated in this position, which is needed to recognise en passant.
+    fn compact_move(&self, mv: &Move) -> CompactMove {
+        CompactMove::from_move(mv, self.en_passant_square())
+    }
+
+    /// Unpacks a compact move for this position. The move is not checked for legality, only
+    /// `None` is returned when there is no piece to move.
+    fn expand_move(&self, mv: CompactMove) -> Option<Move> {
+        mv.expand(|square| self.piece_at(square))
+    }
+
+    /// UCI notation for a move, king takes rook castling when playing Chess960.
+    fn move_to_uci(&self, mv: &Move) -> String {
+        if self.is_chess960() { mv.to_uci_960() } else { mv.to_uci() }
+    }
+}
+
+/// Checks that `load_fen` can set up a FEN: six fields, eight full ranks with one king a side,
+/// and well formed side to move, castling, en passant and counter fields.
+pub fn check_fen(fen: &str) -> Result<(), String> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 6 {
+        return Err(format!("expected 6 fields, found {}", fields.len()));
+    }
+
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(format!("expected 8 ranks, found {}", ranks.len()));
+    }
+    for rank in &ranks {
+        let mut squares = 0;
+        for c in rank.chars() {
+            squares += match c {
+                '1'..='8' => c as u32 - '0' as u32,
+                _ if Piece::from_fen_char(c).is_some() => 1,
+                _ => return Err(format!("unknown piece '{}'", c)),
+            };
+        }
+        if squares != 8 {
+            return Err(format!("rank '{}' has {} squares", rank, squares));
+        }
+    }
+    for king in ['K', 'k'] {
+        if fields[0].matches(king).count() != 1 {
+            return Err(format!("expected one '{}'", king));
+        }
+    }
+
+    if !matches!(fields[1], "w" | "b") {
+        return Err(format!("bad side to move '{}'", fields[1]));
+    }
+    if fields[2] != "-" && !fields[2].chars().all(|c| matches!(c.to_ascii_lowercase(), 'a'..='h' | 'k' | 'q')) {
+        return Err(format!("bad castling field '{}'", fields[2]));
+    }
+    // the square behind a pawn the side to move can take
+    let en_passant_rank = if fields[1] == "w" { b'6' } else { b'3' };
+    let en_passant = fields[3].as_bytes();
+    if fields[3] != "-" && !(en_passant.len() == 2 && (b'a'..=b'h').contains(&en_passant[0]) && en_passant[1] == en_passant_rank) {
+        return Err(format!("bad en passant square '{}'", fields[3]));
+    }
+    if fields[4].parse::<u8>().is_err() || fields[5].parse::<u16>().is_err() {
+        return Err(format!("bad move counters '{} {}'", fields[4], fields[5]));
+    }
+    Ok(())
 }
 
 pub use bitboard::BitboardBoard;
-use crate::board::types::Color;
+pub use mailbox::MailboxBoard;
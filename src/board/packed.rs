@@ -0,0 +1,139 @@
+use super::{BitboardBoard, Board};
+use super::bitboard::CASTLING_RIGHTS;
+use super::types::{Color, Piece, PieceKind, Square};
+
+pub const PACKED_BOARD_SIZE: usize = 28;
+
+/// Nibble for the pawn that can be captured en passant, it belongs to the side not to move.
+const EN_PASSANT_PAWN: u8 = 12;
+/// Nibble for a rook that may still castle, white on the first rank and black on the eighth.
+const CASTLING_ROOK: u8 = 13;
+
+const BLACK_TO_MOVE: u8 = 1;
+const CHESS960: u8 = 2;
+
+const KINDS: [PieceKind; 6] = [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King];
+
+/// Fixed size binary encoding of a position:
+///
+/// | bytes  | content                                                      |
+/// |--------|--------------------------------------------------------------|
+/// | 0..8   | occupancy bitboard, little endian                            |
+/// | 8..24  | one nibble per occupied square from a1 up, low nibble first |
+/// | 24     | state: bit 0 black to move, bit 1 Chess960                   |
+/// | 25     | halfmove clock                                               |
+/// | 26..28 | fullmove number, little endian                               |
+///
+/// Nibbles 0..6 are the white pieces from pawn to king and 6..12 the black ones. Castling
+/// rights and the en passant square are carried by the pieces themselves, see `CASTLING_ROOK`
+/// and `EN_PASSANT_PAWN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedBoard(pub [u8; PACKED_BOARD_SIZE]);
+
+impl PackedBoard {
+    pub fn pack(board: &BitboardBoard) -> Self {
+        let mut bytes = [0u8; PACKED_BOARD_SIZE];
+        let occupied = board.get_all_pieces_mask();
+        bytes[0..8].copy_from_slice(&occupied.to_le_bytes());
+
+        let en_passant_pawn = board.en_passant_square.map(|sq| {
+            if board.side_to_move() == Color::White { sq.0 - 8 } else { sq.0 + 8 }
+        });
+        let castling_rooks: Vec<Square> = CASTLING_RIGHTS.iter()
+            .filter(|&&right| board.castling_rights & right != 0)
+            .map(|&right| board.castling_rook(right))
+            .collect();
+
+        let mut bits = occupied;
+        let mut index = 0;
+        while bits != 0 {
+            let square = Square(bits.trailing_zeros() as u8);
+            bits &= bits - 1;
+            let piece = board.piece_at(square).unwrap();
+            let code = if Some(square.0) == en_passant_pawn && piece.kind == PieceKind::Pawn {
+                EN_PASSANT_PAWN
+            } else if piece.kind == PieceKind::Rook && castling_rooks.contains(&square) {
+                CASTLING_ROOK
+            } else {
+                piece.kind as u8 + if piece.color == Color::White { 0 } else { 6 }
+            };
+            bytes[8 + index / 2] |= code << (4 * (index % 2));
+            index += 1;
+        }
+
+        let mut state = 0;
+        if board.side_to_move() == Color::Black {
+            state |= BLACK_TO_MOVE;
+        }
+        if board.chess960 {
+            state |= CHESS960;
+        }
+        bytes[24] = state;
+        bytes[25] = board.halfmove_clock;
+        bytes[26..28].copy_from_slice(&board.fullmove_number.to_le_bytes());
+        Self(bytes)
+    }
+
+    /// The encoded position, `None` when the bytes cannot describe one: unknown codes, an en
+    /// passant pawn off its double push rank, pawns on the back ranks or not one king a side.
+    pub fn unpack(&self) -> Option<BitboardBoard> {
+        let bytes = &self.0;
+        let occupied = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        if occupied.count_ones() > 32 {
+            return None;
+        }
+        let side_to_move = if bytes[24] & BLACK_TO_MOVE != 0 { Color::Black } else { Color::White };
+
+        let mut pieces = Vec::with_capacity(occupied.count_ones() as usize);
+        let mut castling_rooks = Vec::new();
+        let mut en_passant_square = None;
+        let mut bits = occupied;
+        let mut index = 0;
+        while bits != 0 {
+            let square = Square(bits.trailing_zeros() as u8);
+            bits &= bits - 1;
+            let code = (bytes[8 + index / 2] >> (4 * (index % 2))) & 0xF;
+            index += 1;
+            let piece = match code {
+                0..=11 => Piece { color: if code < 6 { Color::White } else { Color::Black }, kind: KINDS[code as usize % 6] },
+                EN_PASSANT_PAWN => {
+                    let color = side_to_move.opposite();
+                    // only a pawn that just moved two squares, and only one of them
+                    let rank = if color == Color::White { 3 } else { 4 };
+                    if square.0 / 8 != rank || en_passant_square.is_some() {
+                        return None;
+                    }
+                    // the square the pawn skipped, behind it
+                    en_passant_square = Some(if color == Color::White { Square(square.0 - 8) } else { Square(square.0 + 8) });
+                    Piece { color, kind: PieceKind::Pawn }
+                }
+                CASTLING_ROOK => {
+                    let color = match square.0 / 8 {
+                        0 => Color::White,
+                        7 => Color::Black,
+                        _ => return None,
+                    };
+                    castling_rooks.push(square);
+                    Piece { color, kind: PieceKind::Rook }
+                }
+                _ => return None,
+            };
+            if piece.kind == PieceKind::Pawn && (square.0 / 8 == 0 || square.0 / 8 == 7) {
+                return None;
+            }
+            pieces.push((square, piece));
+        }
+        for color in [Color::White, Color::Black] {
+            let kings = pieces.iter().filter(|(_, p)| *p == Piece { color, kind: PieceKind::King }).count();
+            if kings != 1 {
+                return None;
+            }
+        }
+
+        let mut board = BitboardBoard::new_empty(None);
+        board.chess960 = bytes[24] & CHESS960 != 0;
+        let fullmove_number = u16::from_le_bytes([bytes[26], bytes[27]]);
+        board.set_position(&pieces, side_to_move, &castling_rooks, en_passant_square, bytes[25], fullmove_number);
+        Some(board)
+    }
+}
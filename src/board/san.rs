@@ -0,0 +1,132 @@
+use super::Board;
+use super::types::{Move, PieceKind, Square};
+use crate::move_generation::MoveList;
+
+fn piece_letter(kind: PieceKind) -> &'static str {
+    match kind {
+        PieceKind::Pawn => "",
+        PieceKind::Knight => "N",
+        PieceKind::Bishop => "B",
+        PieceKind::Rook => "R",
+        PieceKind::Queen => "Q",
+        PieceKind::King => "K",
+    }
+}
+
+fn letter_piece(c: char) -> Option<PieceKind> {
+    match c {
+        'N' => Some(PieceKind::Knight),
+        'B' => Some(PieceKind::Bishop),
+        'R' => Some(PieceKind::Rook),
+        'Q' => Some(PieceKind::Queen),
+        'K' => Some(PieceKind::King),
+        _ => None,
+    }
+}
+
+fn is_kingside(mv: &Move) -> bool {
+    mv.to.0 % 8 == 6
+}
+
+/// Standard algebraic notation for a legal move, with `+` or `#` when it gives check or mate.
+pub fn move_to_san<B: Board>(board: &mut B, mv: &Move) -> String {
+    let mut san = if mv.castling.is_some() {
+        if is_kingside(mv) { "O-O".to_string() } else { "O-O-O".to_string() }
+    } else {
+        let mut san = piece_letter(mv.piece.kind).to_string();
+        if mv.piece.kind == PieceKind::Pawn {
+            if mv.captures.is_some() {
+                san.push((b'a' + mv.from.0 % 8) as char);
+            }
+        } else {
+            // only as much of the origin as needed to tell same-piece moves apart
+            let others: Vec<Move> = board.generate_moves().into_iter()
+                .filter(|m| m.piece == mv.piece && m.to == mv.to && m.from != mv.from && m.castling.is_none())
+                .collect();
+            if !others.is_empty() {
+                let from = mv.from.to_notation();
+                if others.iter().all(|m| m.from.0 % 8 != mv.from.0 % 8) {
+                    san.push_str(&from[..1]);
+                } else if others.iter().all(|m| m.from.0 / 8 != mv.from.0 / 8) {
+                    san.push_str(&from[1..]);
+                } else {
+                    san.push_str(&from);
+                }
+            }
+        }
+        if mv.captures.is_some() {
+            san.push('x');
+        }
+        san.push_str(&mv.to.to_notation());
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push_str(piece_letter(promotion));
+        }
+        san
+    };
+
+    board.make_move(mv);
+    if board.is_in_check(board.side_to_move()) {
+        san.push(if board.generate_moves().is_empty() { '#' } else { '+' });
+    }
+    board.unmake_move(mv);
+    san
+}
+
+/// Finds the legal move written in algebraic notation. Check and annotation suffixes, missing
+/// or superfluous capture marks and extra disambiguation are accepted, and so is UCI notation.
+pub fn parse_san<B: Board>(board: &B, san: &str) -> Option<Move> {
+    let moves = board.generate_moves();
+    let text = san.trim().trim_end_matches("e.p.").trim_end().trim_end_matches(['+', '#', '!', '?']);
+
+    match text {
+        "O-O" | "0-0" => return moves.into_iter().find(|m| m.castling.is_some() && is_kingside(m)),
+        "O-O-O" | "0-0-0" => return moves.into_iter().find(|m| m.castling.is_some() && !is_kingside(m)),
+        _ => {}
+    }
+
+    parse_algebraic(&moves, text).or_else(|| moves.into_iter().find(|m| board.move_to_uci(m) == text))
+}
+
+fn parse_algebraic(moves: &MoveList, text: &str) -> Option<Move> {
+    let mut chars: Vec<char> = text.chars().filter(|&c| c != 'x' && c != '-' && c != '=').collect();
+    let promotion = match chars.last() {
+        Some(&c) if chars.len() > 2 => letter_piece(c).inspect(|_| { chars.pop(); }),
+        _ => None,
+    };
+    let kind = match chars.first().and_then(|&c| letter_piece(c)) {
+        Some(kind) => {
+            chars.remove(0);
+            kind
+        }
+        None => PieceKind::Pawn,
+    };
+    if chars.len() < 2 {
+        return None;
+    }
+    let to = chars.split_off(chars.len() - 2);
+    if !matches!((to[0], to[1]), ('a'..='h', '1'..='8')) {
+        return None;
+    }
+    let to = Square::from_coords(to[0] as u8 - b'a', to[1] as u8 - b'1');
+    let mut file = None;
+    let mut rank = None;
+    for c in chars {
+        match c {
+            'a'..='h' => file = Some(c as u8 - b'a'),
+            '1'..='8' => rank = Some(c as u8 - b'1'),
+            _ => return None,
+        }
+    }
+
+    let mut candidates = moves.iter().filter(|m| {
+        m.castling.is_none()
+            && m.piece.kind == kind
+            && m.to == to
+            && m.promotion == promotion
+            && file.is_none_or(|f| m.from.0 % 8 == f)
+            && rank.is_none_or(|r| m.from.0 / 8 == r)
+    });
+    let mv = candidates.next()?;
+    candidates.next().is_none().then_some(mv)
+}
@@ -20,6 +20,28 @@ pub struct Piece {
     pub kind: PieceKind,
 }
 
+impl Piece {
+    /// FEN letter, upper case for White.
+    pub fn to_fen_char(&self) -> char {
+        let c = promotion_char(self.kind);
+        if self.color == Color::White { c.to_ascii_uppercase() } else { c }
+    }
+
+    pub fn from_fen_char(c: char) -> Option<Self> {
+        let kind = match c.to_ascii_lowercase() {
+            'p' => PieceKind::Pawn,
+            'n' => PieceKind::Knight,
+            'b' => PieceKind::Bishop,
+            'r' => PieceKind::Rook,
+            'q' => PieceKind::Queen,
+            'k' => PieceKind::King,
+            _ => return None,
+        };
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        Some(Self { color, kind })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Square(pub u8); // 0..63 (A1 = 0, H8 = 63)
 
@@ -33,8 +55,8 @@ impl Square {
     }
 
     pub fn to_notation(&self) -> String {
-        let file = (self.0 % 8) as u8 + b'a';
-        let rank = (self.0 / 8) as u8 + b'1';
+        let file = (self.0 % 8) + b'a';
+        let rank = (self.0 / 8) + b'1';
         format!("{}{}", file as char, rank as char)
     }
 
@@ -58,6 +80,8 @@ pub struct Move {
     pub piece: Piece,
     pub promotion: Option<PieceKind>,
     pub captures: Option<Piece>,
+    /// Starting square of the rook when the move castles, `to` is then the king's destination.
+    pub castling: Option<Square>,
 }
 
 impl Move {
@@ -68,13 +92,158 @@ impl Move {
             piece,
             promotion,
             captures,
+            castling: None,
+        }
+    }
+
+    pub fn to_uci(&self) -> String {
+        let promotion = self.promotion.map(promotion_char).map(String::from).unwrap_or_default();
+        format!("{}{}{}", self.from.to_notation(), self.to.to_notation(), promotion)
+    }
+
+    /// Where the rook ends when this move castles: next to the king on the f or d file.
+    pub fn castling_rook_destination(&self) -> Square {
+        if self.to.0 % 8 == 6 { Square(self.to.0 - 1) } else { Square(self.to.0 + 1) }
+    }
+
+    /// UCI notation for Chess960, where castling is written as the king taking its own rook.
+    pub fn to_uci_960(&self) -> String {
+        match self.castling {
+            Some(rook) => format!("{}{}", self.from.to_notation(), rook.to_notation()),
+            None => self.to_uci(),
         }
     }
 }
 
+/// Lower case letter of a piece kind, written after the squares of a promoting move in UCI
+/// notation and used for Black's pieces in FEN.
+pub fn promotion_char(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn => 'p',
+        PieceKind::Knight => 'n',
+        PieceKind::Bishop => 'b',
+        PieceKind::Rook => 'r',
+        PieceKind::Queen => 'q',
+        PieceKind::King => 'k',
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GameState {
     InProgress,
     Checkmate,
     Stalemate,
 }
+
+/// A move packed into 16 bits: from (6 bits), to (6 bits) and a 4 bit flag. Castling stores
+/// the rook's square as `to`, like Chess960 UCI notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CompactMove(pub u16);
+
+impl CompactMove {
+    pub const NONE: CompactMove = CompactMove(0);
+
+    pub const QUIET: u16 = 0;
+    pub const DOUBLE_PUSH: u16 = 1;
+    pub const KING_CASTLE: u16 = 2;
+    pub const QUEEN_CASTLE: u16 = 3;
+    pub const CAPTURE: u16 = 4;
+    pub const EN_PASSANT: u16 = 5;
+    /// Promotion flags are `PROMOTION | piece`, with knight, bishop, rook, queen as 0..4.
+    pub const PROMOTION: u16 = 8;
+
+    pub fn new(from: Square, to: Square, flag: u16) -> Self {
+        Self((flag << 12) | ((from.0 as u16) << 6) | to.0 as u16)
+    }
+
+    /// Packs a move, `en_passant` being the en passant square of the position it was
+    /// generated in.
+    pub fn from_move(mv: &Move, en_passant: Option<Square>) -> Self {
+        if let Some(rook) = mv.castling {
+            let flag = if mv.to.0 % 8 == 6 { Self::KING_CASTLE } else { Self::QUEEN_CASTLE };
+            return Self::new(mv.from, rook, flag);
+        }
+        let flag = if let Some(kind) = mv.promotion {
+            let piece = match kind {
+                PieceKind::Bishop => 1,
+                PieceKind::Rook => 2,
+                PieceKind::Queen => 3,
+                _ => 0,
+            };
+            Self::PROMOTION | piece | if mv.captures.is_some() { Self::CAPTURE } else { 0 }
+        } else if mv.piece.kind == PieceKind::Pawn && en_passant == Some(mv.to) {
+            Self::EN_PASSANT
+        } else if mv.captures.is_some() {
+            Self::CAPTURE
+        } else if mv.piece.kind == PieceKind::Pawn && mv.from.0.abs_diff(mv.to.0) == 16 {
+            Self::DOUBLE_PUSH
+        } else {
+            Self::QUIET
+        };
+        Self::new(mv.from, mv.to, flag)
+    }
+
+    /// Unpacks the move with `piece_at` telling what stands on a square of its position,
+    /// `None` when there is no piece to move.
+    pub fn expand(&self, piece_at: impl Fn(Square) -> Option<Piece>) -> Option<Move> {
+        let piece = piece_at(self.from())?;
+        if self.is_castling() {
+            return Some(Move { castling: Some(self.to()), ..Move::new(self.from(), self.king_destination(), piece, None, None) });
+        }
+        let captures = if self.is_en_passant() {
+            Some(Piece { color: piece.color.opposite(), kind: PieceKind::Pawn })
+        } else if self.is_capture() {
+            piece_at(self.to())
+        } else {
+            None
+        };
+        Some(Move::new(self.from(), self.to(), piece, self.promotion(), captures))
+    }
+
+    pub fn from(&self) -> Square {
+        Square(((self.0 >> 6) & 0x3F) as u8)
+    }
+
+    pub fn to(&self) -> Square {
+        Square((self.0 & 0x3F) as u8)
+    }
+
+    pub fn flag(&self) -> u16 {
+        self.0 >> 12
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.flag() & Self::CAPTURE != 0
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.flag() == Self::EN_PASSANT
+    }
+
+    pub fn is_double_push(&self) -> bool {
+        self.flag() == Self::DOUBLE_PUSH
+    }
+
+    pub fn is_castling(&self) -> bool {
+        self.flag() == Self::KING_CASTLE || self.flag() == Self::QUEEN_CASTLE
+    }
+
+    pub fn promotion(&self) -> Option<PieceKind> {
+        if self.flag() & Self::PROMOTION == 0 {
+            return None;
+        }
+        Some([PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen][(self.flag() & 3) as usize])
+    }
+
+    /// Destination of the king when castling, on the g or c file.
+    pub fn king_destination(&self) -> Square {
+        let base = self.from().0 - self.from().0 % 8;
+        if self.flag() == Self::KING_CASTLE { Square(base + 6) } else { Square(base + 2) }
+    }
+
+    pub fn to_uci(&self, chess960: bool) -> String {
+        let to = if self.is_castling() && !chess960 { self.king_destination() } else { self.to() };
+        let promotion = self.promotion().map(promotion_char).map(String::from).unwrap_or_default();
+        format!("{}{}{}", self.from().to_notation(), to.to_notation(), promotion)
+    }
+}
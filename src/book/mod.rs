@@ -0,0 +1,4 @@
+pub mod polyglot;
+pub mod random;
+
+pub use polyglot::{polyglot_key, BookEntry, BookSelection, PolyglotBook};
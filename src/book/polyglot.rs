@@ -0,0 +1,202 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::board::{BitboardBoard, Board};
+use crate::board::bitboard::{CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE, CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE};
+use crate::board::types::{Color, Move, Piece, PieceKind, Square};
+use crate::move_generation::MoveList;
+use crate::utils::random::Random;
+use super::random::POLYGLOT_RANDOM;
+
+const ENTRY_SIZE: usize = 16;
+const RANDOM_CASTLE: usize = 768;
+const RANDOM_EN_PASSANT: usize = 772;
+const RANDOM_TURN: usize = 780;
+
+/// A single 16 byte book record, stored big-endian on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSelection {
+    BestOnly,
+    WeightedRandom,
+}
+
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+}
+
+impl PolyglotBook {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                raw_move: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect();
+        Self::from_entries(entries)
+    }
+
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        // books are sorted by key already, but lookups rely on it so don't trust the file
+        entries.sort_by_key(|e| e.key);
+        Self { entries }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.raw_move.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries_for(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = start + self.entries[start..].partition_point(|e| e.key == key);
+        &self.entries[start..end]
+    }
+
+    /// Legal book moves for the position together with their weights, in book order.
+    pub fn book_moves(&self, board: &BitboardBoard) -> Vec<(Move, u16)> {
+        let entries = self.entries_for(polyglot_key(board));
+        if entries.is_empty() {
+            return Vec::new();
+        }
+
+        let legal_moves = board.generate_moves();
+        entries
+            .iter()
+            .filter_map(|e| decode_move(e.raw_move, &legal_moves).map(|mv| (mv, e.weight)))
+            .collect()
+    }
+
+    pub fn choose_move(&self, board: &BitboardBoard, selection: BookSelection, rng: &mut Random) -> Option<Move> {
+        let moves = self.book_moves(board);
+        match selection {
+            BookSelection::BestOnly => moves.iter().max_by_key(|(_, w)| *w).map(|(mv, _)| *mv),
+            BookSelection::WeightedRandom => {
+                let total: u64 = moves.iter().map(|(_, w)| *w as u64).sum();
+                if total == 0 {
+                    return moves.first().map(|(mv, _)| *mv);
+                }
+                let mut pick = rng.next_below(total);
+                for (mv, weight) in &moves {
+                    if pick < *weight as u64 {
+                        return Some(*mv);
+                    }
+                    pick -= *weight as u64;
+                }
+                None
+            }
+        }
+    }
+}
+
+pub fn polyglot_key<B: Board>(board: &B) -> u64 {
+    let mut key = 0u64;
+    for (square, piece) in board.get_all_pieces() {
+        key ^= piece_key(piece, square);
+    }
+    key ^ castling_key(board.castling_rights()) ^ en_passant_key(board) ^ turn_key(board.side_to_move())
+}
+
+pub fn piece_key(piece: Piece, square: Square) -> u64 {
+    // polyglot interleaves colours: black pawn, white pawn, black knight, ...
+    let kind_index = match piece.kind {
+        PieceKind::Pawn => 0,
+        PieceKind::Knight => 1,
+        PieceKind::Bishop => 2,
+        PieceKind::Rook => 3,
+        PieceKind::Queen => 4,
+        PieceKind::King => 5,
+    };
+    let piece_index = kind_index * 2 + if piece.color == Color::White { 1 } else { 0 };
+    POLYGLOT_RANDOM[64 * piece_index + square.0 as usize]
+}
+
+pub fn castling_key(castling_rights: u8) -> u64 {
+    let castling = [CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE, CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE];
+    let mut key = 0u64;
+    for (i, right) in castling.iter().enumerate() {
+        if castling_rights & right != 0 {
+            key ^= POLYGLOT_RANDOM[RANDOM_CASTLE + i];
+        }
+    }
+    key
+}
+
+/// The en passant file only counts when a pawn of the side to move can actually capture there.
+pub fn en_passant_key<B: Board>(board: &B) -> u64 {
+    let Some(ep_square) = board.en_passant_square() else {
+        return 0;
+    };
+    let side = board.side_to_move();
+    let pawn_rank = if side == Color::White { 4 } else { 3 };
+    let pawn = Some(Piece { color: side, kind: PieceKind::Pawn });
+    let file = ep_square.0 % 8;
+    let adjacent = [file.checked_sub(1), (file < 7).then_some(file + 1)];
+    if adjacent.iter().flatten().any(|f| board.piece_at(Square(pawn_rank * 8 + f)) == pawn) {
+        POLYGLOT_RANDOM[RANDOM_EN_PASSANT + file as usize]
+    } else {
+        0
+    }
+}
+
+pub fn turn_key(side_to_move: Color) -> u64 {
+    if side_to_move == Color::White { POLYGLOT_RANDOM[RANDOM_TURN] } else { 0 }
+}
+
+/// Matches a polyglot move against the legal moves. Castling is stored as king takes rook.
+fn decode_move(raw_move: u16, legal_moves: &MoveList) -> Option<Move> {
+    let to = Square((raw_move & 0x3F) as u8);
+    let from = Square(((raw_move >> 6) & 0x3F) as u8);
+    let promotion = match (raw_move >> 12) & 0x7 {
+        1 => Some(PieceKind::Knight),
+        2 => Some(PieceKind::Bishop),
+        3 => Some(PieceKind::Rook),
+        4 => Some(PieceKind::Queen),
+        _ => None,
+    };
+
+    legal_moves.iter().find(|mv| {
+        let target = mv.castling.unwrap_or(mv.to);
+        mv.from == from && target == to && mv.promotion == promotion
+    })
+}
+
+pub fn encode_move(mv: &Move) -> u16 {
+    let to = mv.castling.unwrap_or(mv.to).0;
+    let promotion = match mv.promotion {
+        Some(PieceKind::Knight) => 1,
+        Some(PieceKind::Bishop) => 2,
+        Some(PieceKind::Rook) => 3,
+        Some(PieceKind::Queen) => 4,
+        _ => 0,
+    };
+    (promotion << 12) | ((mv.from.0 as u16) << 6) | to as u16
+}
@@ -0,0 +1,186 @@
+use crate::board::Board;
+use crate::board::types::{Color, Piece, PieceKind, Square};
+
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+pub const KING_VALUE: i32 = 20000;
+
+const PAWN_PST: [i32; 64] = [
+    0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0
+];
+
+const KNIGHT_PST: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+const BISHOP_PST: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+const ROOK_PST: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0
+];
+
+const QUEEN_PST: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20
+];
+
+const KING_PST: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20
+];
+
+const KING_ENDGAME_PST: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50
+];
+
+/// Game phase with all minor and major pieces on the board.
+pub const MAX_PHASE: i32 = 24;
+
+pub fn piece_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => PAWN_VALUE,
+        PieceKind::Knight => KNIGHT_VALUE,
+        PieceKind::Bishop => BISHOP_VALUE,
+        PieceKind::Rook => ROOK_VALUE,
+        PieceKind::Queen => QUEEN_VALUE,
+        PieceKind::King => KING_VALUE,
+    }
+}
+
+fn tables(kind: PieceKind) -> (&'static [i32; 64], &'static [i32; 64]) {
+    match kind {
+        PieceKind::Pawn => (&PAWN_PST, &PAWN_PST),
+        PieceKind::Knight => (&KNIGHT_PST, &KNIGHT_PST),
+        PieceKind::Bishop => (&BISHOP_PST, &BISHOP_PST),
+        PieceKind::Rook => (&ROOK_PST, &ROOK_PST),
+        PieceKind::Queen => (&QUEEN_PST, &QUEEN_PST),
+        PieceKind::King => (&KING_PST, &KING_ENDGAME_PST),
+    }
+}
+
+fn piece_index(piece: Piece) -> usize {
+    piece.kind as usize + if piece.color == Color::White { 0 } else { 6 }
+}
+
+/// Material and piece-square totals from White's point of view, kept up to date by the board
+/// as pieces come and go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Scores {
+    pub material: i32,
+    pub middlegame: i32,
+    pub endgame: i32,
+    /// Indexed like the board's bitboards.
+    pub piece_counts: [u8; 12],
+}
+
+impl Scores {
+    pub fn compute<B: Board>(board: &B) -> Self {
+        let mut scores = Self::default();
+        for (square, piece) in board.get_all_pieces() {
+            scores.add(piece, square);
+        }
+        scores
+    }
+
+    pub fn add(&mut self, piece: Piece, square: Square) {
+        self.update(piece, square, 1);
+        self.piece_counts[piece_index(piece)] += 1;
+    }
+
+    pub fn remove(&mut self, piece: Piece, square: Square) {
+        self.update(piece, square, -1);
+        self.piece_counts[piece_index(piece)] -= 1;
+    }
+
+    fn update(&mut self, piece: Piece, square: Square, sign: i32) {
+        let (middlegame, endgame) = tables(piece.kind);
+        // the tables are written from White's side with rank 8 on top, so White reads them
+        // flipped vertically and Black as they are
+        let (sign, sq) = match piece.color {
+            Color::White => (sign, square.0 as usize ^ 56),
+            Color::Black => (-sign, square.0 as usize),
+        };
+        self.material += sign * piece_value(piece.kind);
+        self.middlegame += sign * middlegame[sq];
+        self.endgame += sign * endgame[sq];
+    }
+
+    pub fn piece_count(&self, piece: Piece) -> u8 {
+        self.piece_counts[piece_index(piece)]
+    }
+
+    fn count_both(&self, kind: PieceKind) -> u8 {
+        self.piece_count(Piece { color: Color::White, kind }) + self.piece_count(Piece { color: Color::Black, kind })
+    }
+
+    /// Neither side can mate: no pawns, rooks or queens and at most one minor piece on the board.
+    pub fn insufficient_material(&self) -> bool {
+        let heavy = [PieceKind::Pawn, PieceKind::Rook, PieceKind::Queen].iter().any(|&kind| self.count_both(kind) != 0);
+        !heavy && self.count_both(PieceKind::Knight) + self.count_both(PieceKind::Bishop) <= 1
+    }
+
+    /// `MAX_PHASE` in the opening down to 0 once only kings and pawns are left.
+    pub fn phase(&self) -> i32 {
+        let count = |kind: PieceKind| self.count_both(kind) as i32;
+        let phase = count(PieceKind::Knight) + count(PieceKind::Bishop) + 2 * count(PieceKind::Rook) + 4 * count(PieceKind::Queen);
+        phase.min(MAX_PHASE)
+    }
+
+    /// Piece-square total blended between the middlegame and endgame tables by phase.
+    pub fn positional(&self) -> i32 {
+        let phase = self.phase();
+        (self.middlegame * phase + self.endgame * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
@@ -1,25 +1,110 @@
+pub mod stream;
+
 use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
 use crate::board::{BitboardBoard, Board};
 use crate::board::types::{Move, Piece, Square, GameState};
+use crate::book::{BookSelection, PolyglotBook};
 use crate::evaluation::{Evaluator, SimpleEvaluator};
-use crate::search::{Searcher, AlphaBetaSearcher};
+use crate::search::{Searcher, AlphaBetaSearcher, SearchLine};
+use crate::tablebase::SyzygyTablebase;
+use crate::utils::random::Random;
+
+pub const DEFAULT_BOOK_DEPTH: u16 = 12;
 
-pub struct GameManager {
-    board: BitboardBoard,
-    evaluator: Box<dyn Evaluator>,
-    searcher: Box<dyn Searcher>,
+pub struct GameManager<B: Board = BitboardBoard> {
+    board: B,
+    evaluator: Box<dyn Evaluator<B>>,
+    searcher: Box<dyn Searcher<B>>,
+    multi_pv: usize,
+    book: Option<PolyglotBook>,
+    book_depth: u16,
+    book_selection: BookSelection,
+    rng: Random,
+    tablebase: Option<Arc<SyzygyTablebase>>,
+}
+
+impl Default for GameManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GameManager {
     pub fn new() -> Self {
+        Self::with_board(BitboardBoard::new_startpos())
+    }
+
+    /// Chess960 mode survives loading new positions.
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.board.chess960 = chess960;
+    }
+}
+
+impl<B: Board> GameManager<B> {
+    pub fn with_board(board: B) -> Self {
         Self {
-            board: BitboardBoard::new_startpos(),
+            board,
             evaluator: Box::new(SimpleEvaluator),
-            searcher: Box::new(AlphaBetaSearcher),
+            searcher: Box::new(AlphaBetaSearcher::default()),
+            multi_pv: 1,
+            book: None,
+            book_depth: DEFAULT_BOOK_DEPTH,
+            book_selection: BookSelection::WeightedRandom,
+            rng: Random::from_time(),
+            tablebase: None,
         }
     }
 
-    pub fn get_board(&self) -> &BitboardBoard {
+    pub fn set_searcher(&mut self, searcher: Box<dyn Searcher<B>>) {
+        self.searcher = searcher;
+    }
+
+    pub fn get_multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    pub fn load_book<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.book = Some(PolyglotBook::open(path)?);
+        Ok(())
+    }
+
+    pub fn set_book(&mut self, book: Option<PolyglotBook>) {
+        self.book = book;
+    }
+
+    /// The book is only consulted up to and including this full move number.
+    pub fn set_book_depth(&mut self, depth: u16) {
+        self.book_depth = depth;
+    }
+
+    pub fn set_book_selection(&mut self, selection: BookSelection) {
+        self.book_selection = selection;
+    }
+
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.rng = Random::new(seed);
+    }
+
+    /// Loads Syzygy tables from `dir` and hands them to the current searcher.
+    pub fn load_tablebase<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        let tablebase = Arc::new(SyzygyTablebase::open(dir)?);
+        self.tablebase = Some(tablebase);
+        self.searcher.set_tablebase(self.tablebase.clone());
+        Ok(())
+    }
+
+    pub fn get_tablebase(&self) -> Option<Arc<SyzygyTablebase>> {
+        self.tablebase.clone()
+    }
+
+    pub fn get_board(&self) -> &B {
         &self.board
     }
 
@@ -27,8 +112,18 @@ impl GameManager {
         self.board.to_fen()
     }
 
+    /// Sets up a new position, which the searcher starts on afresh.
     pub fn load_from_fen(&mut self, fen: &str) {
         self.board.load_fen(fen);
+        self.searcher.clear();
+    }
+
+    /// Like `load_from_fen` for untrusted input, keeping the current position when the FEN is
+    /// malformed.
+    pub fn try_load_from_fen(&mut self, fen: &str) -> Result<(), String> {
+        self.board.try_load_fen(fen)?;
+        self.searcher.clear();
+        Ok(())
     }
 
     pub fn get_evaluation(&self) -> i32 {
@@ -52,8 +147,18 @@ impl GameManager {
 
         match chosen_move {
             Some(mv) => {
-                self.board.make_move(mv);
-                self.board.switch_side();
+                self.board.make_move(&mv);
+                Ok(())
+            }
+            None => Err("Illegal move"),
+        }
+    }
+
+    pub fn make_move_from_uci(&mut self, uci: &str) -> Result<(), &'static str> {
+        let legal_moves = self.board.generate_moves();
+        match legal_moves.iter().find(|m| self.board.move_to_uci(m) == uci) {
+            Some(mv) => {
+                self.apply_move(&mv);
                 Ok(())
             }
             None => Err("Illegal move"),
@@ -62,7 +167,6 @@ impl GameManager {
 
     pub fn apply_move(&mut self, mv: &Move) {
         self.board.make_move(mv);
-        self.board.switch_side();
     }
 
     pub fn get_game_state(&self) -> GameState {
@@ -77,7 +181,34 @@ impl GameManager {
         GameState::InProgress
     }
 
-    pub fn find_best_move(&self) -> Option<Move> {
-        self.searcher.search(&self.board, self.evaluator.as_ref())
+    pub fn book_move(&mut self) -> Option<Move> {
+        let book = self.book.as_ref()?;
+        if self.board.fullmove_number() > self.book_depth {
+            return None;
+        }
+        book.choose_move(&self.board.to_bitboard(), self.book_selection, &mut self.rng)
+    }
+
+    /// Plays from the opening book while it has an answer, otherwise searches.
+    pub fn find_best_move(&mut self) -> Option<Move> {
+        self.book_move().or_else(|| self.searcher.search(&self.board, self.evaluator.as_ref()))
+    }
+
+    /// Searches the configured number of principal variations, best line first.
+    pub fn find_best_lines(&self) -> Vec<SearchLine> {
+        self.find_best_lines_with(&mut |_| {})
+    }
+
+    /// What the opponent would play if the side to move passed, `None` when in check.
+    pub fn find_threat(&self) -> Option<SearchLine> {
+        let mut board = self.board.clone();
+        if !board.make_null_move() {
+            return None;
+        }
+        self.searcher.search_lines(&board, self.evaluator.as_ref(), 1, &mut |_| {}).into_iter().next()
+    }
+
+    pub fn find_best_lines_with(&self, on_iteration: &mut dyn FnMut(&[SearchLine])) -> Vec<SearchLine> {
+        self.searcher.search_lines(&self.board, self.evaluator.as_ref(), self.multi_pv, on_iteration)
     }
 }
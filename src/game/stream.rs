@@ -0,0 +1,139 @@
+use std::io::{self, Read, Write};
+use crate::board::{BitboardBoard, Board};
+use crate::board::packed::{PackedBoard, PACKED_BOARD_SIZE};
+use crate::board::types::{CompactMove, Move};
+
+const HAS_SCORES: u8 = 1;
+/// Written for moves without a score in a game that has some.
+const NO_SCORE: i16 = i16::MIN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedMove {
+    pub mv: CompactMove,
+    /// Search score from the mover's point of view.
+    pub score: Option<i16>,
+}
+
+/// A game as its start position and the moves played from it. In a stream every game is
+/// written as a flags byte, the packed start position, a little endian `u16` move count and
+/// the moves as `u16`, each followed by an `i16` score when the flags say so.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedGame {
+    pub start: PackedBoard,
+    pub moves: Vec<PackedMove>,
+}
+
+impl PackedGame {
+    pub fn new(start: &BitboardBoard) -> Self {
+        Self { start: PackedBoard::pack(start), moves: Vec::new() }
+    }
+
+    /// Packs moves played from `start`, which is needed to encode en passant.
+    pub fn from_moves(start: &BitboardBoard, moves: &[Move]) -> Self {
+        let mut game = Self::new(start);
+        let mut board = start.clone();
+        for mv in moves {
+            game.push(board.compact_move(mv), None);
+            board.make_move(mv);
+        }
+        game
+    }
+
+    pub fn push(&mut self, mv: CompactMove, score: Option<i16>) {
+        self.moves.push(PackedMove { mv, score });
+    }
+
+    /// The start position and the full moves, `None` if a move is not legal where it is played.
+    pub fn replay(&self) -> Option<(BitboardBoard, Vec<Move>)> {
+        let start = self.start.unpack()?;
+        let mut board = start.clone();
+        let mut moves = Vec::with_capacity(self.moves.len());
+        for packed in &self.moves {
+            if !board.is_legal(packed.mv) {
+                return None;
+            }
+            let mv = board.expand_move(packed.mv)?;
+            board.make_move(&mv);
+            moves.push(mv);
+        }
+        Some((start, moves))
+    }
+}
+
+pub struct GameWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> GameWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write_game(&mut self, game: &PackedGame) -> io::Result<()> {
+        let count = u16::try_from(game.moves.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many moves in one game"))?;
+        let has_scores = game.moves.iter().any(|m| m.score.is_some());
+
+        let mut bytes = Vec::with_capacity(1 + PACKED_BOARD_SIZE + 2 + game.moves.len() * 4);
+        bytes.push(if has_scores { HAS_SCORES } else { 0 });
+        bytes.extend_from_slice(&game.start.0);
+        bytes.extend_from_slice(&count.to_le_bytes());
+        for packed in &game.moves {
+            bytes.extend_from_slice(&packed.mv.0.to_le_bytes());
+            if has_scores {
+                bytes.extend_from_slice(&packed.score.unwrap_or(NO_SCORE).to_le_bytes());
+            }
+        }
+        self.writer.write_all(&bytes)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub struct GameReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> GameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// The next game, `None` once the stream ends cleanly between games.
+    pub fn read_game(&mut self) -> io::Result<Option<PackedGame>> {
+        let mut flags = [0u8; 1];
+        if self.reader.read(&mut flags)? == 0 {
+            return Ok(None);
+        }
+        let mut start = [0u8; PACKED_BOARD_SIZE];
+        self.reader.read_exact(&mut start)?;
+        let mut count = [0u8; 2];
+        self.reader.read_exact(&mut count)?;
+
+        let has_scores = flags[0] & HAS_SCORES != 0;
+        let move_size = if has_scores { 4 } else { 2 };
+        let mut body = vec![0u8; u16::from_le_bytes(count) as usize * move_size];
+        self.reader.read_exact(&mut body)?;
+        let moves = body.chunks_exact(move_size).map(|chunk| {
+            let score = has_scores
+                .then(|| i16::from_le_bytes([chunk[2], chunk[3]]))
+                .filter(|&score| score != NO_SCORE);
+            PackedMove { mv: CompactMove(u16::from_le_bytes([chunk[0], chunk[1]])), score }
+        }).collect();
+        Ok(Some(PackedGame { start: PackedBoard(start), moves }))
+    }
+}
+
+impl<R: Read> Iterator for GameReader<R> {
+    type Item = io::Result<PackedGame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_game().transpose()
+    }
+}
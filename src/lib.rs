@@ -0,0 +1,13 @@
+pub mod board;
+pub mod arena;
+pub mod book;
+pub mod utils;
+pub mod move_generation;
+pub mod evaluation;
+pub mod search;
+pub mod tablebase;
+pub mod game;
+pub mod uci;
+
+#[cfg(test)]
+mod tests;
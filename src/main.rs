@@ -1,18 +1,277 @@
 use std::io;
-use crate::board::Board;
-use crate::board::types::{Color, GameState, PieceKind, Square};
-use crate::game::GameManager;
-use crate::utils::bitboards::utils::utils::print_board_state;
-
-mod board;
-mod utils;
-mod move_generation;
-mod tests;
-mod evaluation;
-mod search;
-mod game;
+use std::time::Duration;
+use chess_engine::arena::{self, EngineConfig, GameResult, MatchSettings, SprtSettings};
+use chess_engine::board::{BitboardBoard, Board};
+use chess_engine::board::epd::{load_epd, EpdRecord};
+use chess_engine::board::san::move_to_san;
+use chess_engine::board::types::{Color, GameState, PieceKind, Square};
+use chess_engine::game::GameManager;
+use chess_engine::evaluation::{Evaluator, MaterialEvaluator, SimpleEvaluator};
+use chess_engine::search::bench::{self, BENCH_DEPTH};
+use chess_engine::search::mate::{MateResult, MateSolver, MateTree};
+use chess_engine::search::testsuite;
+use chess_engine::search::{AlphaBetaSearcher, SearchLine, Searcher, DEFAULT_SEARCH_DEPTH, MAX_SEARCH_DEPTH};
+use chess_engine::search::mcts::{MctsSearcher, DEFAULT_ITERATIONS};
+use chess_engine::search::proof_number::{ProofNumberSearcher, DEFAULT_NODE_BUDGET};
+use chess_engine::search::skill::{Skill, SkillLimitedSearcher, MAX_SKILL_LEVEL};
+use chess_engine::uci::{format_score, UciEngine};
+use chess_engine::utils::bitboards::utils::utils::print_board_state;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("uci") => UciEngine::new().run(),
+        Some("match") => run_match(&args[2..]),
+        Some("bench") => run_bench(args.get(2).and_then(|d| d.parse().ok()).unwrap_or(BENCH_DEPTH)),
+        Some("testsuite") => run_testsuite(&args[2..]),
+        Some("mate") => run_mate(&args[2..]),
+        _ => play(),
+    }
+}
+
+/// `bench [depth]`
+fn run_bench(depth: u8) {
+    let total = bench::run_bench(depth, &mut |fen, result| {
+        println!("{}: {} nodes, {} nps", fen, result.nodes, result.nodes_per_second());
+    });
+    println!("Total: {} nodes in {} ms, {} nps", total.nodes, total.time.as_millis(), total.nodes_per_second());
+}
+
+/// The word after `name` in the arguments.
+fn option_word<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// The number after `name` in the arguments, `None` when it is missing or not a number.
+fn parse_u64_option(args: &[String], name: &str) -> Option<u64> {
+    option_word(args, name).and_then(|v| v.parse().ok())
+}
+
+/// `testsuite FILE [depth D] [nodes N] [movetime MS]`, the limits apply to every position.
+fn run_testsuite(args: &[String]) {
+    let Some(path) = args.first() else {
+        println!("Please provide an EPD file.");
+        return;
+    };
+    let records = match load_epd(path) {
+        Ok(records) => records,
+        Err(err) => {
+            println!("Could not read test suite: {}", err);
+            return;
+        }
+    };
+    let nodes = parse_u64_option(args, "nodes");
+    let movetime = parse_u64_option(args, "movetime");
+    let default_depth = if nodes.is_some() || movetime.is_some() { MAX_SEARCH_DEPTH } else { DEFAULT_SEARCH_DEPTH };
+    let mut searcher = AlphaBetaSearcher::new(parse_u64_option(args, "depth").map_or(default_depth, |d| d.min(MAX_SEARCH_DEPTH as u64) as u8));
+    if let Some(nodes) = nodes {
+        searcher = searcher.with_node_limit(nodes);
+    }
+    if let Some(movetime) = movetime {
+        searcher = searcher.with_time_limit(Duration::from_millis(movetime));
+    }
+
+    let summary = testsuite::run_testsuite(&records, &searcher, &SimpleEvaluator, &mut |record, result| {
+        let mut expected = Vec::new();
+        for opcode in ["bm", "am"] {
+            if let Some(moves) = record.operation(opcode) {
+                expected.push(format!("{} {}", opcode, moves.join(" ")));
+            }
+        }
+        print!("{}: {} {} ({})", record.id().unwrap_or("?"), result.san, if result.solved { "solved" } else { "failed" }, expected.join(", "));
+        if result.max_points > 0 {
+            print!(", {}/{} points", result.points, result.max_points);
+        }
+        println!();
+    });
+
+    println!("Solved {} of {} positions", summary.solved, summary.positions);
+    if summary.max_points > 0 {
+        println!(
+            "Score {}/{} ({:.1}%)",
+            summary.points, summary.max_points, 100.0 * summary.points as f64 / summary.max_points as f64
+        );
+    }
+}
+
+/// `mate N [all] [nodes K] FEN`, checks only for the attacker unless `all` is given.
+fn run_mate(args: &[String]) {
+    let Some(moves) = args.first().and_then(|n| n.parse::<u8>().ok()).filter(|&n| n > 0) else {
+        println!("Please provide the number of moves, at least 1.");
+        return;
+    };
+    let mut checks_only = true;
+    let mut node_limit = None;
+    let mut fen = Vec::new();
+    let mut words = args[1..].iter().flat_map(|arg| arg.split_whitespace());
+    while let Some(word) = words.next() {
+        match word {
+            "all" => checks_only = false,
+            "nodes" => node_limit = words.next().and_then(|n| n.parse::<u64>().ok()),
+            _ => fen.push(word),
+        }
+    }
+    let record = match EpdRecord::parse(&fen.join(" ")) {
+        Ok(record) => record,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    let mut solver = MateSolver::new(checks_only);
+    if let Some(nodes) = node_limit {
+        solver = solver.with_node_limit(nodes);
+    }
+    let result = solver.solve(&record.board, moves);
+    let mut board = record.board;
+    match result {
+        MateResult::Mate { moves, tree } => {
+            println!("Mate in {} ({} nodes)", moves, solver.nodes());
+            print_mate_tree(&mut board, &tree, 0);
+        }
+        MateResult::NoMate { refutations } => {
+            println!("No mate in {} ({} nodes)", moves, solver.nodes());
+            for refutation in refutations {
+                let attack = move_to_san(&mut board, &refutation.attack);
+                board.make_move(&refutation.attack);
+                let defence = refutation.defence.map_or_else(|| "stalemate".to_string(), |mv| move_to_san(&mut board, &mv));
+                board.unmake_move(&refutation.attack);
+                println!("{} refuted by {}", attack, defence);
+            }
+        }
+        MateResult::Unknown => println!("Node limit reached after {} nodes", solver.nodes()),
+    }
+}
+
+fn print_mate_tree(board: &mut BitboardBoard, tree: &MateTree, indent: usize) {
+    println!("{:indent$}{}", "", move_to_san(board, &tree.key), indent = indent);
+    board.make_move(&tree.key);
+    for (reply, continuation) in &tree.replies {
+        println!("{:indent$}... {}", "", move_to_san(board, reply), indent = indent + 2);
+        board.make_move(reply);
+        print_mate_tree(board, continuation, indent + 4);
+        board.unmake_move(reply);
+    }
+    board.unmake_move(&tree.key);
+}
+
+/// `match [games N] [searcher1 alphabeta|mcts|pn] [searcher2 ...] [evaluator1 simple|material] [evaluator2 ...]
+/// [depth1 D] [depth2 D] [mcts1 ITERATIONS] [mcts2 ITERATIONS] [nodes N] [movetime MS] [maxplies N] [openings FILE]
+/// [sprt ELO0 ELO1]`, where `nodes` is the proof-number budget for `pn`
+fn run_match(args: &[String]) {
+    let nodes = parse_u64_option(args, "nodes");
+    let movetime = parse_u64_option(args, "movetime");
+    let default_depth = if nodes.is_some() || movetime.is_some() { MAX_SEARCH_DEPTH } else { DEFAULT_SEARCH_DEPTH };
+
+    let engine = |n: u8| -> Result<EngineConfig, String> {
+        let option = |name: &str| format!("{}{}", name, n);
+        let evaluator_name = option_word(args, &option("evaluator")).unwrap_or("simple");
+        let evaluator: Box<dyn Evaluator> = match evaluator_name {
+            "simple" => Box::new(SimpleEvaluator),
+            "material" => Box::new(MaterialEvaluator),
+            other => return Err(format!("Unknown evaluator '{}', use simple or material.", other)),
+        };
+
+        let mcts = parse_u64_option(args, &option("mcts"));
+        let searcher_name = option_word(args, &option("searcher")).unwrap_or(if mcts.is_some() { "mcts" } else { "alphabeta" });
+        let mut limits = vec![searcher_name.to_string(), format!("{} evaluation", evaluator_name)];
+        let searcher: Box<dyn Searcher> = match searcher_name {
+            "alphabeta" => {
+                let depth = parse_u64_option(args, &option("depth")).map_or(default_depth, |d| d.min(MAX_SEARCH_DEPTH as u64) as u8);
+                let mut searcher = AlphaBetaSearcher::new(depth);
+                if let Some(nodes) = nodes {
+                    searcher = searcher.with_node_limit(nodes);
+                }
+                if let Some(movetime) = movetime {
+                    searcher = searcher.with_time_limit(Duration::from_millis(movetime));
+                }
+                limits.push(format!("depth {}", depth));
+                limits.extend(nodes.map(|n| format!("nodes {}", n)));
+                limits.extend(movetime.map(|t| format!("movetime {}", t)));
+                Box::new(searcher)
+            }
+            "mcts" => {
+                let iterations = mcts.unwrap_or(DEFAULT_ITERATIONS);
+                let mut searcher = MctsSearcher::new(iterations);
+                if let Some(movetime) = movetime {
+                    searcher = searcher.with_time_limit(Duration::from_millis(movetime));
+                }
+                limits.push(format!("iterations {}", iterations));
+                limits.extend(movetime.map(|t| format!("movetime {}", t)));
+                Box::new(searcher)
+            }
+            "pn" => {
+                let budget = nodes.map_or(DEFAULT_NODE_BUDGET, |n| n as usize);
+                limits.push(format!("nodes {}", budget));
+                Box::new(ProofNumberSearcher::new(budget))
+            }
+            other => return Err(format!("Unknown searcher '{}', use alphabeta, mcts or pn.", other)),
+        };
+        Ok(EngineConfig::new(&format!("engine{} ({})", n, limits.join(", ")), evaluator, searcher))
+    };
+    let (first, second) = match (engine(1), engine(2)) {
+        (Ok(first), Ok(second)) => (first, second),
+        (Err(err), _) | (_, Err(err)) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    let mut settings = MatchSettings::default();
+    if let Some(games) = parse_u64_option(args, "games") {
+        settings.games = games as usize;
+    }
+    if let Some(max_plies) = parse_u64_option(args, "maxplies") {
+        settings.max_plies = max_plies as usize;
+    }
+    if let Some(path) = option_word(args, "openings") {
+        match arena::load_openings(path) {
+            Ok(openings) => settings.openings = openings,
+            Err(err) => {
+                println!("Could not read openings: {}", err);
+                return;
+            }
+        }
+    }
+    if let Some(i) = args.iter().position(|a| a == "sprt") {
+        let bound = |j: usize| args.get(i + j).and_then(|v| v.parse::<f64>().ok());
+        settings.sprt = Some(SprtSettings::new(bound(1).unwrap_or(0.0), bound(2).unwrap_or(10.0)));
+    }
+
+    println!("{} vs {}", first.name, second.name);
+    let stats = arena::run_match(&first, &second, &settings, &mut |record, stats| {
+        let result = match record.result {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        };
+        let (white, black) = if record.first_is_white { (&first.name, &second.name) } else { (&second.name, &first.name) };
+        print!(
+            "Game {}: {} - {} {} ({:?}), score +{} ={} -{}",
+            stats.games(), white, black, result, record.termination, stats.wins, stats.draws, stats.losses
+        );
+        if let Some(sprt) = &settings.sprt {
+            let (lower, upper) = sprt.bounds();
+            print!(", LLR {:.2} ({:.2}, {:.2})", stats.llr(sprt), lower, upper);
+        }
+        println!();
+    });
+
+    println!("Games: {}, +{} ={} -{}, score {:.1}%", stats.games(), stats.wins, stats.draws, stats.losses, stats.score() * 100.0);
+    match stats.elo() {
+        Some((elo, margin)) => println!("Elo difference: {:.1} +/- {:.1}", elo, margin),
+        None => println!("Elo difference: not enough information"),
+    }
+    if let Some(sprt) = &settings.sprt {
+        println!("SPRT ({}, {}): {:?}", sprt.elo0, sprt.elo1, sprt.decision(&stats));
+    }
+}
+
+fn play() {
     let mut game_manager = GameManager::new();
     let mut input_buffer = String::new();
 
@@ -33,7 +292,7 @@ fn main() {
         }
 
         if game_manager.get_board().side_to_move() == Color::White {
-            println!("Enter command (e.g., 'move', 'getfen', 'loadfen <fen>', 'q'):");
+            println!("Enter command (e.g., 'move', 'analyse', 'threat', 'multipv <k>', 'skill <level>', 'book <path>', 'tb <dir>', 'getfen', 'loadfen <fen>', 'q'):");
             input_buffer.clear();
             io::stdin().read_line(&mut input_buffer).unwrap();
             let input_line = input_buffer.trim();
@@ -48,12 +307,65 @@ fn main() {
                 }
                 "loadfen" => {
                     if parts.len() > 1 {
-                        game_manager.load_from_fen(parts[1]);
+                        if let Err(err) = game_manager.try_load_from_fen(parts[1]) {
+                            println!("Invalid FEN: {}", err);
+                        }
                     } else {
                         println!("Please provide a FEN string.");
                     }
                     continue;
                 }
+                "analyse" => {
+                    let lines = game_manager.find_best_lines();
+                    print_lines(&lines);
+                    continue;
+                }
+                "threat" => {
+                    match game_manager.find_threat() {
+                        Some(line) => print_lines(&[line]),
+                        None => println!("No threat shown while in check."),
+                    }
+                    continue;
+                }
+                "skill" => {
+                    match parts.get(1).and_then(|level| level.parse::<u8>().ok()) {
+                        Some(level) => {
+                            let skill = Skill::new(level);
+                            let searcher = AlphaBetaSearcher::default().with_tablebase(game_manager.get_tablebase());
+                            game_manager.set_searcher(Box::new(SkillLimitedSearcher::new(searcher, skill)));
+                            println!("Skill level {}.", skill.level());
+                        }
+                        None => println!("Please provide a skill level from 0 to {}.", MAX_SKILL_LEVEL),
+                    }
+                    continue;
+                }
+                "multipv" => {
+                    match parts.get(1).and_then(|k| k.parse::<usize>().ok()) {
+                        Some(k) => game_manager.set_multi_pv(k),
+                        None => println!("Please provide the number of lines."),
+                    }
+                    continue;
+                }
+                "book" => {
+                    match parts.get(1) {
+                        Some(path) => match game_manager.load_book(path) {
+                            Ok(()) => println!("Opening book loaded."),
+                            Err(err) => println!("Could not open book: {}", err),
+                        },
+                        None => println!("Please provide a book path."),
+                    }
+                    continue;
+                }
+                "tb" => {
+                    match parts.get(1) {
+                        Some(dir) => match game_manager.load_tablebase(dir) {
+                            Ok(()) => println!("Tablebases loaded."),
+                            Err(err) => println!("Could not open tablebases: {}", err),
+                        },
+                        None => println!("Please provide a tablebase directory."),
+                    }
+                    continue;
+                }
                 "move" => {
                     let moves_by_piece = game_manager.get_legal_moves_grouped();
                     if moves_by_piece.is_empty() {
@@ -108,7 +420,7 @@ fn main() {
                     };
 
                     let final_move = match selected_moves.iter().find(|m| m.to == dest_square) {
-                        Some(m) => m.clone(),
+                        Some(m) => *m,
                         None => {
                             println!("Invalid destination for that piece.");
                             continue;
@@ -121,7 +433,16 @@ fn main() {
             }
         } else {
             println!("Black is thinking...");
-            if let Some(mv) = game_manager.find_best_move() {
+            if let Some(mv) = game_manager.book_move() {
+                println!("Black plays {}{} (book)", mv.from.to_notation(), mv.to.to_notation());
+                game_manager.apply_move(&mv);
+                continue;
+            }
+            let lines = game_manager.find_best_lines();
+            if game_manager.get_multi_pv() > 1 {
+                print_lines(&lines);
+            }
+            if let Some(mv) = lines.first().and_then(SearchLine::best_move) {
                 println!("Black plays {}{}", mv.from.to_notation(), mv.to.to_notation());
                 game_manager.apply_move(&mv);
             } else {
@@ -131,6 +452,13 @@ fn main() {
     }
 }
 
+fn print_lines(lines: &[SearchLine]) {
+    for (i, line) in lines.iter().enumerate() {
+        let pv: Vec<String> = line.pv.iter().map(|m| m.to_uci()).collect();
+        println!("{}. [{}] {}", i + 1, format_score(line.score, line.pv.len()), pv.join(" "));
+    }
+}
+
 fn piece_kind_to_char(kind: PieceKind) -> char {
     match kind {
         PieceKind::Pawn => 'P',
@@ -158,7 +486,7 @@ fn parse_piece_and_square(s: &str) -> Option<(PieceKind, Square)> {
     if s.len() != 3 {
         return None;
     }
-    let piece_kind = char_to_piece_kind(s.chars().nth(0)?)?;
+    let piece_kind = char_to_piece_kind(s.chars().next()?)?;
     let square = Square::from_notation(&s[1..3])?;
     Some((piece_kind, square))
 }
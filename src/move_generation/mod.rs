@@ -1,8 +1,84 @@
+pub mod move_list;
 pub mod naive_move_generator;
 
-use crate::board::BitboardBoard;
-use crate::board::types::Move;
+pub use move_list::MoveList;
+
+use crate::board::{attacks, BitboardBoard, Board};
+use crate::board::types::{Color, Move, PieceKind, Square};
+
+/// Which pseudo-legal moves to generate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationMode {
+    All,
+    /// Captures and promotions.
+    Captures,
+    /// Everything that is neither a capture nor a promotion, castling included.
+    Quiets,
+    /// Moves that may answer a check: king moves, and with a single checker its capture or a
+    /// block. Same as `All` when not in check.
+    Evasions,
+    /// Quiet moves that give check, directly or by discovery.
+    QuietChecks,
+}
+
+pub fn is_tactical(mv: &Move) -> bool {
+    mv.captures.is_some() || mv.promotion.is_some()
+}
+
+/// Whether a move may answer the check given by `checkers`: a king move other than castling, or
+/// against a single checker its capture or a block.
+pub fn is_evasion(board: &BitboardBoard, checkers: u64, mv: &Move) -> bool {
+    let side = board.side_to_move();
+    if mv.piece.kind == PieceKind::King {
+        return mv.castling.is_none();
+    }
+    // with two checkers only the king can move
+    let Some(king) = board.king_square(side).filter(|_| checkers.count_ones() == 1) else { return false };
+    let checker = Square(checkers.trailing_zeros() as u8);
+    let targets = checkers | attacks::between(king, checker);
+    let en_passant = mv.piece.kind == PieceKind::Pawn && Some(mv.to) == board.en_passant_square;
+    let ep_pawn = board.en_passant_square.map(|ep| if side == Color::White { ep.0 - 8 } else { ep.0 + 8 });
+    targets & (1u64 << mv.to.0) != 0 || (en_passant && ep_pawn == Some(checker.0))
+}
 
 pub trait BitboardMoveGenerator {
-    fn generate_moves(&self, board: &BitboardBoard) -> Vec<Move>;
+    fn generate_moves(&self, board: &BitboardBoard) -> MoveList;
+
+    /// Pseudo-legal moves for a generation mode. By default the full list is filtered, so
+    /// generators only need to override this, or `generate_evasions`, when they can do better.
+    fn generate_moves_of(&self, board: &BitboardBoard, mode: GenerationMode) -> MoveList {
+        match mode {
+            GenerationMode::All => self.generate_moves(board),
+            GenerationMode::Captures => {
+                let mut moves = self.generate_moves(board);
+                moves.retain(is_tactical);
+                moves
+            }
+            GenerationMode::Quiets => {
+                let mut moves = self.generate_moves(board);
+                moves.retain(|m| !is_tactical(m));
+                moves
+            }
+            GenerationMode::Evasions => self.generate_evasions(board),
+            GenerationMode::QuietChecks => {
+                let mut moves = self.generate_moves(board);
+                moves.retain(|m| !is_tactical(m) && board.gives_check(m));
+                moves
+            }
+        }
+    }
+
+    /// Pseudo-legal moves that may answer a check, all moves when not in check. By default the
+    /// full list is filtered with `is_evasion`.
+    fn generate_evasions(&self, board: &BitboardBoard) -> MoveList {
+        let checkers = board.checkers();
+        let mut moves = self.generate_moves(board);
+        if checkers != 0 {
+            moves.retain(|m| is_evasion(board, checkers, m));
+        }
+        moves
+    }
+
+    /// Copies the generator so cloned boards keep using it.
+    fn box_clone(&self) -> Box<dyn BitboardMoveGenerator>;
 }
@@ -0,0 +1,234 @@
+use std::fmt;
+use crate::board::types::{CompactMove, Move, Piece, Square};
+
+pub const MAX_MOVES: usize = 256;
+
+/// Fixed capacity list of moves with an ordering score per move, kept on the stack.
+///
+/// Moves are stored packed. A `CompactMove` only names squares, so the list also remembers
+/// the piece on each square its moves start from or capture on, which is all it needs to
+/// expand them again. All moves of a list must come from the same position.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [CompactMove; MAX_MOVES],
+    scores: [i32; MAX_MOVES],
+    len: usize,
+    pieces: [Option<Piece>; 64],
+    en_passant: Option<Square>,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self::with_en_passant(None)
+    }
+
+    /// An empty list for a position with this en passant square, needed to pack en passant
+    /// captures.
+    pub fn with_en_passant(en_passant: Option<Square>) -> Self {
+        Self { moves: [CompactMove::NONE; MAX_MOVES], scores: [0; MAX_MOVES], len: 0, pieces: [None; 64], en_passant }
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        self.push_scored(mv, 0);
+    }
+
+    pub fn push_scored(&mut self, mv: Move, score: i32) {
+        debug_assert!(self.len < MAX_MOVES, "move list is full");
+        let compact = CompactMove::from_move(&mv, self.en_passant);
+        self.pieces[mv.from.0 as usize] = Some(mv.piece);
+        if compact.is_capture() && !compact.is_en_passant() {
+            self.pieces[mv.to.0 as usize] = mv.captures;
+        }
+        self.moves[self.len] = compact;
+        debug_assert_eq!(self.expand(compact), mv, "move does not fit the list's position");
+        self.scores[self.len] = score;
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// The move at `index`, expanded.
+    pub fn get(&self, index: usize) -> Option<Move> {
+        (index < self.len).then(|| self.expand(self.moves[index]))
+    }
+
+    pub fn first(&self) -> Option<Move> {
+        self.get(0)
+    }
+
+    /// The packed move at `index`, as stored in the transposition table and killer slots.
+    pub fn compact(&self, index: usize) -> CompactMove {
+        self.moves[..self.len][index]
+    }
+
+    pub fn contains(&self, mv: &Move) -> bool {
+        self.iter().any(|m| m == *mv)
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { list: self, index: 0 }
+    }
+
+    pub fn score(&self, index: usize) -> i32 {
+        self.scores[index]
+    }
+
+    pub fn set_score(&mut self, index: usize, score: i32) {
+        self.scores[index] = score;
+    }
+
+    /// Scores every move with `score`.
+    pub fn score_each(&mut self, mut score: impl FnMut(&Move) -> i32) {
+        for i in 0..self.len {
+            self.scores[i] = score(&self.expand(self.moves[i]));
+        }
+    }
+
+    /// Moves with their scores, in list order.
+    pub fn iter_scored(&self) -> impl Iterator<Item = (Move, i32)> + '_ {
+        self.iter().zip(self.scores[..self.len].iter().copied())
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&Move) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if keep(&self.expand(self.moves[i])) {
+                self.moves[kept] = self.moves[i];
+                self.scores[kept] = self.scores[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+
+    fn expand(&self, mv: CompactMove) -> Move {
+        mv.expand(|square| self.pieces[square.0 as usize]).expect("moves of the list start on a known piece")
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.moves.swap(a, b);
+        self.scores.swap(a, b);
+    }
+
+    /// Sorts by score, highest first. Equal scores keep their order.
+    pub fn sort(&mut self) {
+        for i in 1..self.len {
+            let mut j = i;
+            while j > 0 && self.scores[j - 1] < self.scores[j] {
+                self.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    /// Moves the best scored move from `start..` to `start` and returns it, so a search can
+    /// pick moves one at a time without sorting the whole list.
+    pub fn select_best(&mut self, start: usize) -> Option<Move> {
+        if start >= self.len {
+            return None;
+        }
+        let mut best = start;
+        for i in start + 1..self.len {
+            if self.scores[i] > self.scores[best] {
+                best = i;
+            }
+        }
+        self.swap(start, best);
+        self.get(start)
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+/// Expands the moves of a list in order.
+pub struct Iter<'a> {
+    list: &'a MoveList,
+    index: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let mv = self.list.get(self.index);
+        self.index += 1;
+        mv
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.list.len.saturating_sub(self.index);
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = Move;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+pub struct IntoIter {
+    list: MoveList,
+    index: usize,
+}
+
+impl Iterator for IntoIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let mv = self.list.get(self.index);
+        self.index += 1;
+        mv
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter { list: self, index: 0 }
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    /// Collects moves without en passant captures, which need `with_en_passant`.
+    fn from_iter<I: IntoIterator<Item = Move>>(iter: I) -> Self {
+        let mut list = MoveList::new();
+        for mv in iter {
+            list.push(mv);
+        }
+        list
+    }
+}
@@ -1,12 +1,12 @@
-use crate::board::types::{Color, Move, PieceKind, Square};
-use crate::board::{BitboardBoard, Board};
+use crate::board::types::{Color, Move, Piece, PieceKind, Square};
+use crate::board::{attacks, BitboardBoard, Board};
 use crate::board::bitboard::{CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE, CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE};
-use crate::move_generation::BitboardMoveGenerator;
+use crate::move_generation::{is_evasion, is_tactical, BitboardMoveGenerator, GenerationMode, MoveList};
 pub struct NaiveMoveGenerator;
 
 impl BitboardMoveGenerator for NaiveMoveGenerator {
-    fn generate_moves(&self, board: &BitboardBoard) -> Vec<Move> {
-        let mut pseudo_legal_moves: Vec<Move> = Vec::new();
+    fn generate_moves(&self, board: &BitboardBoard) -> MoveList {
+        let mut pseudo_legal_moves = MoveList::with_en_passant(board.en_passant_square());
         let color = board.side_to_move();
         let range = match color {
             Color::White => 0..6,
@@ -18,31 +18,243 @@ impl BitboardMoveGenerator for NaiveMoveGenerator {
             while bb != 0 {
                 let square = bb.trailing_zeros() as u8;
                 bb &= bb - 1;
-                match i % 6 {
-                    0 => self.generate_pawn_moves(board, square, color, &mut pseudo_legal_moves),
-                    1 => self.generate_knight_moves(board, square, color, &mut pseudo_legal_moves),
-                    2 => self.generate_bishop_moves(board, square, color, &mut pseudo_legal_moves),
-                    3 => self.generate_rook_moves(board, square, color, &mut pseudo_legal_moves),
-                    4 => self.generate_queen_moves(board, square, color, &mut pseudo_legal_moves),
-                    5 => self.generate_king_moves(board, square, color, &mut pseudo_legal_moves),
-                    _ => (),
-                }
+                self.generate_piece_moves(board, i % 6, square, color, &mut pseudo_legal_moves);
             }
         }
 
         pseudo_legal_moves
     }
+
+    /// Captures and quiet checks come from attack sets rather than the full list, the other
+    /// modes from `generate_moves` and `generate_evasions`.
+    fn generate_moves_of(&self, board: &BitboardBoard, mode: GenerationMode) -> MoveList {
+        match mode {
+            GenerationMode::All => self.generate_moves(board),
+            GenerationMode::Captures => self.generate_captures(board),
+            GenerationMode::Quiets => {
+                let mut moves = self.generate_moves(board);
+                moves.retain(|m| !is_tactical(m));
+                moves
+            }
+            GenerationMode::Evasions => self.generate_evasions(board),
+            GenerationMode::QuietChecks => self.generate_quiet_checks(board),
+        }
+    }
+
+    /// King moves only against a double check, otherwise the other pieces' moves are kept when
+    /// they capture the checker or block it.
+    fn generate_evasions(&self, board: &BitboardBoard) -> MoveList {
+        let checkers = board.checkers();
+        let color = board.side_to_move();
+        let Some(king) = board.king_square(color).filter(|_| checkers != 0) else {
+            return self.generate_moves(board);
+        };
+
+        let mut moves = MoveList::with_en_passant(board.en_passant_square());
+        self.generate_king_moves(board, king.0, color, &mut moves);
+        moves.retain(|m| m.castling.is_none());
+        if checkers.count_ones() > 1 {
+            return moves;
+        }
+
+        let base = if color == Color::White { 0 } else { 6 };
+        let mut others = MoveList::with_en_passant(board.en_passant_square());
+        for kind in 0..5 {
+            let mut bb = board.bitboards[base + kind];
+            while bb != 0 {
+                let square = bb.trailing_zeros() as u8;
+                bb &= bb - 1;
+                self.generate_piece_moves(board, kind, square, color, &mut others);
+            }
+        }
+        for mv in others.iter().filter(|m| is_evasion(board, checkers, m)) {
+            moves.push(mv);
+        }
+        moves
+    }
+
+    fn box_clone(&self) -> Box<dyn BitboardMoveGenerator> {
+        Box::new(Self)
+    }
+}
+
+impl Default for NaiveMoveGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NaiveMoveGenerator {
 
     pub fn new() -> NaiveMoveGenerator {Self}
+
+    /// Squares a knight, bishop, rook, queen or king on `square` attacks.
+    fn piece_attacks(kind: PieceKind, square: Square, occupied: u64) -> u64 {
+        match kind {
+            PieceKind::Knight => attacks::knight_attacks(square),
+            PieceKind::Bishop => attacks::bishop_attacks(square, occupied),
+            PieceKind::Rook => attacks::rook_attacks(square, occupied),
+            PieceKind::Queen => attacks::bishop_attacks(square, occupied) | attacks::rook_attacks(square, occupied),
+            PieceKind::King => attacks::king_attacks(square),
+            PieceKind::Pawn => 0,
+        }
+    }
+
+    /// A pawn move, as one move per piece when it reaches the last rank.
+    fn push_pawn_move(from: Square, to: Square, pawn: Piece, captures: Option<Piece>, moves: &mut MoveList) {
+        if to.0 / 8 == 0 || to.0 / 8 == 7 {
+            for promo in [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight] {
+                moves.push(Move::new(from, to, pawn, Some(promo), captures));
+            }
+        } else {
+            moves.push(Move::new(from, to, pawn, None, captures));
+        }
+    }
+
+    /// Captures, en passant included, and promotions.
+    fn generate_captures(&self, board: &BitboardBoard) -> MoveList {
+        let mut moves = MoveList::with_en_passant(board.en_passant_square());
+        let color = board.side_to_move();
+        let base = if color == Color::White { 0 } else { 6 };
+        let occupied = board.get_all_pieces_mask();
+        let enemy = board.get_mask_for_color(color.opposite());
+
+        for kind in [PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King] {
+            let piece = Piece { color, kind };
+            let mut bb = board.bitboards[base + kind as usize];
+            while bb != 0 {
+                let from = Square(bb.trailing_zeros() as u8);
+                bb &= bb - 1;
+                let mut targets = Self::piece_attacks(kind, from, occupied) & enemy;
+                while targets != 0 {
+                    let to = Square(targets.trailing_zeros() as u8);
+                    targets &= targets - 1;
+                    moves.push(Move::new(from, to, piece, None, board.piece_at(to)));
+                }
+            }
+        }
+
+        let pawn = Piece { color, kind: PieceKind::Pawn };
+        let forward: i8 = if color == Color::White { 8 } else { -8 };
+        let mut bb = board.bitboards[base];
+        while bb != 0 {
+            let from = Square(bb.trailing_zeros() as u8);
+            bb &= bb - 1;
+            let mut targets = attacks::pawn_attacks(from, color) & enemy;
+            while targets != 0 {
+                let to = Square(targets.trailing_zeros() as u8);
+                targets &= targets - 1;
+                Self::push_pawn_move(from, to, pawn, board.piece_at(to), &mut moves);
+            }
+            if let Some(ep) = board.en_passant_square
+                && attacks::pawn_attacks(from, color) & (1u64 << ep.0) != 0 {
+                moves.push(Move::new(from, ep, pawn, None, Some(Piece { color: color.opposite(), kind: PieceKind::Pawn })));
+            }
+            // promotions by a push
+            let to = Square((from.0 as i8 + forward) as u8);
+            if (to.0 / 8 == 0 || to.0 / 8 == 7) && occupied & (1u64 << to.0) == 0 {
+                Self::push_pawn_move(from, to, pawn, None, &mut moves);
+            }
+        }
+        moves
+    }
+
+    /// Quiet moves that give check: to a square the piece attacks the enemy king from, or off
+    /// the king's line for a piece that stands alone between it and one of our sliders.
+    /// Castling is tried move by move.
+    fn generate_quiet_checks(&self, board: &BitboardBoard) -> MoveList {
+        let mut moves = MoveList::with_en_passant(board.en_passant_square());
+        let color = board.side_to_move();
+        let Some(king) = board.king_square(color.opposite()) else { return moves };
+        let base = if color == Color::White { 0 } else { 6 };
+        let own_of = |kind: PieceKind| board.bitboards[base + kind as usize];
+        let occupied = board.get_all_pieces_mask();
+        let own = board.get_mask_for_color(color);
+
+        let queens = own_of(PieceKind::Queen);
+        let mut snipers = (attacks::bishop_attacks(king, 0) & (own_of(PieceKind::Bishop) | queens))
+            | (attacks::rook_attacks(king, 0) & (own_of(PieceKind::Rook) | queens));
+        let mut discoverers = 0u64;
+        while snipers != 0 {
+            let sniper = Square(snipers.trailing_zeros() as u8);
+            snipers &= snipers - 1;
+            let blockers = attacks::between(king, sniper) & occupied;
+            if blockers.count_ones() == 1 && blockers & own != 0 {
+                discoverers |= blockers;
+            }
+        }
+        let uncovering = |from: Square| if discoverers & (1u64 << from.0) != 0 { !attacks::line(king, from) } else { 0 };
+
+        for kind in [PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King] {
+            let piece = Piece { color, kind };
+            let direct = if kind == PieceKind::King { 0 } else { Self::piece_attacks(kind, king, occupied) };
+            let mut bb = own_of(kind);
+            while bb != 0 {
+                let from = Square(bb.trailing_zeros() as u8);
+                bb &= bb - 1;
+                let mut targets = Self::piece_attacks(kind, from, occupied) & !occupied & (direct | uncovering(from));
+                while targets != 0 {
+                    let to = Square(targets.trailing_zeros() as u8);
+                    targets &= targets - 1;
+                    moves.push(Move::new(from, to, piece, None, None));
+                }
+            }
+        }
+
+        // pawn pushes short of the last rank
+        let pawn = Piece { color, kind: PieceKind::Pawn };
+        let (forward, start_rank): (i8, u8) = if color == Color::White { (8, 1) } else { (-8, 6) };
+        let pawn_checks = attacks::pawn_attacks(king, color.opposite());
+        let mut bb = own_of(PieceKind::Pawn);
+        while bb != 0 {
+            let from = Square(bb.trailing_zeros() as u8);
+            bb &= bb - 1;
+            let one = (from.0 as i8 + forward) as u8;
+            if occupied & (1u64 << one) != 0 || one / 8 == 0 || one / 8 == 7 {
+                continue;
+            }
+            let mut targets = 1u64 << one;
+            let two = (one as i8 + forward) as u8;
+            if from.0 / 8 == start_rank && occupied & (1u64 << two) == 0 {
+                targets |= 1u64 << two;
+            }
+            targets &= pawn_checks | uncovering(from);
+            while targets != 0 {
+                let to = Square(targets.trailing_zeros() as u8);
+                targets &= targets - 1;
+                moves.push(Move::new(from, to, pawn, None, None));
+            }
+        }
+
+        if let Some(own_king) = board.king_square(color) {
+            let mut castles = MoveList::new();
+            self.generate_castling_moves(board, own_king.0, color, &mut castles);
+            for mv in castles.iter().filter(|m| board.gives_check(m)) {
+                moves.push(mv);
+            }
+        }
+        moves
+    }
+
+    /// Moves of the piece on `square`, `kind` counting from pawn (0) to king (5).
+    fn generate_piece_moves(&self, board: &BitboardBoard, kind: usize, square: u8, color: Color, moves: &mut MoveList) {
+        match kind {
+            0 => self.generate_pawn_moves(board, square, color, moves),
+            1 => self.generate_knight_moves(board, square, color, moves),
+            2 => self.generate_bishop_moves(board, square, color, moves),
+            3 => self.generate_rook_moves(board, square, color, moves),
+            4 => self.generate_queen_moves(board, square, color, moves),
+            5 => self.generate_king_moves(board, square, color, moves),
+            _ => (),
+        }
+    }
+
     pub fn generate_pawn_moves(
         &self,
         board: &BitboardBoard,
         square: u8,
         color: Color,
-        moves: &mut Vec<Move>,
+        moves: &mut MoveList,
     ) {
         let all_pieces = board.get_all_pieces_mask();
 
@@ -59,7 +271,7 @@ impl NaiveMoveGenerator {
 
 
         let one_forward_square = square_i8 + forward;
-        if one_forward_square >= 0 && one_forward_square < 64 {
+        if (0..64).contains(&one_forward_square) {
             let target_mask = 1u64 << one_forward_square;
             if target_mask & all_pieces == 0 {
 
@@ -73,6 +285,7 @@ impl NaiveMoveGenerator {
                             to: Square(one_forward_square as u8),
                             promotion: Some(*promo),
                             captures: None,
+                            castling: None,
                         });
                     }
                 } else {
@@ -82,12 +295,13 @@ impl NaiveMoveGenerator {
                         to: Square(one_forward_square as u8),
                         promotion: None,
                         captures: None,
+                        castling: None,
                     });
                 }
 
                 if square_mask & start_rank_mask != 0 {
                     let two_forward_square = square_i8 + forward * 2;
-                    if two_forward_square >= 0 && two_forward_square < 64 {
+                    if (0..64).contains(&two_forward_square) {
                         let two_mask = 1u64 << two_forward_square;
                         if two_mask & all_pieces == 0 {
                             moves.push(Move {
@@ -96,6 +310,7 @@ impl NaiveMoveGenerator {
                                 to: Square(two_forward_square as u8),
                                 promotion: None,
                                 captures: None,
+                                castling: None,
                             });
                         }
                     }
@@ -107,41 +322,53 @@ impl NaiveMoveGenerator {
 
         let diagonal_shifts: [i8; 2] = [7, 9];
         for shift in diagonal_shifts {
-            if (from_file == 0 && (shift == 7 || (color == Color::Black && shift == 9))) || (from_file == 7 && (shift == 9 || (color == Color::Black && shift == 7))) {
-                continue;
-            }
-
             let dir = if color == Color::White { shift } else { -shift };
             let target = square_i8 + dir;
 
-            if target >= 0 && target < 64 {
+            if (0..64).contains(&target) {
                 let to_file = target % 8;
-                if (from_file as i8 - to_file as i8).abs() > 1 {
+                if (from_file as i8 - to_file).abs() > 1 {
                     continue;
                 }
 
                 let target_mask = 1u64 << target;
                 if target_mask & enemy != 0 {
-                    moves.push(Move {
-                        piece: current_piece,
-                        from: Square(square),
-                        to: Square(target as u8),
-                        promotion: None,
-                        captures: board.piece_at(Square(target as u8)),
-                    });
-                }
-
-                if let Some(ep_sq) = board.en_passant_square {
-                    if target == ep_sq.0 as i8 {
+                    let captures = board.piece_at(Square(target as u8));
+                    if target_mask & promotion_mask != 0 {
+                        for promo in [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight] {
+                            moves.push(Move {
+                                piece: current_piece,
+                                from: Square(square),
+                                to: Square(target as u8),
+                                promotion: Some(promo),
+                                captures,
+                                castling: None,
+                            });
+                        }
+                    } else {
                         moves.push(Move {
                             piece: current_piece,
                             from: Square(square),
                             to: Square(target as u8),
                             promotion: None,
-                            captures: board.piece_at(Square(target as u8)),
+                            captures,
+                            castling: None,
                         });
                     }
                 }
+
+                // the captured pawn sits behind the en passant square
+                if let Some(ep_sq) = board.en_passant_square
+                    && target == ep_sq.0 as i8 {
+                    moves.push(Move {
+                        piece: current_piece,
+                        from: Square(square),
+                        to: Square(target as u8),
+                        promotion: None,
+                        captures: Some(Piece { color: enemy_color, kind: PieceKind::Pawn }),
+                        castling: None,
+                    });
+                }
             }
         }
     }
@@ -151,7 +378,7 @@ impl NaiveMoveGenerator {
         board: &BitboardBoard,
         square: u8,
         color: Color,
-        moves: &mut Vec<Move>,
+        moves: &mut MoveList,
     ) {
         let current_piece = board.piece_at(Square(square)).unwrap();
         let own_mask = board.get_mask_for_color(color);
@@ -163,7 +390,7 @@ impl NaiveMoveGenerator {
             let mut target = square as i8;
             loop {
                 target += dir;
-                if target < 0 || target >= 64 {
+                if !(0..64).contains(&target) {
                     break;
                 }
 
@@ -180,6 +407,7 @@ impl NaiveMoveGenerator {
                     to: Square(target as u8),
                     promotion: None,
                     captures: if target_mask & enemy_mask != 0 { board.piece_at(Square(target as u8)) } else { None },
+                    castling: None,
                 });
 
                 if target_mask & enemy_mask != 0 { break; }
@@ -192,7 +420,7 @@ impl NaiveMoveGenerator {
         board: &BitboardBoard,
         square: u8,
         color: Color,
-        moves: &mut Vec<Move>,
+        moves: &mut MoveList,
     ) {
         let current_piece = board.piece_at(Square(square)).unwrap();
         let own_mask = board.get_mask_for_color(color);
@@ -203,9 +431,8 @@ impl NaiveMoveGenerator {
         for &dir in directions.iter() {
             let mut target = square as i8;
             loop {
-                let from_file = target % 8;
                 target += dir;
-                if target < 0 || target >= 64 {
+                if !(0..64).contains(&target) {
                     break;
                 }
                 let to_file = target % 8;
@@ -223,6 +450,7 @@ impl NaiveMoveGenerator {
                     to: Square(target as u8),
                     promotion: None,
                     captures: if target_mask & enemy_mask != 0 { board.piece_at(Square(target as u8)) } else { None },
+                    castling: None,
                 });
 
                 if target_mask & enemy_mask != 0 { break; }
@@ -235,7 +463,7 @@ impl NaiveMoveGenerator {
         board: &BitboardBoard,
         square: u8,
         color: Color,
-        moves: &mut Vec<Move>,
+        moves: &mut MoveList,
     ) {
         let current_piece = board.piece_at(Square(square)).unwrap();
         let own_mask = board.get_mask_for_color(color);
@@ -245,10 +473,10 @@ impl NaiveMoveGenerator {
 
         for &offset in knight_offsets.iter() {
             let target = square as i8 + offset;
-            if target < 0 || target >= 64 { continue; }
+            if !(0..64).contains(&target) { continue; }
 
             // check if it doesn't go over the board
-            let file_diff = ((square as i8) % 8) - (target % 8) as i8;
+            let file_diff = ((square as i8) % 8) - (target % 8);
             if file_diff.abs() > 2 { continue; }
 
             let target_mask = 1u64 << target;
@@ -260,6 +488,7 @@ impl NaiveMoveGenerator {
                 to: Square(target as u8),
                 promotion: None,
                 captures: if target_mask & enemy_mask != 0 { board.piece_at(Square(target as u8)) } else { None },
+                castling: None,
             });
         }
     }
@@ -269,7 +498,7 @@ impl NaiveMoveGenerator {
         board: &BitboardBoard,
         square: u8,
         color: Color,
-        moves: &mut Vec<Move>,
+        moves: &mut MoveList,
     ) {
         let current_piece = board.piece_at(Square(square)).unwrap();
         let own_mask = board.get_mask_for_color(color);
@@ -279,7 +508,7 @@ impl NaiveMoveGenerator {
 
         for &offset in king_offsets.iter() {
             let target = square as i8 + offset;
-            if target < 0 || target >= 64 { continue; }
+            if !(0..64).contains(&target) { continue; }
 
             let from_file = square % 8;
             let to_file = target as u8 % 8;
@@ -297,32 +526,43 @@ impl NaiveMoveGenerator {
                 to: Square(target as u8),
                 promotion: None,
                 captures: if target_mask & enemy_mask != 0 { board.piece_at(Square(target as u8)) } else { None },
+                castling: None,
             });
         }
 
-        // Castling
+        self.generate_castling_moves(board, square, color, moves);
+    }
+
+    /// Castling, the king and rook may start anywhere on the back rank in Chess960.
+    fn generate_castling_moves(&self, board: &BitboardBoard, square: u8, color: Color, moves: &mut MoveList) {
+        let current_piece = Piece { color, kind: PieceKind::King };
         let all_pieces = board.get_all_pieces_mask();
-        if color == Color::White {
-            if board.castling_rights & CASTLE_WHITE_KINGSIDE != 0 {
-                if all_pieces & 0x60 == 0 {
-                    moves.push(Move::new(Square(4), Square(6), current_piece, None, None));
-                }
-            }
-            if board.castling_rights & CASTLE_WHITE_QUEENSIDE != 0 {
-                if all_pieces & 0xE == 0 {
-                    moves.push(Move::new(Square(4), Square(2), current_piece, None, None));
-                }
-            }
-        } else {
-            if board.castling_rights & CASTLE_BLACK_KINGSIDE != 0 {
-                if all_pieces & 0x6000000000000000 == 0 {
-                    moves.push(Move::new(Square(60), Square(62), current_piece, None, None));
-                }
+        let own_rooks = board.bitboards[if color == Color::White { 3 } else { 9 }];
+        let (base, rights) = match color {
+            Color::White => (0, [CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE]),
+            Color::Black => (56, [CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE]),
+        };
+        for right in rights {
+            let rook = board.castling_rook(right);
+            if board.castling_rights & right == 0 || own_rooks & (1u64 << rook.0) == 0 {
+                continue;
             }
-            if board.castling_rights & CASTLE_BLACK_QUEENSIDE != 0 {
-                if all_pieces & 0xE00000000000000 == 0 {
-                    moves.push(Move::new(Square(60), Square(58), current_piece, None, None));
-                }
+            let kingside = right & (CASTLE_WHITE_KINGSIDE | CASTLE_BLACK_KINGSIDE) != 0;
+            let (king_to, rook_to) = if kingside { (base + 6, base + 5) } else { (base + 2, base + 3) };
+
+            let squares = [square, rook.0, king_to, rook_to];
+            let (low, high) = (*squares.iter().min().unwrap(), *squares.iter().max().unwrap());
+            let span = (u64::MAX >> (63 - high)) & (u64::MAX << low);
+            let blockers = all_pieces & !(1u64 << square) & !(1u64 << rook.0);
+            if span & blockers == 0 {
+                moves.push(Move {
+                    piece: current_piece,
+                    from: Square(square),
+                    to: Square(king_to),
+                    promotion: None,
+                    captures: None,
+                    castling: Some(rook),
+                });
             }
         }
     }
@@ -332,7 +572,7 @@ impl NaiveMoveGenerator {
         board: &BitboardBoard,
         square: u8,
         color: Color,
-        moves: &mut Vec<Move>,
+        moves: &mut MoveList,
     ) {
         self.generate_rook_moves(board, square, color, moves);
         self.generate_bishop_moves(board, square, color, moves);
@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+use crate::board::{BitboardBoard, Board};
+use crate::evaluation::SimpleEvaluator;
+use super::{AlphaBetaSearcher, Searcher};
+
+pub const BENCH_DEPTH: u8 = 5;
+
+/// A mix of opening, middlegame and endgame positions.
+pub const BENCH_POSITIONS: [&str; 6] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bq1rk1/pp2ppbp/2np1np1/8/3NP3/2N1BP2/PPPQ2PP/R3KB1R w KQ - 3 9",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+];
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BenchResult {
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl BenchResult {
+    pub fn nodes_per_second(&self) -> u64 {
+        (self.nodes as f64 / self.time.as_secs_f64().max(1e-9)) as u64
+    }
+}
+
+/// Searches every bench position to `depth`, calling `on_position` after each one.
+pub fn run_bench(depth: u8, on_position: &mut dyn FnMut(&str, &BenchResult)) -> BenchResult {
+    let searcher = AlphaBetaSearcher::new(depth);
+    let mut total = BenchResult::default();
+    for fen in BENCH_POSITIONS {
+        let mut board = BitboardBoard::new_empty(None);
+        board.load_fen(fen);
+        let start = Instant::now();
+        searcher.search(&board, &SimpleEvaluator);
+        let result = BenchResult { nodes: searcher.nodes(), time: start.elapsed() };
+        on_position(fen, &result);
+        total.nodes += result.nodes;
+        total.time += result.time;
+    }
+    total
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use crate::board::Board;
+use crate::board::types::Move;
+use crate::move_generation::{GenerationMode, MoveList};
+
+/// A proven mate: the attacker's key move and, for every defence, the mate that follows it.
+/// A key without replies mates at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MateTree {
+    pub key: Move,
+    pub replies: Vec<(Move, MateTree)>,
+}
+
+impl MateTree {
+    /// Attacker moves needed against the best defence.
+    pub fn length(&self) -> u8 {
+        1 + self.replies.iter().map(|(_, tree)| tree.length()).max().unwrap_or(0)
+    }
+}
+
+/// Why an attacking move fails: the first defence that escapes, `None` when the move stalemates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Refutation {
+    pub attack: Move,
+    pub defence: Option<Move>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MateResult {
+    /// Mate in `moves`, the shortest there is.
+    Mate { moves: u8, tree: MateTree },
+    /// No mate within the asked number of moves, with the refutation of every try.
+    NoMate { refutations: Vec<Refutation> },
+    /// The node limit ran out before the question was settled.
+    Unknown,
+}
+
+/// What is known about one position with the attacker to move.
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    /// Mates in this many moves, or `u8::MAX` when not proven.
+    mate_in: u8,
+    /// Has no mate in this many moves or fewer.
+    no_mate_in: u8,
+}
+
+/// Proves or refutes a forced mate in N moves. The attacker tries checks only, or every move
+/// when asked to, while the defender tries every legal reply. Results are cached by position hash.
+pub struct MateSolver {
+    checks_only: bool,
+    node_limit: Option<u64>,
+    nodes: u64,
+    stopped: bool,
+    table: HashMap<u64, Bounds>,
+}
+
+impl MateSolver {
+    pub fn new(checks_only: bool) -> Self {
+        Self { checks_only, node_limit: None, nodes: 0, stopped: false, table: HashMap::new() }
+    }
+
+    pub fn with_node_limit(mut self, nodes: u64) -> Self {
+        self.node_limit = Some(nodes);
+        self
+    }
+
+    /// Nodes visited by the last `solve`.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Looks for the shortest mate of at most `moves` moves for the side to move. Nothing mates
+    /// in zero moves.
+    pub fn solve<B: Board>(&mut self, board: &B, moves: u8) -> MateResult {
+        self.nodes = 0;
+        self.stopped = false;
+        self.table.clear();
+        if moves == 0 {
+            return MateResult::NoMate { refutations: Vec::new() };
+        }
+        let mut board = board.clone();
+
+        for n in 1..=moves {
+            if self.attack(&mut board, n) {
+                // the proof is in the table, building the tree only reads it back
+                let limit = self.node_limit.take();
+                let tree = self.tree(&mut board, n);
+                self.node_limit = limit;
+                return match tree {
+                    Some(tree) => MateResult::Mate { moves: n, tree },
+                    None => MateResult::Unknown,
+                };
+            }
+            if self.stopped {
+                return MateResult::Unknown;
+            }
+        }
+
+        let limit = self.node_limit.take();
+        let refutations = self.attacking_moves(&mut board, moves)
+            .iter()
+            .map(|attack| Refutation { attack: *attack, defence: self.refute(&mut board, attack, moves) })
+            .collect();
+        self.node_limit = limit;
+        MateResult::NoMate { refutations }
+    }
+
+    fn tick(&mut self) {
+        self.nodes += 1;
+        if self.node_limit.is_some_and(|limit| self.nodes > limit) {
+            self.stopped = true;
+        }
+    }
+
+    /// Checks first, captures among them leading, then other captures and quiet moves. On the
+    /// last move only checks can mate.
+    fn attacking_moves<B: Board>(&self, board: &mut B, n: u8) -> Vec<Move> {
+        let captures = board.generate_legal_moves_of(GenerationMode::Captures);
+        let (mut moves, other_captures): (Vec<Move>, Vec<Move>) = captures.into_iter().partition(|mv| board.gives_check(mv));
+        moves.extend(board.generate_legal_moves_of(GenerationMode::QuietChecks));
+        if !self.checks_only && n > 1 {
+            let quiets = board.generate_legal_moves_of(GenerationMode::Quiets);
+            moves.extend(other_captures);
+            moves.extend(quiets.into_iter().filter(|mv| !board.gives_check(mv)));
+        }
+        moves
+    }
+
+    /// Whether the side to move mates in `n` moves or fewer.
+    fn attack<B: Board>(&mut self, board: &mut B, n: u8) -> bool {
+        if n == 0 {
+            return false;
+        }
+        let hash = board.hash();
+        if let Some(bounds) = self.table.get(&hash) {
+            if bounds.mate_in <= n {
+                return true;
+            }
+            if bounds.no_mate_in >= n {
+                return false;
+            }
+        }
+        self.tick();
+        if self.stopped {
+            return false;
+        }
+
+        let mut mates = false;
+        for mv in self.attacking_moves(board, n) {
+            board.make_move(&mv);
+            mates = self.defend(board, n);
+            board.unmake_move(&mv);
+            if mates || self.stopped {
+                break;
+            }
+        }
+        if self.stopped {
+            return false;
+        }
+
+        let bounds = self.table.entry(hash).or_insert(Bounds { mate_in: u8::MAX, no_mate_in: 0 });
+        if mates {
+            bounds.mate_in = bounds.mate_in.min(n);
+        } else {
+            bounds.no_mate_in = bounds.no_mate_in.max(n);
+        }
+        mates
+    }
+
+    /// Whether every reply of the side to move still loses to a mate within `n - 1` more moves.
+    fn defend<B: Board>(&mut self, board: &mut B, n: u8) -> bool {
+        self.tick();
+        let replies: MoveList = board.generate_moves();
+        if replies.is_empty() {
+            return board.is_in_check(board.side_to_move());
+        }
+        if n == 1 {
+            return false;
+        }
+        for reply in replies {
+            board.make_move(&reply);
+            let mated = self.attack(board, n - 1);
+            board.unmake_move(&reply);
+            if !mated {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn refute<B: Board>(&mut self, board: &mut B, attack: &Move, n: u8) -> Option<Move> {
+        board.make_move(attack);
+        let mut defence = None;
+        for reply in board.generate_moves() {
+            board.make_move(&reply);
+            let mated = self.attack(board, n - 1);
+            board.unmake_move(&reply);
+            if !mated {
+                defence = Some(reply);
+                break;
+            }
+        }
+        board.unmake_move(attack);
+        defence
+    }
+
+    /// The mating tree of a position proven to mate in `n`, following the shortest mate after
+    /// every defence. `None` if the proof can't be read back, which would be a solver bug.
+    fn tree<B: Board>(&mut self, board: &mut B, n: u8) -> Option<MateTree> {
+        for key in self.attacking_moves(board, n) {
+            board.make_move(&key);
+            if self.defend(board, n) {
+                let mut replies = Vec::new();
+                for reply in board.generate_moves() {
+                    board.make_move(&reply);
+                    let subtree = (1..n).find(|&k| self.attack(board, k)).and_then(|k| self.tree(board, k));
+                    board.unmake_move(&reply);
+                    let Some(subtree) = subtree else {
+                        board.unmake_move(&key);
+                        return None;
+                    };
+                    replies.push((reply, subtree));
+                }
+                board.unmake_move(&key);
+                return Some(MateTree { key, replies });
+            }
+            board.unmake_move(&key);
+        }
+        None
+    }
+}
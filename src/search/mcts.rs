@@ -0,0 +1,378 @@
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+use crate::board::Board;
+use crate::board::types::Move;
+use crate::evaluation::Evaluator;
+use crate::utils::random::Random;
+use super::{SearchLine, Searcher};
+
+pub const DEFAULT_ITERATIONS: u64 = 10_000;
+pub const DEFAULT_EXPLORATION: f64 = 1.4;
+/// Centipawns that make a side roughly ten times as likely to win as to lose.
+const PROBABILITY_SCALE: f64 = 400.0;
+/// Softmax temperature in centipawns for the PUCT move priors.
+const PRIOR_TEMPERATURE: f64 = 200.0;
+
+/// How a child is picked on the way down the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// UCB1 on the mean result, unvisited children first.
+    Uct,
+    /// Exploration weighted by move priors from a one ply evaluation of every child.
+    Puct,
+}
+
+/// How a new leaf is valued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafValue {
+    /// The evaluator's score turned into a win probability.
+    Evaluation,
+    /// A random playout of at most this many plies, scored by the evaluator if it does not end.
+    Rollout(u16),
+}
+
+/// Evaluator score to the side to move's expected result between 0 and 1.
+pub fn win_probability(score: i32) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-score as f64 / PROBABILITY_SCALE))
+}
+
+fn probability_to_score(probability: f64) -> i32 {
+    let p = probability.clamp(0.001, 0.999);
+    (PROBABILITY_SCALE * (p / (1.0 - p)).log10()).round() as i32
+}
+
+struct Node {
+    mv: Option<Move>,
+    hash: u64,
+    children: Vec<usize>,
+    expanded: bool,
+    /// Result for the side to move when the game is over here.
+    terminal: Option<f64>,
+    prior: f64,
+    visits: u32,
+    /// Sum of results for the side that played `mv`.
+    value: f64,
+}
+
+impl Node {
+    fn new(mv: Option<Move>, hash: u64, prior: f64) -> Self {
+        Self { mv, hash, children: Vec::new(), expanded: false, terminal: None, prior, visits: 0, value: 0.0 }
+    }
+
+    fn mean(&self) -> f64 {
+        if self.visits == 0 { 0.5 } else { self.value / self.visits as f64 }
+    }
+}
+
+/// The search tree, root at index 0.
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn new(hash: u64) -> Self {
+        Self { nodes: vec![Node::new(None, hash, 1.0)] }
+    }
+
+    /// Copies the subtree below `index` into a tree of its own.
+    fn subtree(&self, index: usize) -> Self {
+        let mut tree = Self { nodes: Vec::new() };
+        tree.copy_node(self, index);
+        tree.nodes[0].mv = None;
+        tree
+    }
+
+    fn copy_node(&mut self, from: &Tree, index: usize) -> usize {
+        let node = &from.nodes[index];
+        let copy = self.nodes.len();
+        self.nodes.push(Node { children: Vec::new(), ..*node });
+        for &child in &node.children {
+            let child = self.copy_node(from, child);
+            self.nodes[copy].children.push(child);
+        }
+        copy
+    }
+
+    /// The node for a position at most two plies below the root, so the tree survives our move
+    /// and the opponent's reply.
+    fn find(&self, hash: u64) -> Option<usize> {
+        let root = &self.nodes[0];
+        if root.hash == hash {
+            return Some(0);
+        }
+        root.children.iter()
+            .flat_map(|&child| std::iter::once(child).chain(self.nodes[child].children.iter().copied()))
+            .find(|&index| self.nodes[index].hash == hash)
+    }
+
+    /// Whether an expanded root has exactly the board's moves, which catches a hash collision
+    /// before a stale tree is searched on.
+    fn matches<B: Board>(&self, board: &B) -> bool {
+        let root = &self.nodes[0];
+        if !root.expanded || root.terminal.is_some() {
+            return true;
+        }
+        let moves = board.generate_moves();
+        root.children.len() == moves.len() && root.children.iter().all(|&child| moves.contains(&self.nodes[child].mv.unwrap()))
+    }
+
+    fn most_visited(&self, index: usize) -> Option<usize> {
+        self.nodes[index].children.iter().copied().max_by_key(|&child| self.nodes[child].visits)
+    }
+
+    fn line(&self, first: usize) -> Vec<Move> {
+        let mut pv = vec![self.nodes[first].mv.unwrap()];
+        let mut index = first;
+        while let Some(child) = self.most_visited(index).filter(|&child| self.nodes[child].visits > 0) {
+            pv.push(self.nodes[child].mv.unwrap());
+            index = child;
+        }
+        pv
+    }
+}
+
+/// Monte Carlo tree search. The tree of the last search is kept, and when the next position is
+/// our own or one reached by two plies from it the search carries on from there.
+pub struct MctsSearcher {
+    selection: Selection,
+    leaf_value: LeafValue,
+    exploration: f64,
+    iterations: u64,
+    time_limit: Option<Duration>,
+    reuse_tree: bool,
+    rng: RefCell<Random>,
+    tree: RefCell<Option<Tree>>,
+    /// Visits the root already had from the previous search.
+    reused_visits: Cell<u32>,
+}
+
+impl Default for MctsSearcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_ITERATIONS)
+    }
+}
+
+impl<B: Board> Searcher<B> for MctsSearcher {
+    /// Root moves are ordered by visits, scores are the mean results turned back into centipawns.
+    fn search_lines(
+        &self,
+        board: &B,
+        evaluator: &dyn Evaluator<B>,
+        multi_pv: usize,
+        on_iteration: &mut dyn FnMut(&[SearchLine]),
+    ) -> Vec<SearchLine> {
+        let hash = board.hash();
+        let reused = self.tree.borrow_mut().take()
+            .filter(|_| self.reuse_tree)
+            .and_then(|tree| tree.find(hash).map(|index| tree.subtree(index)))
+            .filter(|tree| tree.matches(board));
+        let mut tree = reused.unwrap_or_else(|| Tree::new(hash));
+        self.reused_visits.set(tree.nodes[0].visits);
+
+        let deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        let mut board = board.clone();
+        for iteration in 0..self.iterations {
+            if iteration % 64 == 0 && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+            self.iterate(&mut tree, &mut board, evaluator);
+            // a position without moves is settled by the first iteration
+            if tree.nodes[0].terminal.is_some() {
+                break;
+            }
+        }
+
+        let mut roots = tree.nodes[0].children.clone();
+        roots.sort_by_key(|&child| std::cmp::Reverse(tree.nodes[child].visits));
+        let lines: Vec<SearchLine> = roots.iter()
+            .take(multi_pv.max(1))
+            .map(|&child| {
+                let pv = tree.line(child);
+                SearchLine { depth: pv.len().min(u8::MAX as usize) as u8, score: probability_to_score(tree.nodes[child].mean()), pv }
+            })
+            .collect();
+        if !lines.is_empty() {
+            on_iteration(&lines);
+        }
+        *self.tree.borrow_mut() = Some(tree);
+        lines
+    }
+
+    /// Forgets the kept tree.
+    fn clear(&self) {
+        *self.tree.borrow_mut() = None;
+    }
+}
+
+impl MctsSearcher {
+    pub fn new(iterations: u64) -> Self {
+        Self {
+            selection: Selection::Puct,
+            leaf_value: LeafValue::Evaluation,
+            exploration: DEFAULT_EXPLORATION,
+            iterations: iterations.max(1),
+            time_limit: None,
+            reuse_tree: true,
+            rng: RefCell::new(Random::from_time()),
+            tree: RefCell::new(None),
+            reused_visits: Cell::new(0),
+        }
+    }
+
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    pub fn with_leaf_value(mut self, leaf_value: LeafValue) -> Self {
+        self.leaf_value = leaf_value;
+        self
+    }
+
+    /// The exploration constant, `c` in UCT and `c_puct` in PUCT.
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Stops before the iteration limit once `time` has passed.
+    pub fn with_time_limit(mut self, time: Duration) -> Self {
+        self.time_limit = Some(time);
+        self
+    }
+
+    pub fn with_tree_reuse(mut self, reuse_tree: bool) -> Self {
+        self.reuse_tree = reuse_tree;
+        self
+    }
+
+    /// Seeds the rollouts for reproducible searches.
+    pub fn with_seed(self, seed: u64) -> Self {
+        *self.rng.borrow_mut() = Random::new(seed);
+        self
+    }
+
+    /// Root visits carried over from the previous search.
+    pub fn reused_visits(&self) -> u32 {
+        self.reused_visits.get()
+    }
+
+    /// Root visits after the last search.
+    pub fn visits(&self) -> u32 {
+        self.tree.borrow().as_ref().map_or(0, |tree| tree.nodes[0].visits)
+    }
+
+    /// One selection, expansion, evaluation and backup pass from the root.
+    fn iterate<B: Board>(&self, tree: &mut Tree, board: &mut B, evaluator: &dyn Evaluator<B>) {
+        let mut path = vec![0];
+        let mut moves = Vec::new();
+        let mut index = 0;
+        while tree.nodes[index].expanded && tree.nodes[index].terminal.is_none() {
+            index = self.select(tree, index);
+            let mv = tree.nodes[index].mv.unwrap();
+            board.make_move(&mv);
+            moves.push(mv);
+            path.push(index);
+        }
+
+        // result for the side to move at the leaf
+        let mut result = match tree.nodes[index].terminal {
+            Some(result) => result,
+            None => {
+                self.expand(tree, index, board, evaluator);
+                match tree.nodes[index].terminal {
+                    Some(result) => result,
+                    None => self.leaf_value(board, evaluator),
+                }
+            }
+        };
+
+        for mv in moves.iter().rev() {
+            board.unmake_move(mv);
+        }
+        for &index in path.iter().rev() {
+            let node = &mut tree.nodes[index];
+            node.visits += 1;
+            node.value += 1.0 - result;
+            result = 1.0 - result;
+        }
+    }
+
+    fn select(&self, tree: &Tree, index: usize) -> usize {
+        let parent = &tree.nodes[index];
+        let parent_visits = parent.visits.max(1) as f64;
+        let score = |child: &Node| match self.selection {
+            Selection::Uct if child.visits == 0 => f64::INFINITY,
+            Selection::Uct => child.mean() + self.exploration * (parent_visits.ln() / child.visits as f64).sqrt(),
+            Selection::Puct => {
+                // unvisited children count as a draw
+                child.mean() + self.exploration * child.prior * parent_visits.sqrt() / (1.0 + child.visits as f64)
+            }
+        };
+        parent.children.iter().copied()
+            .max_by(|&a, &b| score(&tree.nodes[a]).total_cmp(&score(&tree.nodes[b])))
+            .unwrap()
+    }
+
+    fn expand<B: Board>(&self, tree: &mut Tree, index: usize, board: &mut B, evaluator: &dyn Evaluator<B>) {
+        tree.nodes[index].expanded = true;
+        let moves = board.generate_moves();
+        if moves.is_empty() {
+            let mated = board.is_in_check(board.side_to_move());
+            tree.nodes[index].terminal = Some(if mated { 0.0 } else { 0.5 });
+            return;
+        }
+        if board.halfmove_clock() >= 100 {
+            tree.nodes[index].terminal = Some(0.5);
+            return;
+        }
+
+        let mut children = Vec::with_capacity(moves.len());
+        for mv in moves {
+            board.make_move(&mv);
+            let hash = board.hash();
+            let prior = match self.selection {
+                Selection::Puct => -evaluator.evaluate(board) as f64 / PRIOR_TEMPERATURE,
+                Selection::Uct => 0.0,
+            };
+            board.unmake_move(&mv);
+            children.push(Node::new(Some(mv), hash, prior));
+        }
+        // softmax over the one ply scores
+        let max = children.iter().map(|child| child.prior).fold(f64::MIN, f64::max);
+        let total: f64 = children.iter().map(|child| (child.prior - max).exp()).sum();
+        for mut child in children {
+            child.prior = (child.prior - max).exp() / total;
+            let child_index = tree.nodes.len();
+            tree.nodes.push(child);
+            tree.nodes[index].children.push(child_index);
+        }
+    }
+
+    fn leaf_value<B: Board>(&self, board: &mut B, evaluator: &dyn Evaluator<B>) -> f64 {
+        let LeafValue::Rollout(max_plies) = self.leaf_value else {
+            return win_probability(evaluator.evaluate(board));
+        };
+
+        let mut rng = self.rng.borrow_mut();
+        let mut played = Vec::new();
+        let mut result = None;
+        for _ in 0..max_plies {
+            let moves = board.generate_moves();
+            if moves.is_empty() {
+                result = Some(if board.is_in_check(board.side_to_move()) { 0.0 } else { 0.5 });
+                break;
+            }
+            let Some(mv) = moves.get(rng.next_below(moves.len() as u64) as usize) else { break };
+            board.make_move(&mv);
+            played.push(mv);
+        }
+        let result = result.unwrap_or_else(|| win_probability(evaluator.evaluate(board)));
+        // back to the leaf's side to move
+        let result = if played.len() % 2 == 0 { result } else { 1.0 - result };
+        for mv in played.iter().rev() {
+            board.unmake_move(mv);
+        }
+        result
+    }
+}
@@ -1,67 +1,362 @@
+pub mod bench;
+pub mod mate;
+pub mod mcts;
+pub mod proof_number;
+pub mod skill;
+pub mod testsuite;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use crate::board::{BitboardBoard, Board};
-use crate::board::types::{Move, GameState};
-use crate::evaluation::{Evaluator, SimpleEvaluator};
+use crate::board::types::{CompactMove, Move, PieceKind};
+use crate::move_generation::{GenerationMode, MoveList};
+use crate::evaluation::Evaluator;
+use crate::tablebase::{SyzygyTablebase, Wdl};
+
+pub const DEFAULT_SEARCH_DEPTH: u8 = 4;
+/// Depth used when a search is bounded by nodes or time instead.
+pub const MAX_SEARCH_DEPTH: u8 = 64;
+pub const MATE_SCORE: i32 = i32::MAX - 100;
+pub const TB_WIN_SCORE: i32 = MATE_SCORE - 2000;
 
-const SEARCH_DEPTH: u8 = 4;
+/// One principal variation found by the search, scored from the side to move's point of view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchLine {
+    pub depth: u8,
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
 
-pub trait Searcher {
-    fn search(&self, board: &BitboardBoard, evaluator: &dyn Evaluator) -> Option<Move>;
+impl SearchLine {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
 }
 
-pub struct AlphaBetaSearcher;
+pub trait Searcher<B: Board = BitboardBoard> {
+    /// Searches the `multi_pv` best root moves. `on_iteration` is called with the lines of every
+    /// completed iteration, best line first.
+    fn search_lines(
+        &self,
+        board: &B,
+        evaluator: &dyn Evaluator<B>,
+        multi_pv: usize,
+        on_iteration: &mut dyn FnMut(&[SearchLine]),
+    ) -> Vec<SearchLine>;
+
+    fn search(&self, board: &B, evaluator: &dyn Evaluator<B>) -> Option<Move> {
+        self.search_lines(board, evaluator, 1, &mut |_| {})
+            .first()
+            .and_then(SearchLine::best_move)
+    }
+
+    /// Hands over Syzygy tables to probe. Searchers that cannot use them ignore this.
+    fn set_tablebase(&mut self, _tablebase: Option<Arc<SyzygyTablebase>>) {}
+
+    /// Forgets whatever was kept from earlier searches, for a new game or an unrelated position.
+    fn clear(&self) {}
+}
+
+/// A shared searcher, so its state can still be looked at while a game owns it.
+impl<B: Board, S: Searcher<B> + ?Sized> Searcher<B> for Rc<S> {
+    fn search_lines(
+        &self,
+        board: &B,
+        evaluator: &dyn Evaluator<B>,
+        multi_pv: usize,
+        on_iteration: &mut dyn FnMut(&[SearchLine]),
+    ) -> Vec<SearchLine> {
+        (**self).search_lines(board, evaluator, multi_pv, on_iteration)
+    }
+
+    fn set_tablebase(&mut self, tablebase: Option<Arc<SyzygyTablebase>>) {
+        if let Some(searcher) = Rc::get_mut(self) {
+            searcher.set_tablebase(tablebase);
+        }
+    }
+
+    fn clear(&self) {
+        (**self).clear();
+    }
+}
+
+/// Root moves from the previous iteration are tried before everything else.
+const PREVIOUS_LINE_SCORE: i32 = 1_000_000;
+
+fn piece_order(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => 1,
+        PieceKind::Knight => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Rook => 4,
+        PieceKind::Queen => 5,
+        PieceKind::King => 6,
+    }
+}
+
+/// Orders captures by most valuable victim, least valuable attacker, and promotions by the
+/// new piece, ahead of quiet moves.
+fn score_moves(moves: &mut MoveList) {
+    moves.score_each(|mv| {
+        let mut score = 0;
+        if let Some(captured) = mv.captures {
+            score += 100 + 10 * piece_order(captured.kind) - piece_order(mv.piece.kind);
+        }
+        if let Some(promotion) = mv.promotion {
+            score += 50 + piece_order(promotion);
+        }
+        score
+    });
+}
+
+pub struct AlphaBetaSearcher {
+    depth: u8,
+    node_limit: Option<u64>,
+    time_limit: Option<Duration>,
+    tablebase: Option<Arc<SyzygyTablebase>>,
+    nodes: Cell<u64>,
+}
+
+struct SearchContext<'a, B: Board> {
+    evaluator: &'a dyn Evaluator<B>,
+    nodes: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    stopped: bool,
+}
+
+impl<B: Board> SearchContext<'_, B> {
+    /// Counts a node, returns true once the search has to stop.
+    fn tick(&mut self) -> bool {
+        self.nodes += 1;
+        if self.node_limit.is_some_and(|limit| self.nodes > limit)
+            || (self.nodes.is_multiple_of(256) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline)) {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+}
+
+impl Default for AlphaBetaSearcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEARCH_DEPTH)
+    }
+}
+
+impl<B: Board> Searcher<B> for AlphaBetaSearcher {
+    fn search_lines(
+        &self,
+        board: &B,
+        evaluator: &dyn Evaluator<B>,
+        multi_pv: usize,
+        on_iteration: &mut dyn FnMut(&[SearchLine]),
+    ) -> Vec<SearchLine> {
+        let mut root_moves = board.generate_moves();
+        if let Some(filtered) = self.tablebase.as_ref().and_then(|tb| tb.filter_root_moves(&board.to_bitboard(), &root_moves)) {
+            root_moves = filtered.into_iter().collect();
+        }
+
+        let mut ctx = SearchContext {
+            evaluator,
+            nodes: 0,
+            node_limit: self.node_limit,
+            deadline: self.time_limit.map(|limit| Instant::now() + limit),
+            stopped: false,
+        };
+
+        // the whole search runs on one board with make/unmake
+        let mut board = board.clone();
+        let mut lines: Vec<SearchLine> = Vec::new();
+        for depth in 1..=self.depth {
+            // try the previous iteration's lines first, in their order, and the rest as usual
+            score_moves(&mut root_moves);
+            for i in 0..root_moves.len() {
+                if let Some(position) = lines.iter().position(|l| l.best_move() == root_moves.get(i)) {
+                    root_moves.set_score(i, PREVIOUS_LINE_SCORE - position as i32);
+                }
+            }
+            root_moves.sort();
 
-impl Searcher for AlphaBetaSearcher {
-    fn search(&self, board: &BitboardBoard, evaluator: &dyn Evaluator) -> Option<Move> {
-        let mut best_move = None;
+            let mut iteration_lines: Vec<SearchLine> = Vec::new();
+            while iteration_lines.len() < multi_pv {
+                let excluded: Vec<Move> = iteration_lines.iter().filter_map(SearchLine::best_move).collect();
+                match self.search_root(&mut ctx, &mut board, depth, &root_moves, &excluded) {
+                    Some(line) if !ctx.stopped => iteration_lines.push(line),
+                    // an interrupted first iteration is still better than no move at all
+                    Some(line) if lines.is_empty() && iteration_lines.is_empty() => iteration_lines.push(line),
+                    _ => break,
+                }
+            }
+
+            if ctx.stopped && !lines.is_empty() {
+                break;
+            }
+            lines = iteration_lines;
+            on_iteration(&lines);
+            if ctx.stopped {
+                break;
+            }
+        }
+
+        self.nodes.set(ctx.nodes);
+        lines
+    }
+
+    fn set_tablebase(&mut self, tablebase: Option<Arc<SyzygyTablebase>>) {
+        self.tablebase = tablebase;
+    }
+}
+
+impl AlphaBetaSearcher {
+    pub fn new(depth: u8) -> Self {
+        Self { depth: depth.max(1), node_limit: None, time_limit: None, tablebase: None, nodes: Cell::new(0) }
+    }
+
+    /// Nodes visited by the last search.
+    pub fn nodes(&self) -> u64 {
+        self.nodes.get()
+    }
+
+    /// Stops the search after roughly `nodes` nodes, keeping the last finished iteration.
+    pub fn with_node_limit(mut self, nodes: u64) -> Self {
+        self.node_limit = Some(nodes);
+        self
+    }
+
+    pub fn with_time_limit(mut self, time: Duration) -> Self {
+        self.time_limit = Some(time);
+        self
+    }
+
+    /// Filters root moves by DTZ and uses WDL probes inside the search once few enough
+    /// pieces are left.
+    pub fn with_tablebase(mut self, tablebase: Option<Arc<SyzygyTablebase>>) -> Self {
+        self.tablebase = tablebase;
+        self
+    }
+
+    fn search_root<B: Board>(
+        &self,
+        ctx: &mut SearchContext<B>,
+        board: &mut B,
+        depth: u8,
+        root_moves: &MoveList,
+        excluded: &[Move],
+    ) -> Option<SearchLine> {
+        let mut best_line = None;
         let mut best_score = -i32::MAX;
         let mut alpha = -i32::MAX;
         let beta = i32::MAX;
+        let mut child_pv = Vec::new();
 
-        let mut moves = board.generate_moves();
-        moves.sort_by_key(|m| m.captures.is_some());
-
-        for mv in moves {
-            let mut new_board = board.clone();
-            new_board.make_move(&mv);
-            new_board.switch_side();
-            let score = -self.alphabeta(&new_board, evaluator, SEARCH_DEPTH - 1, -beta, -alpha);
-            if score > best_score {
+        for mv in root_moves.iter().filter(|m| !excluded.contains(m)) {
+            board.make_move(&mv);
+            let score = -self.alphabeta(ctx, board, depth - 1, -beta, -alpha, &mut child_pv);
+            board.unmake_move(&mv);
+            if ctx.stopped && best_line.is_some() {
+                break;
+            }
+            if best_line.is_none() || score > best_score {
                 best_score = score;
-                best_move = Some(mv);
+                let pv = Self::expand_pv(board, &mv, &child_pv);
+                best_line = Some(SearchLine { depth, score, pv });
             }
             alpha = alpha.max(best_score);
         }
 
-        best_move
+        best_line
     }
-}
 
-impl AlphaBetaSearcher {
-    fn alphabeta(&self, board: &BitboardBoard, evaluator: &dyn Evaluator, depth: u8, mut alpha: i32, beta: i32) -> i32 {
+    /// Turns the compact continuation stored during the search back into full moves.
+    fn expand_pv<B: Board>(board: &mut B, first: &Move, rest: &[CompactMove]) -> Vec<Move> {
+        let mut pv = vec![*first];
+        board.make_move(first);
+        for &compact in rest {
+            let Some(mv) = board.expand_move(compact) else { break };
+            board.make_move(&mv);
+            pv.push(mv);
+        }
+        for mv in pv.iter().rev() {
+            board.unmake_move(mv);
+        }
+        pv
+    }
+
+    /// Only boards with few enough pieces are converted for the probe.
+    fn probe_wdl<B: Board>(tablebase: &SyzygyTablebase, board: &B) -> Option<Wdl> {
+        let pieces: usize = board.scores().piece_counts.iter().map(|&count| count as usize).sum();
+        if pieces > tablebase.cardinality() {
+            return None;
+        }
+        let board = board.to_bitboard();
+        if !tablebase.covers(&board) {
+            return None;
+        }
+        tablebase.probe_wdl(&board)
+    }
+
+    fn alphabeta<B: Board>(
+        &self,
+        ctx: &mut SearchContext<B>,
+        board: &mut B,
+        depth: u8,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<CompactMove>,
+    ) -> i32 {
+        pv.clear();
+        if ctx.tick() {
+            return 0;
+        }
+
+        if let Some(tablebase) = &self.tablebase
+            && let Some(wdl) = Self::probe_wdl(tablebase, board) {
+            // prefer wins found closer to the root
+            return match wdl {
+                Wdl::Win => TB_WIN_SCORE + depth as i32,
+                Wdl::Loss => -TB_WIN_SCORE - depth as i32,
+                _ => 0,
+            };
+        }
+
         if depth == 0 {
-            return evaluator.evaluate(board);
+            return ctx.evaluator.evaluate(board);
         }
 
-        let mut moves = board.generate_moves();
+        // in check only evasions need to be generated
+        let in_check = board.is_in_check(board.side_to_move());
+        let mut moves = board.generate_legal_moves_of(if in_check { GenerationMode::Evasions } else { GenerationMode::All });
         if moves.is_empty() {
-            if board.is_in_check(board.side_to_move()) {
-                return -i32::MAX + 100; // Checkmate
+            if in_check {
+                return -MATE_SCORE; // Checkmate
             } else {
                 return 0; // Stalemate
             }
         }
 
-        moves.sort_by_key(|m| m.captures.is_some());
+        score_moves(&mut moves);
 
-        for mv in moves {
-            let mut new_board = board.clone();
-            new_board.make_move(&mv);
-            new_board.switch_side();
-            let score = -self.alphabeta(&new_board, evaluator, depth - 1, -beta, -alpha);
+        let mut child_pv = Vec::new();
+        let mut index = 0;
+        while let Some(mv) = moves.select_best(index) {
+            index += 1;
+            board.make_move(&mv);
+            let score = -self.alphabeta(ctx, board, depth - 1, -beta, -alpha, &mut child_pv);
+            board.unmake_move(&mv);
+            if ctx.stopped {
+                return 0;
+            }
             if score >= beta {
                 return beta; // Pruning
             }
-            alpha = alpha.max(score);
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(moves.compact(index - 1));
+                pv.extend_from_slice(&child_pv);
+            }
         }
 
         alpha
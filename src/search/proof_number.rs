@@ -0,0 +1,303 @@
+use std::cell::Cell;
+use crate::board::Board;
+use crate::board::types::{Color, Move};
+use crate::evaluation::Evaluator;
+use super::{SearchLine, Searcher, MATE_SCORE};
+
+pub const DEFAULT_NODE_BUDGET: usize = 200_000;
+pub const DEFAULT_MAX_PLIES: u16 = 24;
+
+const INFINITY: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofResult {
+    /// The side to move forces mate.
+    Win,
+    /// The side to move gets mated.
+    Loss,
+    /// Neither side can force mate and no line reached the ply limit.
+    Draw,
+    /// The node budget or the ply limit left the question open.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    pub result: ProofResult,
+    /// The shortest win against the longest defence for a win or a loss, the longest resistance
+    /// for a draw and the most promising line when nothing was proven.
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+}
+
+/// Proof-number search: two best-first searches, one trying to prove that the side to move
+/// mates and one that the opponent does. Draws are only claimed when both are disproved without
+/// reaching the ply limit.
+pub struct ProofNumberSearcher {
+    node_budget: usize,
+    max_plies: u16,
+    nodes: Cell<u64>,
+}
+
+impl Default for ProofNumberSearcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_NODE_BUDGET)
+    }
+}
+
+impl<B: Board> Searcher<B> for ProofNumberSearcher {
+    /// Reports one line, whatever `multi_pv` asks for. Proven results score as mate or draw,
+    /// unproven ones as 0.
+    fn search_lines(
+        &self,
+        board: &B,
+        _evaluator: &dyn Evaluator<B>,
+        _multi_pv: usize,
+        on_iteration: &mut dyn FnMut(&[SearchLine]),
+    ) -> Vec<SearchLine> {
+        let mut proof = self.prove(board);
+        if proof.pv.is_empty() {
+            // a root that is already drawn still needs a move to play
+            match board.generate_moves().first() {
+                Some(mv) => proof.pv.push(mv),
+                None => return Vec::new(),
+            }
+        }
+        let score = match proof.result {
+            ProofResult::Win => MATE_SCORE,
+            ProofResult::Loss => -MATE_SCORE,
+            ProofResult::Draw | ProofResult::Unknown => 0,
+        };
+        let lines = vec![SearchLine { depth: proof.pv.len().min(u8::MAX as usize) as u8, score, pv: proof.pv }];
+        on_iteration(&lines);
+        lines
+    }
+}
+
+impl ProofNumberSearcher {
+    /// `node_budget` bounds the tree size of both searches together.
+    pub fn new(node_budget: usize) -> Self {
+        Self { node_budget: node_budget.max(1), max_plies: DEFAULT_MAX_PLIES, nodes: Cell::new(0) }
+    }
+
+    /// Lines are cut off after this many plies and count as no mate for either side.
+    pub fn with_max_plies(mut self, plies: u16) -> Self {
+        self.max_plies = plies.max(1);
+        self
+    }
+
+    /// Nodes created by the last search.
+    pub fn nodes(&self) -> u64 {
+        self.nodes.get()
+    }
+
+    pub fn prove<B: Board>(&self, board: &B) -> Proof {
+        let us = board.side_to_move();
+        let mut board = board.clone();
+
+        let mut win = ProofTree::new(us, self.max_plies);
+        let win_status = win.search(&mut board, self.node_budget);
+        let mut nodes = win.nodes.len() as u64;
+        let result = if win_status == Some(true) {
+            Some((ProofResult::Win, win.principal_line()))
+        } else {
+            let mut loss = ProofTree::new(us.opposite(), self.max_plies);
+            let loss_status = loss.search(&mut board, self.node_budget.saturating_sub(win.nodes.len()).max(1));
+            nodes += loss.nodes.len() as u64;
+            if loss_status == Some(true) {
+                Some((ProofResult::Loss, loss.principal_line()))
+            } else if win_status == Some(false) && loss_status == Some(false) && !win.hit_horizon && !loss.hit_horizon {
+                Some((ProofResult::Draw, win.principal_line()))
+            } else {
+                None
+            }
+        };
+
+        self.nodes.set(nodes);
+        let (result, pv) = result.unwrap_or_else(|| (ProofResult::Unknown, win.principal_line()));
+        Proof { result, pv, nodes }
+    }
+}
+
+struct Node {
+    mv: Option<Move>,
+    parent: usize,
+    children: Vec<usize>,
+    /// Nodes where the attacker is to move need one proven child, the others all of them.
+    or_node: bool,
+    proof: u64,
+    disproof: u64,
+    /// Plies to the end of the line once the node is proven or disproven.
+    length: u16,
+}
+
+impl Node {
+    fn is_solved(&self) -> bool {
+        self.proof == 0 || self.disproof == 0
+    }
+}
+
+/// One proof-number search for a win of `attacker`, with the tree kept in an arena.
+struct ProofTree {
+    attacker: Color,
+    max_plies: u16,
+    nodes: Vec<Node>,
+    /// Some line was cut off by the ply limit, so a disproof does not mean a draw.
+    hit_horizon: bool,
+}
+
+impl ProofTree {
+    fn new(attacker: Color, max_plies: u16) -> Self {
+        Self { attacker, max_plies, nodes: Vec::new(), hit_horizon: false }
+    }
+
+    /// `Some(true)` when the attacker's win is proven, `Some(false)` when disproven and `None`
+    /// when the budget ran out first.
+    fn search<B: Board>(&mut self, board: &mut B, budget: usize) -> Option<bool> {
+        let (proof, disproof) = self.evaluate(board, 0, &[]);
+        self.nodes.push(Node {
+            mv: None,
+            parent: 0,
+            children: Vec::new(),
+            or_node: board.side_to_move() == self.attacker,
+            proof,
+            disproof,
+            length: 0,
+        });
+
+        while !self.nodes[0].is_solved() && self.nodes.len() < budget {
+            // walk down to the most proving node
+            let mut index = 0;
+            let mut path = Vec::new();
+            let mut hashes = vec![board.hash()];
+            while !self.nodes[index].children.is_empty() {
+                index = self.select_child(index);
+                let mv = self.nodes[index].mv.unwrap();
+                board.make_move(&mv);
+                hashes.push(board.hash());
+                path.push(mv);
+            }
+
+            self.expand(board, index, &hashes);
+            for mv in path.iter().rev() {
+                board.unmake_move(mv);
+            }
+
+            loop {
+                self.update(index);
+                if index == 0 {
+                    break;
+                }
+                index = self.nodes[index].parent;
+            }
+        }
+
+        let root = &self.nodes[0];
+        if root.proof == 0 {
+            Some(true)
+        } else if root.disproof == 0 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Proof and disproof numbers of a new node, `path` holds the hashes of the positions
+    /// before it for spotting repetitions.
+    fn evaluate<B: Board>(&mut self, board: &B, ply: u16, path: &[u64]) -> (u64, u64) {
+        const PROVEN: (u64, u64) = (0, INFINITY);
+        const DISPROVEN: (u64, u64) = (INFINITY, 0);
+
+        let moves = board.generate_moves();
+        let to_move = board.side_to_move();
+        if moves.is_empty() {
+            let mated = board.is_in_check(to_move);
+            return if mated && to_move != self.attacker { PROVEN } else { DISPROVEN };
+        }
+        if board.halfmove_clock() >= 100 || board.scores().insufficient_material() || path.contains(&board.hash()) {
+            return DISPROVEN;
+        }
+        if ply >= self.max_plies {
+            self.hit_horizon = true;
+            return DISPROVEN;
+        }
+        // fewer replies make a node easier to solve
+        let count = moves.len() as u64;
+        if to_move == self.attacker { (1, count) } else { (count, 1) }
+    }
+
+    fn expand<B: Board>(&mut self, board: &mut B, index: usize, hashes: &[u64]) {
+        let ply = hashes.len() as u16;
+        let or_node = !self.nodes[index].or_node;
+        for mv in board.generate_moves() {
+            board.make_move(&mv);
+            let (proof, disproof) = self.evaluate(board, ply, hashes);
+            board.unmake_move(&mv);
+            let child = self.nodes.len();
+            self.nodes.push(Node { mv: Some(mv), parent: index, children: Vec::new(), or_node, proof, disproof, length: 0 });
+            self.nodes[index].children.push(child);
+        }
+    }
+
+    fn select_child(&self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        let children = node.children.iter().copied();
+        if node.or_node {
+            children.min_by_key(|&c| self.nodes[c].proof).unwrap()
+        } else {
+            children.min_by_key(|&c| self.nodes[c].disproof).unwrap()
+        }
+    }
+
+    fn update(&mut self, index: usize) {
+        let node = &self.nodes[index];
+        if node.children.is_empty() {
+            return;
+        }
+        let children = || node.children.iter().map(|&c| &self.nodes[c]);
+        let min_proof = children().map(|c| c.proof).min().unwrap();
+        let min_disproof = children().map(|c| c.disproof).min().unwrap();
+        let sum_proof = children().fold(0u64, |sum, c| sum.saturating_add(c.proof));
+        let sum_disproof = children().fold(0u64, |sum, c| sum.saturating_add(c.disproof));
+        let (proof, disproof) = if node.or_node { (min_proof, sum_disproof) } else { (sum_proof, min_disproof) };
+
+        // a solved node needs one solved child to pick, the other kind needs all of them
+        let shortest = |solved: fn(&Node) -> bool| children().filter(|c| solved(c)).map(|c| c.length).min().unwrap_or(0);
+        let longest = children().map(|c| c.length).max().unwrap_or(0);
+        let length = match (node.or_node, proof == 0, disproof == 0) {
+            (true, true, _) => 1 + shortest(|c| c.proof == 0),
+            (false, _, true) => 1 + shortest(|c| c.disproof == 0),
+            (_, true, _) | (_, _, true) => 1 + longest,
+            _ => 0,
+        };
+
+        let node = &mut self.nodes[index];
+        node.proof = proof;
+        node.disproof = disproof;
+        node.length = length;
+    }
+
+    /// Follows the winner's quickest and the loser's longest moves through solved nodes, and
+    /// the most proving moves through unsolved ones.
+    fn principal_line(&self) -> Vec<Move> {
+        let mut line = Vec::new();
+        let mut index = 0;
+        while !self.nodes[index].children.is_empty() {
+            let node = &self.nodes[index];
+            let children = node.children.iter().copied();
+            // the side choosing here is the attacker at OR nodes
+            let chooser_wins = if node.or_node { node.proof == 0 } else { node.disproof == 0 };
+            index = if !node.is_solved() {
+                self.select_child(index)
+            } else if chooser_wins {
+                let wins = |c: &usize| if node.or_node { self.nodes[*c].proof == 0 } else { self.nodes[*c].disproof == 0 };
+                children.filter(wins).min_by_key(|&c| self.nodes[c].length).unwrap()
+            } else {
+                children.max_by_key(|&c| self.nodes[c].length).unwrap()
+            };
+            line.push(self.nodes[index].mv.unwrap());
+        }
+        line
+    }
+}
+
@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+use crate::board::Board;
+use crate::evaluation::Evaluator;
+use crate::tablebase::SyzygyTablebase;
+use crate::utils::random::Random;
+use super::{AlphaBetaSearcher, SearchLine, Searcher, MATE_SCORE};
+
+pub const MAX_SKILL_LEVEL: u8 = 20;
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2400;
+/// Root moves searched to choose from when playing below full strength.
+const CANDIDATES: usize = 4;
+/// Scores are capped here when weighing candidates, so mates do not overflow.
+const SCORE_CAP: i32 = 2000;
+
+/// Playing strength from 0 to 20, where 20 is the unrestricted engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skill {
+    level: u8,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self::new(MAX_SKILL_LEVEL)
+    }
+}
+
+impl Skill {
+    pub fn new(level: u8) -> Self {
+        Self { level: level.min(MAX_SKILL_LEVEL) }
+    }
+
+    /// Maps `MIN_ELO..=MAX_ELO` linearly onto the levels below full strength.
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let level = (elo - MIN_ELO) * (MAX_SKILL_LEVEL as u32 - 1) / (MAX_ELO - MIN_ELO);
+        Self::new(level as u8)
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn is_full_strength(&self) -> bool {
+        self.level == MAX_SKILL_LEVEL
+    }
+
+    /// Search depth allowed at this level.
+    pub fn depth(&self) -> u8 {
+        1 + self.level / 4
+    }
+
+    /// Node budget allowed at this level, doubling every two levels.
+    pub fn nodes(&self) -> u64 {
+        (200.0 * 2f64.powf(self.level as f64 / 2.0)) as u64
+    }
+
+    /// Chance of a deliberate mistake among the candidate moves.
+    fn error_chance(&self) -> f64 {
+        (MAX_SKILL_LEVEL - self.level) as f64 / 100.0
+    }
+
+    /// How far below the best move a mistake may score, in centipawns.
+    fn error_margin(&self) -> i32 {
+        15 * (MAX_SKILL_LEVEL - self.level) as i32
+    }
+
+    /// Random bonus, in centipawns, that any candidate may get on top of its score.
+    fn noise(&self) -> i32 {
+        10 * (MAX_SKILL_LEVEL - self.level) as i32
+    }
+
+    /// Picks one of the lines, best first, by adding random noise to every score, more of it at
+    /// lower levels. Now and then any candidate that is not much worse is taken instead.
+    fn pick(&self, lines: &[SearchLine], rng: &mut Random) -> usize {
+        let score = |line: &SearchLine| line.score.clamp(-SCORE_CAP, SCORE_CAP);
+        let top = score(&lines[0]);
+
+        if rng.next_f64() < self.error_chance() {
+            let plausible: Vec<usize> = (0..lines.len())
+                .filter(|&i| top - score(&lines[i]) <= self.error_margin())
+                .collect();
+            return plausible[rng.next_below(plausible.len() as u64) as usize];
+        }
+
+        let mut best = 0;
+        let mut best_value = i32::MIN;
+        for (i, line) in lines.iter().enumerate() {
+            let value = score(line) + rng.next_below(self.noise() as u64 + 1) as i32;
+            if value > best_value {
+                best_value = value;
+                best = i;
+            }
+        }
+        best
+    }
+}
+
+/// Alpha-beta held back to a skill level: a lower depth and node budget, and a move picked among
+/// the best few with seedable randomness.
+pub struct SkillLimitedSearcher {
+    searcher: AlphaBetaSearcher,
+    skill: Skill,
+    rng: RefCell<Random>,
+}
+
+impl SkillLimitedSearcher {
+    pub fn new(mut searcher: AlphaBetaSearcher, skill: Skill) -> Self {
+        if !skill.is_full_strength() {
+            searcher.depth = searcher.depth.min(skill.depth());
+            searcher.node_limit = Some(searcher.node_limit.map_or(skill.nodes(), |limit| limit.min(skill.nodes())));
+        }
+        Self { searcher, skill, rng: RefCell::new(Random::from_time()) }
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        *self.rng.borrow_mut() = Random::new(seed);
+        self
+    }
+
+    pub fn skill(&self) -> Skill {
+        self.skill
+    }
+}
+
+impl<B: Board> Searcher<B> for SkillLimitedSearcher {
+    /// The picked move comes first, followed by the other lines up to `multi_pv`.
+    fn search_lines(
+        &self,
+        board: &B,
+        evaluator: &dyn Evaluator<B>,
+        multi_pv: usize,
+        on_iteration: &mut dyn FnMut(&[SearchLine]),
+    ) -> Vec<SearchLine> {
+        if self.skill.is_full_strength() {
+            return self.searcher.search_lines(board, evaluator, multi_pv, on_iteration);
+        }
+
+        let mut lines = self.searcher.search_lines(board, evaluator, multi_pv.max(CANDIDATES), &mut |lines| {
+            on_iteration(&lines[..multi_pv.min(lines.len())]);
+        });
+        // a forced mate is never given away
+        if lines.len() > 1 && lines[0].score < MATE_SCORE - 1000 {
+            let picked = self.skill.pick(&lines, &mut self.rng.borrow_mut());
+            let line = lines.remove(picked);
+            lines.insert(0, line);
+        }
+        lines.truncate(multi_pv.max(1));
+        lines
+    }
+
+    fn set_tablebase(&mut self, tablebase: Option<Arc<SyzygyTablebase>>) {
+        Searcher::<B>::set_tablebase(&mut self.searcher, tablebase);
+    }
+}
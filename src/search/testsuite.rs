@@ -0,0 +1,63 @@
+use crate::board::epd::EpdRecord;
+use crate::board::san::move_to_san;
+use crate::board::types::Move;
+use crate::evaluation::Evaluator;
+use super::Searcher;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionResult {
+    pub engine_move: Option<Move>,
+    /// The engine's move in algebraic notation, `-` when it found none.
+    pub san: String,
+    /// The move is one of the `bm` moves and none of the `am` moves.
+    pub solved: bool,
+    /// STS style points for the move out of the best move's points.
+    pub points: u32,
+    pub max_points: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SuiteSummary {
+    pub positions: usize,
+    pub solved: usize,
+    pub points: u32,
+    pub max_points: u32,
+}
+
+pub fn run_position(record: &EpdRecord, searcher: &dyn Searcher, evaluator: &dyn Evaluator) -> PositionResult {
+    let engine_move = searcher.search(&record.board, evaluator);
+    let mut board = record.board.clone();
+    let san = engine_move.map_or_else(|| "-".to_string(), |mv| move_to_san(&mut board, &mv));
+
+    let best = record.best_moves();
+    let avoid = record.avoid_moves();
+    let solved = engine_move.is_some_and(|mv| {
+        (record.operation("bm").is_some() || record.operation("am").is_some())
+            && (record.operation("bm").is_none() || best.contains(&mv))
+            && !avoid.contains(&mv)
+    });
+
+    let weighted = record.move_points();
+    let points = weighted.iter().find(|(mv, _)| Some(*mv) == engine_move).map_or(0, |&(_, points)| points);
+    let max_points = weighted.iter().map(|&(_, points)| points).max().unwrap_or(0);
+    PositionResult { engine_move, san, solved, points, max_points }
+}
+
+/// Searches every record with the searcher's own limits, calling `on_position` after each one.
+pub fn run_testsuite(
+    records: &[EpdRecord],
+    searcher: &dyn Searcher,
+    evaluator: &dyn Evaluator,
+    on_position: &mut dyn FnMut(&EpdRecord, &PositionResult),
+) -> SuiteSummary {
+    let mut summary = SuiteSummary::default();
+    for record in records {
+        let result = run_position(record, searcher, evaluator);
+        on_position(record, &result);
+        summary.positions += 1;
+        summary.solved += result.solved as usize;
+        summary.points += result.points;
+        summary.max_points += result.max_points;
+    }
+    summary
+}
@@ -0,0 +1,128 @@
+use std::sync::OnceLock;
+
+/// Lookup tables used to turn a position into a Syzygy table index.
+pub struct EncodingTables {
+    pub map_b1h1h7: [u64; 64],
+    pub map_a1d1d4: [u64; 64],
+    pub map_kk: [[u64; 64]; 10],
+    pub binomial: [[u64; 64]; 7],
+    pub map_pawns: [u64; 64],
+    pub lead_pawn_idx: [[u64; 64]; 7],
+    pub lead_pawns_size: [[u64; 4]; 7],
+}
+
+pub fn encoding_tables() -> &'static EncodingTables {
+    static TABLES: OnceLock<EncodingTables> = OnceLock::new();
+    TABLES.get_or_init(EncodingTables::new)
+}
+
+/// Positive above the a1-h8 diagonal, zero on it and negative below.
+pub fn off_a1h8(square: u8) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+pub fn rank_of(square: u8) -> u8 {
+    square / 8
+}
+
+pub fn file_of(square: u8) -> u8 {
+    square % 8
+}
+
+fn kings_touch(a: u8, b: u8) -> bool {
+    (rank_of(a) as i32 - rank_of(b) as i32).abs() <= 1 && (file_of(a) as i32 - file_of(b) as i32).abs() <= 1
+}
+
+impl EncodingTables {
+    fn new() -> Self {
+        let mut tables = Self {
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 7],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; 7],
+            lead_pawns_size: [[0; 4]; 7],
+        };
+
+        // squares below the a1-h8 diagonal map to 0..27
+        let mut code = 0;
+        for s in 0..64u8 {
+            if off_a1h8(s) < 0 {
+                tables.map_b1h1h7[s as usize] = code;
+                code += 1;
+            }
+        }
+
+        // the a1-d1-d4 triangle maps to 0..9 with the diagonal squares last
+        let mut diagonal = Vec::new();
+        code = 0;
+        for s in 0..=27u8 {
+            if off_a1h8(s) < 0 && file_of(s) <= 3 {
+                tables.map_a1d1d4[s as usize] = code;
+                code += 1;
+            } else if off_a1h8(s) == 0 && file_of(s) <= 3 {
+                diagonal.push(s);
+            }
+        }
+        for s in diagonal {
+            tables.map_a1d1d4[s as usize] = code;
+            code += 1;
+        }
+
+        // the 462 legal placements of two kings with the first one in the a1-d1-d4 triangle
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10u64 {
+            for s1 in 0..=27u8 {
+                if tables.map_a1d1d4[s1 as usize] != idx || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+                for s2 in 0..64u8 {
+                    if kings_touch(s1, s2) || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                        continue;
+                    } else if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        tables.map_kk[idx as usize][s2 as usize] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            tables.map_kk[idx as usize][s2 as usize] = code;
+            code += 1;
+        }
+
+        tables.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..7.min(n + 1) {
+                tables.binomial[k][n] = if k > 0 { tables.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { tables.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // a2-h7 map to 47..0, the pawn with the highest value is the leading one
+        let mut available_squares = 47;
+        for lead_pawns_count in 1..=6 {
+            for file in 0..4u8 {
+                let mut idx = 0;
+                for rank in 1..=6u8 {
+                    let sq = rank * 8 + file;
+                    if lead_pawns_count == 1 {
+                        tables.map_pawns[sq as usize] = available_squares;
+                        available_squares -= 1;
+                        tables.map_pawns[(sq ^ 7) as usize] = available_squares;
+                        available_squares = available_squares.saturating_sub(1);
+                    }
+                    tables.lead_pawn_idx[lead_pawns_count][sq as usize] = idx;
+                    idx += tables.binomial[lead_pawns_count - 1][tables.map_pawns[sq as usize] as usize];
+                }
+                tables.lead_pawns_size[lead_pawns_count][file as usize] = idx;
+            }
+        }
+
+        tables
+    }
+}
@@ -0,0 +1,254 @@
+pub mod encoding;
+pub mod syzygy;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::{Move, PieceKind};
+use crate::move_generation::MoveList;
+use syzygy::{material_key, TableEntry, TableKind};
+
+/// Win/draw/loss from the side to move's point of view. Cursed wins and blessed losses are
+/// results that the fifty-move rule turns into draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Self {
+        match value {
+            i32::MIN..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    fn negate(self) -> Self {
+        Self::from_value(-(self as i32))
+    }
+
+    /// DTZ of a position whose best move resets the fifty-move counter.
+    fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Win => 1,
+            Wdl::CursedWin => 101,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -101,
+            Wdl::Loss => -1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProbeState {
+    Ok,
+    ZeroingBestMove,
+}
+
+/// Syzygy WDL/DTZ tables found in a local directory.
+pub struct SyzygyTablebase {
+    wdl: HashMap<String, Arc<TableEntry>>,
+    dtz: HashMap<String, Arc<TableEntry>>,
+    cardinality: usize,
+}
+
+fn is_capture(mv: &Move) -> bool {
+    mv.captures.is_some()
+}
+
+fn is_zeroing(mv: &Move) -> bool {
+    mv.captures.is_some() || mv.piece.kind == PieceKind::Pawn
+}
+
+fn play(board: &BitboardBoard, mv: &Move) -> BitboardBoard {
+    let mut next = board.clone();
+    next.make_move(mv);
+    next
+}
+
+impl SyzygyTablebase {
+    /// Registers every `.rtbw`/`.rtbz` file in `dir`. Tables are read on first use.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut tablebase = Self { wdl: HashMap::new(), dtz: HashMap::new(), cardinality: 0 };
+
+        for dir_entry in fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else { continue };
+            let kind = match extension.to_str() {
+                Some("rtbw") => TableKind::Wdl,
+                Some("rtbz") => TableKind::Dtz,
+                _ => continue,
+            };
+            let Some(entry) = stem.to_str().and_then(|name| TableEntry::new(kind, name, path.clone())) else { continue };
+
+            let entry = Arc::new(entry);
+            let tables = if kind == TableKind::Wdl { &mut tablebase.wdl } else { &mut tablebase.dtz };
+            if kind == TableKind::Wdl {
+                tablebase.cardinality = tablebase.cardinality.max(entry.piece_count());
+            }
+            let name = entry.name().to_string();
+            if let Some((white, black)) = name.split_once('v') {
+                tables.insert(format!("{}v{}", black, white), entry.clone());
+            }
+            tables.insert(name, entry);
+        }
+
+        Ok(tablebase)
+    }
+
+    /// The largest number of pieces covered by the WDL tables.
+    pub fn cardinality(&self) -> usize {
+        self.cardinality
+    }
+
+    /// True when the position is small enough to be looked up.
+    pub fn covers(&self, board: &BitboardBoard) -> bool {
+        board.castling_rights == 0 && (board.get_all_pieces_mask().count_ones() as usize) <= self.cardinality
+    }
+
+    fn probe_table(&self, board: &BitboardBoard, kind: TableKind, wdl: Wdl) -> Option<Option<i32>> {
+        if board.get_all_pieces_mask().count_ones() == 2 {
+            return Some(Some(0));
+        }
+        let tables = if kind == TableKind::Wdl { &self.wdl } else { &self.dtz };
+        let entry = tables.get(&material_key(board))?;
+        Some(entry.probe(board, wdl as i32))
+    }
+
+    pub fn probe_wdl(&self, board: &BitboardBoard) -> Option<Wdl> {
+        self.search(board, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Resolves captures (and pawn moves when `zeroing_moves` is set) before trusting the
+    /// table, since tables don't store positions where en passant is possible and store
+    /// "don't care" values when a zeroing move wins.
+    fn search(&self, board: &BitboardBoard, zeroing_moves: bool) -> Option<(Wdl, ProbeState)> {
+        let moves = board.generate_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+
+        for mv in moves.iter().filter(|m| is_capture(m) || (zeroing_moves && m.piece.kind == PieceKind::Pawn)) {
+            searched += 1;
+            let value = self.search(&play(board, &mv), false)?.0.negate();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, ProbeState::ZeroingBestMove));
+                }
+            }
+        }
+
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let value = if no_more_moves {
+            best
+        } else {
+            Wdl::from_value(self.probe_table(board, TableKind::Wdl, Wdl::Draw)??)
+        };
+
+        if best >= value {
+            let state = if best > Wdl::Draw || no_more_moves { ProbeState::ZeroingBestMove } else { ProbeState::Ok };
+            return Some((best, state));
+        }
+        Some((value, ProbeState::Ok))
+    }
+
+    /// Distance to zeroing in plies, positive when the side to move wins. Cursed wins and
+    /// blessed losses are offset by 100.
+    pub fn probe_dtz(&self, board: &BitboardBoard) -> Option<i32> {
+        let (wdl, state) = self.search(board, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if state == ProbeState::ZeroingBestMove {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        let sign = (wdl as i32).signum();
+        if let Some(dtz) = self.probe_table(board, TableKind::Dtz, wdl)? {
+            let cursed = matches!(wdl, Wdl::BlessedLoss | Wdl::CursedWin);
+            return Some((dtz + if cursed { 100 } else { 0 }) * sign);
+        }
+
+        // the table stores the other side to move, so do a one ply search
+        let mut min_dtz = i32::MAX;
+        for mv in board.generate_moves() {
+            let next = play(board, &mv);
+            let zeroing = is_zeroing(&mv);
+            let mut dtz = if zeroing {
+                -self.search(&next, false)?.0.dtz_before_zeroing()
+            } else {
+                -self.probe_dtz(&next)?
+            };
+
+            if dtz == 1 && next.is_in_check(next.side_to_move()) && next.generate_moves().is_empty() {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == sign {
+                min_dtz = dtz;
+            }
+        }
+
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    /// Keeps only the root moves that preserve the best tablebase result, preferring the
+    /// fastest win by DTZ. Returns `None` when the position can't be probed.
+    pub fn filter_root_moves(&self, board: &BitboardBoard, moves: &MoveList) -> Option<Vec<Move>> {
+        if !self.covers(board) || moves.is_empty() {
+            return None;
+        }
+
+        let halfmove_clock = board.halfmove_clock as i32;
+        let mut ranked = Vec::with_capacity(moves.len());
+        for mv in moves {
+            let next = play(board, &mv);
+            let mut dtz = if is_zeroing(&mv) {
+                self.probe_wdl(&next)?.negate().dtz_before_zeroing()
+            } else {
+                let dtz = -self.probe_dtz(&next)?;
+                dtz + dtz.signum()
+            };
+            if dtz == 2 && next.is_in_check(next.side_to_move()) && next.generate_moves().is_empty() {
+                dtz = 1;
+            }
+
+            // wins that stay inside the fifty-move rule rank equally above everything else,
+            // losses rank equally unless a fifty-move draw is in sight
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock <= 99 { 1000 } else { 1000 - (dtz + halfmove_clock) }
+            } else if dtz < 0 {
+                if -dtz * 2 + halfmove_clock < 100 { -1000 } else { -1000 + (-dtz + halfmove_clock) }
+            } else {
+                0
+            };
+            ranked.push((mv, rank, dtz));
+        }
+
+        let best_rank = ranked.iter().map(|(_, rank, _)| *rank).max()?;
+        let mut best: Vec<(Move, i32)> = ranked
+            .into_iter()
+            .filter(|(_, rank, _)| *rank == best_rank)
+            .map(|(mv, _, dtz)| (mv, dtz))
+            .collect();
+
+        // among winning moves play the one that zeroes the counter soonest
+        if best_rank > 0 {
+            let fastest = best.iter().map(|(_, dtz)| *dtz).min()?;
+            best.retain(|(_, dtz)| *dtz == fastest);
+        }
+        Some(best.into_iter().map(|(mv, _)| mv).collect())
+    }
+}
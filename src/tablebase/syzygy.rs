@@ -0,0 +1,696 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::{Color, Piece, PieceKind};
+use super::encoding::{encoding_tables, file_of, off_a1h8, rank_of};
+
+pub const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+pub const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+pub const FLAG_STM: u8 = 1;
+pub const FLAG_MAPPED: u8 = 2;
+pub const FLAG_WIN_PLIES: u8 = 4;
+pub const FLAG_LOSS_PLIES: u8 = 8;
+pub const FLAG_WIDE: u8 = 16;
+pub const FLAG_SINGLE_VALUE: u8 = 128;
+
+const MAX_PIECES: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TableKind::Wdl => "rtbw",
+            TableKind::Dtz => "rtbz",
+        }
+    }
+
+    fn magic(&self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        }
+    }
+
+    fn sides(&self) -> usize {
+        match self {
+            TableKind::Wdl => 2,
+            TableKind::Dtz => 1,
+        }
+    }
+}
+
+/// Syzygy piece code: 1..6 for white pawn..king, 9..14 for black.
+pub fn piece_code(piece: Piece) -> u8 {
+    let kind = match piece.kind {
+        PieceKind::Pawn => 1,
+        PieceKind::Knight => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Rook => 4,
+        PieceKind::Queen => 5,
+        PieceKind::King => 6,
+    };
+    if piece.color == Color::White { kind } else { kind + 8 }
+}
+
+fn piece_char(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn => 'P',
+        PieceKind::Knight => 'N',
+        PieceKind::Bishop => 'B',
+        PieceKind::Rook => 'R',
+        PieceKind::Queen => 'Q',
+        PieceKind::King => 'K',
+    }
+}
+
+const NAME_ORDER: [PieceKind; 6] = [PieceKind::King, PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight, PieceKind::Pawn];
+
+/// Material of one side in table naming order, e.g. "KRP".
+fn side_material(board: &BitboardBoard, color: Color) -> String {
+    let mut out = String::new();
+    for kind in NAME_ORDER {
+        let count = board.bitboards[bitboard_index(color, kind)].count_ones();
+        for _ in 0..count {
+            out.push(piece_char(kind));
+        }
+    }
+    out
+}
+
+/// Material key of a position written like a table name, white first: "KRvK".
+pub fn material_key(board: &BitboardBoard) -> String {
+    format!("{}v{}", side_material(board, Color::White), side_material(board, Color::Black))
+}
+
+fn bitboard_index(color: Color, kind: PieceKind) -> usize {
+    let base = if color == Color::White { 0 } else { 6 };
+    base + match kind {
+        PieceKind::Pawn => 0,
+        PieceKind::Knight => 1,
+        PieceKind::Bishop => 2,
+        PieceKind::Rook => 3,
+        PieceKind::Queen => 4,
+        PieceKind::King => 5,
+    }
+}
+
+fn swap_sides(key: &str) -> String {
+    match key.split_once('v') {
+        Some((white, black)) => format!("{}v{}", black, white),
+        None => key.to_string(),
+    }
+}
+
+/// Huffman and recursive pairing data for one side (and pawn file) of a table.
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    pieces: [u8; MAX_PIECES],
+    group_len: [usize; MAX_PIECES + 1],
+    group_idx: [u64; MAX_PIECES + 1],
+    size_of_block: u64,
+    span: u64,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_length: usize,
+    block_length_size: usize,
+    blocks_num: usize,
+    data: usize,
+    max_sym_len: u8,
+    min_sym_len: u8,
+    lowest_sym: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    btree: usize,
+    map_idx: [u16; 4],
+}
+
+struct TableData {
+    bytes: Vec<u8>,
+    // [side][file], pawnless tables only use file 0
+    pairs: [[PairsData; 4]; 2],
+    map: usize,
+}
+
+/// One table file on disk, loaded lazily on first probe.
+pub struct TableEntry {
+    kind: TableKind,
+    path: PathBuf,
+    key: String,
+    key2: String,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    pawn_count: [u8; 2],
+    data: OnceLock<Option<TableData>>,
+}
+
+fn read_u16_le(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn read_u32_le(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn read_u32_be(bytes: &[u8], at: usize) -> u32 {
+    // blocks may be read slightly past the end of the file, treat that as zeros
+    let mut buf = [0u8; 4];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = bytes.get(at + i).copied().unwrap_or(0);
+    }
+    u32::from_be_bytes(buf)
+}
+
+impl TableEntry {
+    /// Builds an entry from a table name such as "KRvK".
+    pub fn new(kind: TableKind, name: &str, path: PathBuf) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let valid = |side: &str| side.starts_with('K') && side.chars().all(|c| "KQRBNP".contains(c));
+        if !valid(white) || !valid(black) {
+            return None;
+        }
+
+        let count = |side: &str, c: char| side.chars().filter(|&x| x == c).count() as u8;
+        let has_unique_pieces = "QRBNP".chars().any(|c| count(white, c) == 1 || count(black, c) == 1);
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+        // the leading colour is the side with fewer pawns, as this compresses better
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] };
+
+        Some(Self {
+            kind,
+            path,
+            key: name.to_string(),
+            key2: swap_sides(name),
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            pawn_count,
+            data: OnceLock::new(),
+        })
+    }
+
+    /// Builds an entry over table bytes that are already in memory.
+    pub fn from_bytes(kind: TableKind, name: &str, bytes: Vec<u8>) -> Option<Self> {
+        let entry = Self::new(kind, name, PathBuf::new())?;
+        let data = entry.parse(bytes)?;
+        entry.data.set(Some(data)).ok()?;
+        Some(entry)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.key
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.piece_count
+    }
+
+    fn data(&self) -> Option<&TableData> {
+        self.data
+            .get_or_init(|| fs::read(&self.path).ok().and_then(|bytes| self.parse(bytes)))
+            .as_ref()
+    }
+
+    fn parse(&self, bytes: Vec<u8>) -> Option<TableData> {
+        if bytes.len() < 5 || bytes[0..4] != self.kind.magic() {
+            return None;
+        }
+        let flags = bytes[4];
+        if (flags & 2 != 0) != self.has_pawns || (flags & 1 != 0) != (self.key != self.key2) {
+            return None;
+        }
+
+        let mut table = TableData { bytes, pairs: Default::default(), map: 0 };
+        let sides = if self.kind.sides() == 2 && self.key != self.key2 { 2 } else { 1 };
+        let max_file = if self.has_pawns { 3 } else { 0 };
+        let both_sides_have_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let bytes = &table.bytes;
+        let mut data = 5;
+
+        for file in 0..=max_file {
+            let first = bytes[data];
+            let second = if both_sides_have_pawns { bytes[data + 1] } else { 0xFF };
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            data += 1 + both_sides_have_pawns as usize;
+
+            for k in 0..self.piece_count {
+                for side in 0..sides {
+                    let byte = bytes[data];
+                    table.pairs[side][file].pieces[k] = if side == 1 { byte >> 4 } else { byte & 0xF };
+                }
+                data += 1;
+            }
+            for (side, &side_order) in order.iter().enumerate().take(sides) {
+                self.set_groups(&mut table.pairs[side][file], side_order, file);
+            }
+        }
+        data += data & 1;
+
+        for file in 0..=max_file {
+            for side in 0..sides {
+                data = set_sizes(&mut table.pairs[side][file], &table.bytes, data)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            table.map = data;
+            for file in 0..=max_file {
+                let pairs = &mut table.pairs[0][file];
+                if pairs.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                if pairs.flags & FLAG_WIDE != 0 {
+                    data += data & 1;
+                    for i in 0..4 {
+                        pairs.map_idx[i] = ((data - table.map) / 2 + 1) as u16;
+                        data += 2 * read_u16_le(&table.bytes, data) as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        pairs.map_idx[i] = (data - table.map + 1) as u16;
+                        data += table.bytes[data] as usize + 1;
+                    }
+                }
+            }
+            data += data & 1;
+        }
+
+        for file in 0..=max_file {
+            for side in 0..sides {
+                let pairs = &mut table.pairs[side][file];
+                pairs.sparse_index = data;
+                data += pairs.sparse_index_size * 6;
+            }
+        }
+        for file in 0..=max_file {
+            for side in 0..sides {
+                let pairs = &mut table.pairs[side][file];
+                pairs.block_length = data;
+                data += pairs.block_length_size * 2;
+            }
+        }
+        for file in 0..=max_file {
+            for side in 0..sides {
+                let pairs = &mut table.pairs[side][file];
+                if pairs.blocks_num > 0 {
+                    data = (data + 0x3F) & !0x3F;
+                }
+                pairs.data = data;
+                data += pairs.blocks_num * pairs.size_of_block as usize;
+            }
+        }
+
+        (data <= table.bytes.len()).then_some(table)
+    }
+
+    /// Splits the piece sequence into groups and computes each group's index multiplier.
+    fn set_groups(&self, pairs: &mut PairsData, order: [u8; 2], file: usize) {
+        let tables = encoding_tables();
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns { 0 } else if self.has_unique_pieces { 3 } else { 2 };
+        pairs.group_len[n] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+                pairs.group_len[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        pairs.group_len[n] = 0;
+
+        let both_sides_have_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if both_sides_have_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - pairs.group_len[0] - if both_sides_have_pawns { pairs.group_len[1] } else { 0 };
+        let mut idx = 1u64;
+
+        let mut k = 0u8;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                pairs.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    tables.lead_pawns_size[pairs.group_len[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                pairs.group_idx[1] = idx;
+                idx *= tables.binomial[pairs.group_len[1]][48 - pairs.group_len[0]];
+            } else {
+                pairs.group_idx[next] = idx;
+                idx *= tables.binomial[pairs.group_len[next]][free_squares];
+                free_squares -= pairs.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        pairs.group_idx[n] = idx;
+    }
+
+    /// Probes the table, returning the raw WDL (-2..=2) or DTZ value. `None` means the table
+    /// stores the other side to move (DTZ only) or could not be read.
+    pub fn probe(&self, board: &BitboardBoard, wdl: i32) -> Option<i32> {
+        let table = self.data()?;
+        let (stm, tb_file, idx) = self.encode(table, board)?;
+        let side = if self.kind == TableKind::Dtz { 0 } else { stm };
+        let pairs = &table.pairs[side][tb_file];
+
+        if self.kind == TableKind::Dtz
+            && (pairs.flags & FLAG_STM) as usize != stm
+            && (self.key != self.key2 || self.has_pawns) {
+            return None;
+        }
+
+        let value = decompress_pairs(pairs, &table.bytes, idx) as i32;
+        Some(match self.kind {
+            TableKind::Wdl => value - 2,
+            TableKind::Dtz => self.map_dtz_score(table, tb_file, value, wdl),
+        })
+    }
+
+    /// The stored side to move, pawn file and index of the position inside this table.
+    pub fn index(&self, board: &BitboardBoard) -> Option<(usize, usize, u64)> {
+        self.encode(self.data()?, board)
+    }
+
+    fn encode(&self, table: &TableData, board: &BitboardBoard) -> Option<(usize, usize, u64)> {
+        let tables = encoding_tables();
+
+        let symmetric_black_to_move = self.key == self.key2 && board.side_to_move() == Color::Black;
+        let black_stronger = material_key(board) != self.key;
+        let flip = symmetric_black_to_move || black_stronger;
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip as usize) ^ (board.side_to_move() == Color::Black) as usize;
+
+        let mut squares = [0u8; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0u64;
+        let mut lead_pawns_count = 0;
+        let mut tb_file = 0;
+
+        let pawn_order = |a: &u8, b: &u8| tables.map_pawns[*a as usize].cmp(&tables.map_pawns[*b as usize]);
+
+        if self.has_pawns {
+            let lead_piece = table.pairs[0][0].pieces[0] ^ flip_color;
+            let lead_color = if lead_piece < 8 { Color::White } else { Color::Black };
+            lead_pawns = board.bitboards[bitboard_index(lead_color, PieceKind::Pawn)];
+            let mut bb = lead_pawns;
+            while bb != 0 {
+                squares[size] = bb.trailing_zeros() as u8 ^ flip_squares;
+                size += 1;
+                bb &= bb - 1;
+            }
+            lead_pawns_count = size;
+            let lead = (0..lead_pawns_count).max_by(|&a, &b| pawn_order(&squares[a], &squares[b])).unwrap();
+            squares.swap(0, lead);
+            let file = file_of(squares[0]);
+            tb_file = file.min(7 - file) as usize;
+        }
+
+        let side = if self.kind == TableKind::Dtz { 0 } else { stm };
+        let pairs = &table.pairs[side][tb_file];
+
+        let mut bb = board.get_all_pieces_mask() ^ lead_pawns;
+        while bb != 0 {
+            let sq = bb.trailing_zeros() as u8;
+            bb &= bb - 1;
+            squares[size] = sq ^ flip_squares;
+            pieces[size] = piece_code(board.piece_at(crate::board::types::Square(sq))?) ^ flip_color;
+            size += 1;
+        }
+        if size < 2 {
+            return None;
+        }
+
+        // reorder the pieces to the sequence used when the table was built
+        for i in lead_pawns_count..size - 1 {
+            for j in i + 1..size {
+                if pairs.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // move the leading piece to the a1-d1-d4 triangle
+        if file_of(squares[0]) > 3 {
+            for sq in squares.iter_mut().take(size) {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = tables.lead_pawn_idx[lead_pawns_count][squares[0] as usize];
+            squares[1..lead_pawns_count].sort_by(pawn_order);
+            for (i, &sq) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+                idx += tables.binomial[i][tables.map_pawns[sq as usize] as usize];
+            }
+        } else {
+            if rank_of(squares[0]) > 3 {
+                for sq in squares.iter_mut().take(size) {
+                    *sq ^= 56;
+                }
+            }
+
+            for i in 0..pairs.group_len[0] {
+                let off = off_a1h8(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for sq in squares.iter_mut().take(size).skip(i) {
+                        *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let s = |i: usize| squares[i] as u64;
+                let rank = |i: usize| rank_of(squares[i]) as u64;
+                let adjust1 = (s(1) > s(0)) as u64;
+                let adjust2 = (s(2) > s(0)) as u64 + (s(2) > s(1)) as u64;
+
+                idx = if off_a1h8(squares[0]) != 0 {
+                    (tables.map_a1d1d4[squares[0] as usize] * 63 + (s(1) - adjust1)) * 62 + s(2) - adjust2
+                } else if off_a1h8(squares[1]) != 0 {
+                    (6 * 63 + rank(0) * 28 + tables.map_b1h1h7[squares[1] as usize]) * 62 + s(2) - adjust2
+                } else if off_a1h8(squares[2]) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + rank(0) * 7 * 28 + (rank(1) - adjust1) * 28
+                        + tables.map_b1h1h7[squares[2] as usize]
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(0) * 7 * 6 + (rank(1) - adjust1) * 6
+                        + (rank(2) - adjust2)
+                };
+            } else {
+                idx = tables.map_kk[tables.map_a1d1d4[squares[0] as usize] as usize][squares[1] as usize];
+            }
+        }
+
+        idx *= pairs.group_idx[0];
+        let mut group_start = pairs.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while pairs.group_len[next] != 0 {
+            let group_end = group_start + pairs.group_len[next];
+            squares[group_start..group_end].sort();
+            let mut n = 0u64;
+            for i in 0..pairs.group_len[next] {
+                let sq = squares[group_start + i];
+                let adjust = squares[..group_start].iter().filter(|&&s| sq > s).count() as u64;
+                let pawn_offset = if remaining_pawns { 8 } else { 0 };
+                n += tables.binomial[i + 1][(sq as u64 - adjust - pawn_offset) as usize];
+            }
+            remaining_pawns = false;
+            idx += n * pairs.group_idx[next];
+            group_start = group_end;
+            next += 1;
+        }
+
+        Some((stm, tb_file, idx))
+    }
+
+    fn map_dtz_score(&self, table: &TableData, file: usize, mut value: i32, wdl: i32) -> i32 {
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        let pairs = &table.pairs[0][file];
+        let flags = pairs.flags;
+
+        if flags & FLAG_MAPPED != 0 {
+            let map_index = pairs.map_idx[WDL_MAP[(wdl + 2) as usize]] as usize + value as usize;
+            value = if flags & FLAG_WIDE != 0 {
+                read_u16_le(&table.bytes, table.map + 2 * map_index) as i32
+            } else {
+                table.bytes[table.map + map_index] as i32
+            };
+        }
+
+        // tables store moves unless flagged otherwise, callers want plies
+        if (wdl == 2 && flags & FLAG_WIN_PLIES == 0)
+            || (wdl == -2 && flags & FLAG_LOSS_PLIES == 0)
+            || wdl == 1
+            || wdl == -1 {
+            value *= 2;
+        }
+        value + 1
+    }
+}
+
+fn set_sizes(pairs: &mut PairsData, bytes: &[u8], mut data: usize) -> Option<usize> {
+    pairs.flags = *bytes.get(data)?;
+    data += 1;
+    if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+        pairs.blocks_num = 0;
+        pairs.block_length_size = 0;
+        pairs.span = 0;
+        pairs.sparse_index_size = 0;
+        pairs.min_sym_len = bytes[data];
+        return Some(data + 1);
+    }
+
+    let group_end = pairs.group_len.iter().position(|&l| l == 0)?;
+    let tb_size = pairs.group_idx[group_end];
+    pairs.size_of_block = 1u64 << bytes[data];
+    pairs.span = 1u64 << bytes[data + 1];
+    pairs.sparse_index_size = tb_size.div_ceil(pairs.span) as usize;
+    let padding = bytes[data + 2] as usize;
+    pairs.blocks_num = read_u32_le(bytes, data + 3) as usize;
+    pairs.block_length_size = pairs.blocks_num + padding;
+    pairs.max_sym_len = bytes[data + 7];
+    pairs.min_sym_len = bytes[data + 8];
+    data += 9;
+    pairs.lowest_sym = data;
+
+    let base_len = (pairs.max_sym_len - pairs.min_sym_len) as usize + 1;
+    let lowest = |i: usize| read_u16_le(bytes, pairs.lowest_sym + 2 * i) as u64;
+    let mut base64 = vec![0u64; base_len];
+    for i in (0..base_len.saturating_sub(1)).rev() {
+        base64[i] = (base64[i + 1] + lowest(i) - lowest(i + 1)) / 2;
+    }
+    for (i, base) in base64.iter_mut().enumerate() {
+        let shift = 64 - i as u32 - pairs.min_sym_len as u32;
+        *base = if shift >= 64 { 0 } else { *base << shift };
+    }
+    pairs.base64 = base64;
+    data += base_len * 2;
+
+    let symbols = read_u16_le(bytes, data) as usize;
+    data += 2;
+    pairs.btree = data;
+    pairs.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for sym in 0..symbols {
+        if !visited[sym] {
+            pairs.symlen[sym] = set_symlen(pairs, bytes, sym, &mut visited);
+        }
+    }
+
+    Some(data + symbols * 3 + (symbols & 1))
+}
+
+fn btree_left(pairs: &PairsData, bytes: &[u8], sym: usize) -> usize {
+    let at = pairs.btree + sym * 3;
+    (((bytes[at + 1] & 0xF) as usize) << 8) | bytes[at] as usize
+}
+
+fn btree_right(pairs: &PairsData, bytes: &[u8], sym: usize) -> usize {
+    let at = pairs.btree + sym * 3;
+    ((bytes[at + 2] as usize) << 4) | (bytes[at + 1] >> 4) as usize
+}
+
+fn set_symlen(pairs: &mut PairsData, bytes: &[u8], sym: usize, visited: &mut [bool]) -> u8 {
+    visited[sym] = true;
+    let right = btree_right(pairs, bytes, sym);
+    if right == 0xFFF {
+        return 0;
+    }
+    let left = btree_left(pairs, bytes, sym);
+    if !visited[left] {
+        pairs.symlen[left] = set_symlen(pairs, bytes, left, visited);
+    }
+    if !visited[right] {
+        pairs.symlen[right] = set_symlen(pairs, bytes, right, visited);
+    }
+    pairs.symlen[left] + pairs.symlen[right] + 1
+}
+
+fn decompress_pairs(pairs: &PairsData, bytes: &[u8], idx: u64) -> u16 {
+    if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+        return pairs.min_sym_len as u16;
+    }
+
+    // the sparse index points close to the block holding idx, walk from there
+    let k = (idx / pairs.span) as usize;
+    let entry = pairs.sparse_index + k * 6;
+    let mut block = read_u32_le(bytes, entry) as usize;
+    let mut offset = read_u16_le(bytes, entry + 4) as i64;
+    offset += (idx % pairs.span) as i64 - (pairs.span / 2) as i64;
+
+    let block_length = |b: usize| read_u16_le(bytes, pairs.block_length + 2 * b) as i64;
+    while offset < 0 {
+        block -= 1;
+        offset += block_length(block) + 1;
+    }
+    while offset > block_length(block) {
+        offset -= block_length(block) + 1;
+        block += 1;
+    }
+
+    let mut ptr = pairs.data + block * pairs.size_of_block as usize;
+    let mut buf64 = ((read_u32_be(bytes, ptr) as u64) << 32) | read_u32_be(bytes, ptr + 4) as u64;
+    ptr += 8;
+    let mut buf64_size = 64;
+    let min_sym_len = pairs.min_sym_len as usize;
+
+    let mut sym;
+    loop {
+        let mut len = 0;
+        while buf64 < pairs.base64[len] {
+            len += 1;
+        }
+        sym = ((buf64 - pairs.base64[len]) >> (64 - len - min_sym_len)) as usize;
+        sym += read_u16_le(bytes, pairs.lowest_sym + 2 * len) as usize;
+
+        if offset < pairs.symlen[sym] as i64 + 1 {
+            break;
+        }
+        offset -= pairs.symlen[sym] as i64 + 1;
+        len += min_sym_len;
+        buf64 <<= len;
+        buf64_size -= len;
+        if buf64_size <= 32 {
+            buf64_size += 32;
+            buf64 |= (read_u32_be(bytes, ptr) as u64) << (64 - buf64_size);
+            ptr += 4;
+        }
+    }
+
+    // expand the pair tree down to the single value at offset
+    while pairs.symlen[sym] != 0 {
+        let left = btree_left(pairs, bytes, sym);
+        if offset < pairs.symlen[left] as i64 + 1 {
+            sym = left;
+        } else {
+            offset -= pairs.symlen[left] as i64 + 1;
+            sym = btree_right(pairs, bytes, sym);
+        }
+    }
+
+    btree_left(pairs, bytes, sym) as u16
+}
@@ -0,0 +1,156 @@
+use crate::arena::{opening_board, play_game, run_match, EngineConfig, GameResult, MatchSettings, MatchStats, SprtDecision, SprtSettings, Termination};
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::Move;
+use crate::evaluation::{Evaluator, SimpleEvaluator};
+use crate::search::{AlphaBetaSearcher, SearchLine, Searcher};
+
+/// Always plays the first legal move, claiming the given score for it.
+struct FirstMoveSearcher(i32);
+
+impl Searcher for FirstMoveSearcher {
+    fn search_lines(&self, board: &BitboardBoard, _: &dyn Evaluator, _: usize, _: &mut dyn FnMut(&[SearchLine])) -> Vec<SearchLine> {
+        board.generate_moves().first().map(|mv| SearchLine { depth: 1, score: self.0, pv: vec![mv] }).into_iter().collect()
+    }
+}
+
+/// Returns no line at all, or a line whose move is the given one whatever the position.
+struct NoMoveSearcher(Option<Move>);
+
+impl Searcher for NoMoveSearcher {
+    fn search_lines(&self, _: &BitboardBoard, _: &dyn Evaluator, _: usize, _: &mut dyn FnMut(&[SearchLine])) -> Vec<SearchLine> {
+        self.0.map(|mv| SearchLine { depth: 1, score: 0, pv: vec![mv] }).into_iter().collect()
+    }
+}
+
+fn alphabeta_engine(depth: u8) -> EngineConfig {
+    EngineConfig::new("alphabeta", Box::new(SimpleEvaluator), Box::new(AlphaBetaSearcher::new(depth)))
+}
+
+fn quick_settings(openings: &[&str], games: usize) -> MatchSettings {
+    MatchSettings {
+        games,
+        openings: openings.iter().map(|o| o.to_string()).collect(),
+        max_plies: 12,
+        ..MatchSettings::default()
+    }
+}
+
+#[test]
+fn arena_elo_from_results() {
+    let stats = MatchStats { wins: 60, draws: 20, losses: 20 };
+    let (elo, margin) = stats.elo().unwrap();
+    assert!((elo - 147.19).abs() < 0.01, "{}", elo);
+    assert!(margin > 0.0 && margin < elo);
+
+    let (elo, _) = MatchStats { wins: 10, draws: 5, losses: 10 }.elo().unwrap();
+    assert_eq!(elo, 0.0);
+    assert!(MatchStats { wins: 3, draws: 0, losses: 0 }.elo().is_none());
+}
+
+#[test]
+fn arena_sprt_decisions() {
+    let sprt = SprtSettings::new(0.0, 10.0);
+    let (lower, upper) = sprt.bounds();
+    assert!((upper - 2.944).abs() < 0.001 && (lower + 2.944).abs() < 0.001);
+
+    assert_eq!(sprt.decision(&MatchStats { wins: 400, draws: 200, losses: 200 }), SprtDecision::AcceptH1);
+    assert_eq!(sprt.decision(&MatchStats { wins: 200, draws: 200, losses: 400 }), SprtDecision::AcceptH0);
+    assert_eq!(sprt.decision(&MatchStats { wins: 5, draws: 5, losses: 4 }), SprtDecision::Continue);
+    // a clean sweep has no variance yet, the floor keeps its LLR finite and short of a decision
+    let sweep = MatchStats { wins: 3, draws: 0, losses: 0 };
+    assert!(sweep.llr(&sprt) > 0.0 && sweep.llr(&sprt).is_finite());
+    assert_eq!(sprt.decision(&sweep), SprtDecision::Continue);
+}
+
+#[test]
+fn arena_opening_formats() {
+    let board = opening_board("e2e4 e7e5").unwrap();
+    assert_eq!(board.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+    let board = opening_board("8/8/8/4k3/8/8/3Q4/4K3 b - - bm Kd5;").unwrap();
+    assert_eq!(board.to_fen(), "8/8/8/4k3/8/8/3Q4/4K3 b - - 0 1");
+    assert!(opening_board("e2e5").is_none());
+}
+
+#[test]
+fn arena_game_ends_by_rules() {
+    let engine = alphabeta_engine(2);
+    let settings = quick_settings(&[], 0);
+    let play = |fen: &str| play_game(&engine, &engine, &opening_board(fen).unwrap(), &settings);
+
+    let (result, termination, moves) = play("k7/7Q/2K5/8/8/8/8/8 w - - 0 1");
+    assert_eq!((result, termination), (GameResult::WhiteWins, Termination::Checkmate));
+    assert_eq!(moves.len(), 1);
+    assert_eq!(play("8/8/8/4k3/8/8/3N4/4K3 w - - 0 1").1, Termination::InsufficientMaterial);
+    assert_eq!(play("8/8/8/4k3/8/8/3R4/4K3 w - - 100 80").1, Termination::FiftyMoves);
+    assert_eq!(play("k7/2Q5/2K5/8/8/8/8/8 b - - 0 1").1, Termination::Stalemate);
+}
+
+#[test]
+fn arena_repetition_is_a_draw() {
+    // with only kings and a blocked pawn pair, both sides just shuffle
+    let shuffler = EngineConfig::new("first", Box::new(SimpleEvaluator), Box::new(FirstMoveSearcher(0)));
+    let mut settings = quick_settings(&[], 0);
+    settings.max_plies = 100;
+    let opening = opening_board("7k/8/8/8/4p3/4P3/8/K7 w - - 0 1").unwrap();
+    let (result, termination, _) = play_game(&shuffler, &shuffler, &opening, &settings);
+    assert_eq!((result, termination), (GameResult::Draw, Termination::Repetition));
+}
+
+#[test]
+fn arena_adjudication_needs_both_engines() {
+    let claiming = |score| EngineConfig::new("first", Box::new(SimpleEvaluator), Box::new(FirstMoveSearcher(score)));
+    let settings = quick_settings(&[], 0);
+    let opening = BitboardBoard::new_startpos();
+
+    // only White believes it is winning
+    let (_, termination, _) = play_game(&claiming(2000), &claiming(0), &opening, &settings);
+    assert_eq!(termination, Termination::MaxPlies);
+
+    // Black agrees, and after six plies of agreement the game is over
+    let (result, termination, moves) = play_game(&claiming(2000), &claiming(-2000), &opening, &settings);
+    assert_eq!((result, termination), (GameResult::WhiteWins, Termination::Adjudication));
+    assert_eq!(moves.len(), 6);
+}
+
+#[test]
+fn arena_match_swaps_colours() {
+    let first = alphabeta_engine(1);
+    let second = alphabeta_engine(1);
+    let settings = quick_settings(&["e2e4 e7e5", "d2d4 d7d5"], 4);
+
+    let mut records = Vec::new();
+    let stats = run_match(&first, &second, &settings, &mut |record, _| records.push((record.opening, record.first_is_white)));
+    assert_eq!(stats.games(), 4);
+    assert_eq!(records, vec![(0, true), (0, false), (1, true), (1, false)]);
+}
+
+#[test]
+fn arena_sprt_stops_early() {
+    let strong = alphabeta_engine(2);
+    let weak = EngineConfig::new("first", Box::new(SimpleEvaluator), Box::new(FirstMoveSearcher(0)));
+    let mut settings = quick_settings(&["k7/7Q/2K5/8/8/8/8/8 w - - 0 1"], 40);
+    settings.sprt = Some(SprtSettings::new(0.0, 200.0));
+
+    let stats = run_match(&strong, &weak, &settings, &mut |_, _| {});
+    assert!(stats.games() < 40);
+    assert_eq!(settings.sprt.unwrap().decision(&stats), SprtDecision::AcceptH1);
+}
+
+#[test]
+fn arena_engine_without_a_legal_move_forfeits() {
+    let engine = alphabeta_engine(1);
+    let silent = EngineConfig::new("silent", Box::new(SimpleEvaluator), Box::new(NoMoveSearcher(None)));
+    let settings = quick_settings(&[], 0);
+    let opening = BitboardBoard::new_startpos();
+
+    let (result, termination, moves) = play_game(&engine, &silent, &opening, &settings);
+    assert_eq!((result, termination), (GameResult::WhiteWins, Termination::Forfeit));
+    assert_eq!(moves.len(), 1);
+
+    // a move from some other position isn't played either
+    let other = opening_board("e2e4").unwrap().generate_moves().first().unwrap();
+    let illegal = EngineConfig::new("illegal", Box::new(SimpleEvaluator), Box::new(NoMoveSearcher(Some(other))));
+    let (result, termination, moves) = play_game(&illegal, &engine, &opening, &settings);
+    assert_eq!((result, termination), (GameResult::BlackWins, Termination::Forfeit));
+    assert!(moves.is_empty());
+}
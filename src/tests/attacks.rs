@@ -0,0 +1,90 @@
+use crate::board::attacks::{between, bishop_attacks, king_attacks, knight_attacks, line, rook_attacks};
+use crate::board::types::{Color, Square};
+use crate::board::{BitboardBoard, Board};
+use super::perft::board_from_fen;
+
+fn sq(name: &str) -> Square {
+    Square::from_notation(name).unwrap()
+}
+
+fn squares(names: &[&str]) -> u64 {
+    names.iter().fold(0, |acc, name| acc | 1u64 << sq(name).0)
+}
+
+#[test]
+fn attack_tables() {
+    assert_eq!(knight_attacks(sq("a1")), squares(&["b3", "c2"]));
+    assert_eq!(king_attacks(sq("h8")), squares(&["g8", "g7", "h7"]));
+    assert_eq!(rook_attacks(sq("d4"), squares(&["d6", "f4"])).count_ones(), 2 + 3 + 2 + 3);
+    assert_eq!(bishop_attacks(sq("a1"), squares(&["c3"])), squares(&["b2", "c3"]));
+}
+
+#[test]
+fn between_and_line() {
+    assert_eq!(between(sq("a1"), sq("d4")), squares(&["b2", "c3"]));
+    assert_eq!(between(sq("e1"), sq("e8")).count_ones(), 6);
+    assert_eq!(between(sq("a1"), sq("b3")), 0);
+    assert_eq!(between(sq("c3"), sq("d4")), 0);
+    assert_eq!(line(sq("c3"), sq("d4")), squares(&["a1", "b2", "c3", "d4", "e5", "f6", "g7", "h8"]));
+    assert_eq!(line(sq("h1"), sq("f3")).count_ones(), 8);
+    assert_eq!(line(sq("a1"), sq("b3")), 0);
+}
+
+#[test]
+fn attackers_to_square() {
+    let board = board_from_fen("4k3/8/2n5/3p4/4P3/5B2/8/4K2R w K - 0 1");
+    let all = board.get_all_pieces_mask();
+    assert_eq!(board.attackers_to(sq("d5"), all), squares(&["e4"]));
+    assert_eq!(board.attackers_to(sq("e4"), all), squares(&["d5", "f3"]));
+    // with the pawn out of the way the bishop sees through to c6
+    assert_eq!(board.attackers_to(sq("c6"), all & !squares(&["e4", "d5"])), squares(&["f3"]));
+    assert_eq!(board.attackers_to(sq("f1"), all), squares(&["e1", "h1"]));
+}
+
+#[test]
+fn square_attacked_and_checkers() {
+    let board = board_from_fen("4k3/8/8/8/1b6/8/3P4/4K2R w K - 0 1");
+    assert!(board.is_square_attacked(sq("c3"), Color::Black));
+    assert!(!board.is_square_attacked(sq("e1"), Color::Black));
+    assert!(board.is_square_attacked(sq("h8"), Color::White));
+    assert_eq!(board.checkers(), 0);
+
+    let board = board_from_fen("4k3/8/8/8/1b6/8/8/4K2R w K - 0 1");
+    assert_eq!(board.checkers(), squares(&["b4"]));
+    assert!(board.is_in_check(Color::White));
+
+    let board = board_from_fen("4r1k1/8/8/8/8/3n4/8/4K3 w - - 0 1");
+    assert_eq!(board.checkers(), squares(&["e8", "d3"]));
+}
+
+#[test]
+fn pinned_pieces() {
+    let board = board_from_fen("4r1k1/8/8/b7/8/2N5/4B3/4K3 w - - 0 1");
+    assert_eq!(board.pinned(Color::White), squares(&["c3", "e2"]));
+    // two blockers on the same line pin neither of them
+    let board = board_from_fen("4r1k1/8/8/8/4N3/8/4B3/4K3 w - - 0 1");
+    assert_eq!(board.pinned(Color::White), 0);
+    // an enemy piece in the way is no pin for white
+    let board = board_from_fen("4r1k1/8/8/8/8/8/4n3/4K3 w - - 0 1");
+    assert_eq!(board.pinned(Color::White), 0);
+}
+
+#[test]
+fn gives_check_matches_playing_the_move() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "4k3/8/8/8/4B3/8/4R3/R3K2R w KQ - 0 1",
+        "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+        "3k4/8/8/8/2pP4/8/1B6/4K3 b - d3 0 1",
+        "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+        "3n2k1/4P3/8/8/8/8/8/4K3 w - - 0 1",
+    ];
+    for fen in fens {
+        let board = board_from_fen(fen);
+        for mv in board.generate_moves() {
+            let mut next: BitboardBoard = board.clone();
+            next.make_move(&mv);
+            assert_eq!(board.gives_check(&mv), next.is_in_check(next.side_to_move()), "{} {}", fen, mv.to_uci());
+        }
+    }
+}
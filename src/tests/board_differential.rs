@@ -0,0 +1,97 @@
+use crate::board::{Board, MailboxBoard};
+use crate::evaluation::SimpleEvaluator;
+use crate::game::GameManager;
+use crate::move_generation::GenerationMode;
+use crate::search::{AlphaBetaSearcher, Searcher};
+use crate::utils::random::Random;
+use super::perft::{board_from_fen, perft, TRICKY_FENS};
+
+fn fens() -> impl Iterator<Item = &'static str> {
+    ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"]
+        .into_iter()
+        .chain(TRICKY_FENS)
+        .chain(["rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"])
+        // Chess960, with X-FEN and Shredder-FEN castling rights
+        .chain(["bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9"])
+        .chain(["2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9"])
+}
+
+fn mailbox_from_fen(fen: &str) -> MailboxBoard {
+    let mut board = MailboxBoard::new_empty();
+    board.load_fen(fen);
+    board
+}
+
+fn sorted_uci<B: Board>(board: &B) -> Vec<String> {
+    let mut moves: Vec<String> = board.generate_moves().iter().map(|m| board.move_to_uci(&m)).collect();
+    moves.sort();
+    moves
+}
+
+#[test]
+fn mailbox_board_perft_matches() {
+    let expected = [[20, 400, 8902], [48, 2039, 97862], [14, 191, 2812], [6, 264, 9467], [44, 1486, 62379], [21, 528, 12189], [21, 807, 18002]];
+    for (fen, counts) in fens().zip(expected) {
+        let mut mailbox = mailbox_from_fen(fen);
+        let mut bitboard = board_from_fen(fen);
+        for (depth, count) in counts.into_iter().enumerate() {
+            let depth = depth as u32 + 1;
+            assert_eq!(perft(&mut mailbox, depth), count, "{} depth {}", fen, depth);
+            assert_eq!(perft(&mut bitboard, depth), count, "{} depth {}", fen, depth);
+        }
+        assert_eq!(mailbox.to_fen(), bitboard.to_fen());
+    }
+}
+
+#[test]
+fn mailbox_board_random_games_match() {
+    let mut rng = Random::new(43);
+    for fen in fens() {
+        for _ in 0..4 {
+            let mut bitboard = board_from_fen(fen);
+            let mut mailbox = mailbox_from_fen(fen);
+            let mut played = Vec::new();
+            for _ in 0..120 {
+                assert_eq!(mailbox.to_fen(), bitboard.to_fen());
+                assert_eq!(mailbox.hash(), bitboard.hash(), "{}", bitboard.to_fen());
+                assert_eq!(mailbox.scores(), bitboard.scores(), "{}", bitboard.to_fen());
+                assert_eq!(mailbox.is_in_check(mailbox.side_to_move()), bitboard.is_in_check(bitboard.side_to_move()));
+                assert_eq!(sorted_uci(&mailbox), sorted_uci(&bitboard), "{}", bitboard.to_fen());
+                for mode in [GenerationMode::Captures, GenerationMode::Quiets, GenerationMode::QuietChecks] {
+                    assert_eq!(mailbox.generate_legal_moves_of(mode).len(), bitboard.generate_legal_moves_of(mode).len(), "{:?} {}", mode, bitboard.to_fen());
+                }
+
+                let moves = bitboard.generate_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                let mv = moves.get(rng.next_below(moves.len() as u64) as usize).unwrap();
+                bitboard.make_move(&mv);
+                mailbox.make_move(&mv);
+                played.push(mv);
+            }
+            for mv in played.iter().rev() {
+                mailbox.unmake_move(mv);
+            }
+            assert_eq!(mailbox.to_fen(), board_from_fen(fen).to_fen());
+            assert_eq!(mailbox.hash(), board_from_fen(fen).hash());
+        }
+    }
+}
+
+#[test]
+fn search_runs_on_either_board() {
+    let searcher = AlphaBetaSearcher::new(3);
+    for fen in fens() {
+        let bitboard_line = &searcher.search_lines(&board_from_fen(fen), &SimpleEvaluator, 1, &mut |_| {})[0];
+        let mailbox_line = &searcher.search_lines(&mailbox_from_fen(fen), &SimpleEvaluator, 1, &mut |_| {})[0];
+        assert_eq!(mailbox_line.score, bitboard_line.score, "{}", fen);
+    }
+
+    let mut game = GameManager::with_board(MailboxBoard::new_startpos());
+    game.set_searcher(Box::new(AlphaBetaSearcher::new(2)));
+    game.make_move_from_uci("e2e4").unwrap();
+    let mv = game.find_best_move().unwrap();
+    game.apply_move(&mv);
+    assert_eq!(game.get_board().fullmove_number(), 2);
+}
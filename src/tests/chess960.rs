@@ -0,0 +1,97 @@
+use crate::board::bitboard::{CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE, START_FEN};
+use crate::board::chess960::{back_rank, start_fen};
+use crate::board::types::Square;
+use crate::board::{BitboardBoard, Board};
+use crate::uci::UciEngine;
+use super::perft::{board_from_fen, perft, play};
+
+#[test]
+fn chess960_start_positions() {
+    assert_eq!(start_fen(518).unwrap(), START_FEN);
+    assert_eq!(back_rank(0).unwrap().iter().collect::<String>(), "BBQNNRKR");
+    assert_eq!(back_rank(959).unwrap().iter().collect::<String>(), "RKRNNQBB");
+    assert!(back_rank(960).is_none());
+
+    let mut seen = std::collections::HashSet::new();
+    for index in 0..960 {
+        let rank = back_rank(index).unwrap();
+        let files = |c: char| -> Vec<usize> { (0..8).filter(|&f| rank[f] == c).collect() };
+        let (bishops, rooks, king) = (files('B'), files('R'), files('K')[0]);
+        assert_ne!(bishops[0] % 2, bishops[1] % 2);
+        assert!(rooks[0] < king && king < rooks[1]);
+        assert!(seen.insert(rank));
+    }
+}
+
+#[test]
+fn chess960_fen_castling_fields() {
+    let board = BitboardBoard::new_chess960(0).unwrap();
+    assert_eq!(board.to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+    assert_eq!(board.to_shredder_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+    assert_eq!(board.castling_rook(CASTLE_WHITE_QUEENSIDE), Square(5));
+
+    // Shredder-FEN letters name the rook, X-FEN only does so when it isn't the outermost one
+    let board = board_from_fen("1r2k1r1/8/8/8/8/8/8/RR2K2R w BHg - 0 1");
+    assert_eq!(board.castling_rook(CASTLE_WHITE_QUEENSIDE), Square(1));
+    assert_eq!(board.castling_rook(CASTLE_WHITE_KINGSIDE), Square(7));
+    assert_eq!(board.to_fen(), "1r2k1r1/8/8/8/8/8/8/RR2K2R w KBk - 0 1");
+    assert_eq!(board.to_shredder_fen(), "1r2k1r1/8/8/8/8/8/8/RR2K2R w HBg - 0 1");
+    assert_eq!(board_from_fen(&board.to_fen()).to_shredder_fen(), board.to_shredder_fen());
+}
+
+#[test]
+fn chess960_perft() {
+    let cases = [
+        ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189]),
+        ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", [20, 479, 10471]),
+        ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", [26, 568, 13744]),
+    ];
+    for (fen, counts) in cases {
+        let mut board = board_from_fen(fen);
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(perft(&mut board, depth as u32 + 1), count, "{} depth {}", fen, depth + 1);
+        }
+    }
+}
+
+#[test]
+fn chess960_castling_moves_king_and_rook() {
+    // the king lands on the rook's square and the rook jumps over it
+    let mut board = board_from_fen("4k3/8/8/8/8/8/8/5KR1 w G - 0 1");
+    board.chess960 = true;
+    let before = board.to_fen();
+    let mv = board.generate_moves().into_iter().find(|m| m.castling.is_some()).unwrap();
+    assert_eq!(board.move_to_uci(&mv), "f1g1");
+    board.make_move(&mv);
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+    board.unmake_move(&mv);
+    assert_eq!(board.to_fen(), before);
+
+    let mut board = board_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    play(&mut board, "e1g1");
+    assert_eq!(board.to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+}
+
+#[test]
+fn chess960_uci_option() {
+    // only castling gets the rook to d1, where it mates as nothing can block the d-file
+    let mate = "2rkr3/2p1p3/8/8/8/8/8/RK6 w Q - 0 1";
+    let mut engine = UciEngine::new();
+    let mut out = Vec::new();
+    engine.handle_command("setoption name UCI_Chess960 value true", &mut out);
+    engine.handle_command(&format!("position fen {}", mate), &mut out);
+    engine.handle_command("go depth 2", &mut out);
+    let output = String::from_utf8(std::mem::take(&mut out)).unwrap();
+    assert!(output.ends_with("bestmove b1a1\n"), "{}", output);
+
+    // played back as castling, Black is mated and has no move left
+    engine.handle_command(&format!("position fen {} moves b1a1", mate), &mut out);
+    engine.handle_command("go depth 1", &mut out);
+    let output = String::from_utf8(out).unwrap();
+    assert!(output.ends_with("bestmove 0000\n"), "{}", output);
+
+    let mut board = board_from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    board.chess960 = true;
+    play(&mut board, "e1h1");
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+}
@@ -0,0 +1,53 @@
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::{CompactMove, PieceKind, Square};
+use super::perft::board_from_fen;
+
+fn compact(board: &BitboardBoard, uci: &str) -> CompactMove {
+    let mv = board.generate_moves().into_iter().find(|m| board.move_to_uci(m) == uci).unwrap();
+    board.compact_move(&mv)
+}
+
+#[test]
+fn compact_move_is_two_bytes() {
+    assert_eq!(std::mem::size_of::<CompactMove>(), 2);
+    let mv = CompactMove::new(Square(12), Square(28), CompactMove::DOUBLE_PUSH);
+    assert_eq!((mv.from(), mv.to(), mv.flag()), (Square(12), Square(28), CompactMove::DOUBLE_PUSH));
+}
+
+#[test]
+fn compact_move_flags() {
+    let board = board_from_fen("r3k2r/1P6/8/3pP3/8/8/6P1/R3K2R w KQkq d6 0 1");
+    assert!(compact(&board, "g2g4").is_double_push());
+    assert!(compact(&board, "e5d6").is_en_passant() && compact(&board, "e5d6").is_capture());
+    assert_eq!(compact(&board, "e1g1").flag(), CompactMove::KING_CASTLE);
+    assert_eq!(compact(&board, "e1c1").flag(), CompactMove::QUEEN_CASTLE);
+    assert_eq!(compact(&board, "e1c1").to(), Square(0));
+    assert_eq!(compact(&board, "a1a8").flag(), CompactMove::CAPTURE);
+    assert_eq!(compact(&board, "e1f1").flag(), CompactMove::QUIET);
+
+    let promotion = compact(&board, "b7a8n");
+    assert!(promotion.is_capture());
+    assert_eq!(promotion.promotion(), Some(PieceKind::Knight));
+    assert_eq!(compact(&board, "b7b8q").promotion(), Some(PieceKind::Queen));
+    assert!(!compact(&board, "b7b8q").is_capture());
+}
+
+#[test]
+fn compact_move_round_trips() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    ];
+    for fen in fens {
+        let mut board = board_from_fen(fen);
+        board.chess960 = true;
+        for mv in board.generate_moves() {
+            let compact = board.compact_move(&mv);
+            assert_eq!(board.expand_move(compact), Some(mv), "{}", fen);
+            assert_eq!(compact.to_uci(true), board.move_to_uci(&mv));
+            assert_eq!(compact.to_uci(false), mv.to_uci());
+        }
+    }
+}
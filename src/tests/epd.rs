@@ -0,0 +1,115 @@
+use crate::board::{BitboardBoard, Board};
+use crate::board::epd::EpdRecord;
+use crate::board::san::{move_to_san, parse_san};
+use crate::evaluation::SimpleEvaluator;
+use crate::search::AlphaBetaSearcher;
+use crate::search::testsuite::run_testsuite;
+use super::perft::{board_from_fen, POSITIONS};
+
+#[test]
+fn parses_operations() {
+    let record = EpdRecord::parse(
+        r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6 Qh4; am Qf4;id "WAC.001; mate";acd 12; ce -35; hmvc 3; fmvn 27"#,
+    ).unwrap();
+    assert_eq!(record.board.to_fen(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 3 27");
+    assert_eq!(record.operation("bm").unwrap(), ["Qg6", "Qh4"]);
+    assert_eq!(record.id(), Some("WAC.001; mate"));
+    assert_eq!(record.acd(), Some(12));
+    assert_eq!(record.ce(), Some(-35));
+    assert_eq!(record.operation("xx"), None);
+
+    let uci: Vec<String> = record.best_moves().iter().map(|m| m.to_uci()).collect();
+    assert_eq!(uci, ["g3g6", "g3h4"]);
+    assert_eq!(record.avoid_moves()[0].to_uci(), "g3f4");
+
+    // no operations and no counters
+    let record = EpdRecord::parse("8/8/8/8/8/8/8/K6k b - -").unwrap();
+    assert!(record.operations.is_empty());
+    assert_eq!(record.board.to_fen(), "8/8/8/8/8/8/8/K6k b - - 0 1");
+
+    assert!(EpdRecord::parse("8/8/8/8/8/8/K6k w - -").is_err());
+    assert!(EpdRecord::parse("8/8/8/8/8/8/8/K6k x - -").is_err());
+    assert!(EpdRecord::parse("8/8/8/8/8/8/8/K6k w -").is_err());
+    assert!(EpdRecord::parse(r#"8/8/8/8/8/8/8/K6k w - - id "open"#).is_err());
+}
+
+#[test]
+fn sts_move_points() {
+    let record = EpdRecord::parse(
+        r#"1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - - bm Qd1+; id "STS(v1.0) Undermine.001"; c0 "Qd1+=10, Qb6=4, e8=Q=3";"#,
+    ).unwrap();
+    let points: Vec<(String, u32)> = record.move_points().iter().map(|(m, p)| (m.to_uci(), *p)).collect();
+    // e8=Q is not a legal move here and is left out
+    assert_eq!(points, [("d6d1".to_string(), 10), ("d6b6".to_string(), 4)]);
+
+    let record = EpdRecord::parse("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd8#;").unwrap();
+    assert_eq!(record.move_points().len(), 1);
+    assert_eq!(record.move_points()[0].1, 10);
+}
+
+#[test]
+fn san_round_trip() {
+    let mut fens: Vec<String> = POSITIONS.lines().map(|epd| format!("{} 0 1", epd)).collect();
+    fens.push("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string());
+    for fen in fens {
+        let mut board = board_from_fen(&fen);
+        for mv in board.generate_moves() {
+            let san = move_to_san(&mut board, &mv);
+            assert_eq!(parse_san(&board, &san), Some(mv), "{} in {}", san, fen);
+            assert_eq!(parse_san(&board, &board.move_to_uci(&mv)), Some(mv));
+        }
+    }
+}
+
+#[test]
+fn san_notation() {
+    let mut board = board_from_fen("r3k2r/1P6/8/3pP3/8/2N3N1/8/R3K2R w KQkq d6 0 1");
+    let san = |board: &mut BitboardBoard, uci: &str| {
+        let mv = board.generate_moves().into_iter().find(|m| m.to_uci() == uci).unwrap();
+        move_to_san(board, &mv)
+    };
+    assert_eq!(san(&mut board, "e5d6"), "exd6");
+    assert_eq!(san(&mut board, "e1g1"), "O-O");
+    assert_eq!(san(&mut board, "e1c1"), "O-O-O");
+    assert_eq!(san(&mut board, "c3e4"), "Nce4");
+    assert_eq!(san(&mut board, "b7a8q"), "bxa8=Q+");
+    assert_eq!(san(&mut board, "a1a8"), "Rxa8+");
+    assert_eq!(san(&mut board, "b7b8n"), "b8=N");
+
+    assert_eq!(parse_san(&board, "Nc3e4").unwrap().to_uci(), "c3e4");
+    assert_eq!(parse_san(&board, "exd6 e.p.").unwrap().to_uci(), "e5d6");
+    assert_eq!(parse_san(&board, "0-0-0").unwrap().to_uci(), "e1c1");
+    assert_eq!(parse_san(&board, "b8Q").unwrap().to_uci(), "b7b8q");
+    // ambiguous, missing and illegal moves
+    assert_eq!(parse_san(&board, "Ne4"), None);
+    assert_eq!(parse_san(&board, "Qd1"), None);
+    assert_eq!(parse_san(&board, "R18"), None);
+    assert_eq!(parse_san(&board, ""), None);
+
+    let mut board = board_from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1");
+    assert_eq!(san(&mut board, "d1d8"), "Rd8#");
+}
+
+#[test]
+fn testsuite_scores_positions() {
+    let records: Vec<EpdRecord> = [
+        r#"6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - bm Rd8#; id "mate";"#,
+        r#"4k3/8/8/3q4/8/8/3R4/4K3 w - - bm Rxd5; id "queen";"#,
+        r#"4k3/8/8/3q4/8/8/3R4/4K3 w - - am Rxd5; id "avoid";"#,
+        r#"4k3/8/8/3q4/8/8/3R4/4K3 w - - id "points"; c0 "Rd4=2, Rxd5=10";"#,
+    ].iter().map(|line| EpdRecord::parse(line).unwrap()).collect();
+
+    let mut results = Vec::new();
+    let summary = run_testsuite(&records, &AlphaBetaSearcher::new(2), &SimpleEvaluator, &mut |record, result| {
+        results.push((record.id().unwrap().to_string(), result.san.clone(), result.solved));
+    });
+    assert_eq!(results[0], ("mate".to_string(), "Rd8#".to_string(), true));
+    assert_eq!(results[1], ("queen".to_string(), "Rxd5".to_string(), true));
+    assert!(!results[2].2);
+    // only c0 points, so no solved verdict
+    assert!(!results[3].2);
+    assert_eq!(summary.positions, 4);
+    assert_eq!(summary.solved, 2);
+    assert_eq!(summary.points, 10 + 10 + 10);
+    assert_eq!(summary.max_points, 10 + 10 + 10);
+}
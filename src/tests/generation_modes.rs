@@ -0,0 +1,97 @@
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::{Move, PieceKind};
+use crate::move_generation::{is_evasion, is_tactical, BitboardMoveGenerator, GenerationMode};
+use crate::move_generation::naive_move_generator::NaiveMoveGenerator;
+use super::perft::{board_from_fen, positions, TRICKY_FENS};
+
+const CHECK_FENS: [&str; 4] = [
+    // double check from knight and rook
+    "4r1k1/8/8/8/8/3n4/8/4K3 w - - 0 1",
+    // a checking pawn that can be taken en passant
+    "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+    // discovered checks
+    "4k3/8/8/8/4B3/8/4R3/R3K2R w KQ - 0 1",
+    // castling into check from the rook
+    "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+];
+
+fn sorted(mut moves: Vec<Move>) -> Vec<String> {
+    let mut names: Vec<String> = moves.drain(..).map(|m| m.to_uci()).collect();
+    names.sort();
+    names
+}
+
+fn gives_check(board: &BitboardBoard, mv: &Move) -> bool {
+    let mut next = board.clone();
+    next.make_move(mv);
+    next.is_in_check(next.side_to_move())
+}
+
+fn check_modes(board: &BitboardBoard) {
+    let all = board.generate_moves();
+    let expected = |keep: &dyn Fn(&Move) -> bool| sorted(all.iter().filter(|m| keep(m)).collect());
+    let generated = |mode| sorted(board.generate_moves_of(mode).iter().collect());
+    let fen = board.to_fen();
+
+    assert_eq!(generated(GenerationMode::All), expected(&|_| true), "{}", fen);
+    assert_eq!(generated(GenerationMode::Captures), expected(&is_tactical), "{}", fen);
+    assert_eq!(generated(GenerationMode::Quiets), expected(&|m| !is_tactical(m)), "{}", fen);
+    assert_eq!(generated(GenerationMode::Evasions), expected(&|_| true), "{}", fen);
+    assert_eq!(generated(GenerationMode::QuietChecks), expected(&|m| !is_tactical(m) && gives_check(board, m)), "{}", fen);
+
+    // the generator builds captures and quiet checks itself, so compare its pseudo-legal lists
+    // with the filtered full list too
+    let pseudo = NaiveMoveGenerator.generate_moves(board);
+    let filtered = |keep: &dyn Fn(&Move) -> bool| sorted(pseudo.iter().filter(|m| keep(m)).collect());
+    let direct = |mode| sorted(NaiveMoveGenerator.generate_moves_of(board, mode).iter().collect());
+    assert_eq!(direct(GenerationMode::Captures), filtered(&is_tactical), "{}", fen);
+    assert_eq!(direct(GenerationMode::QuietChecks), filtered(&|m| !is_tactical(m) && board.gives_check(m)), "{}", fen);
+}
+
+#[test]
+fn generation_modes_match_filtered_moves() {
+    for fen in TRICKY_FENS.into_iter().chain(CHECK_FENS) {
+        let board = board_from_fen(fen);
+        check_modes(&board);
+        // and every position one move further, which includes plenty of checks
+        for mv in board.generate_moves() {
+            let mut next = board.clone();
+            next.make_move(&mv);
+            check_modes(&next);
+        }
+    }
+    for board in positions() {
+        check_modes(&board);
+    }
+}
+
+#[test]
+fn evasions_are_generated_directly() {
+    // only king moves are generated against a double check
+    let board = board_from_fen(CHECK_FENS[0]);
+    assert_eq!(board.checkers().count_ones(), 2);
+    let pseudo = NaiveMoveGenerator.generate_moves_of(&board, GenerationMode::Evasions);
+    assert!(!pseudo.is_empty() && pseudo.iter().all(|m| m.piece.kind == PieceKind::King));
+
+    // against a single check the pieces only keep captures of the checker and blocks
+    let board = board_from_fen(CHECK_FENS[1]);
+    let checkers = board.checkers();
+    let pseudo = NaiveMoveGenerator.generate_moves_of(&board, GenerationMode::Evasions);
+    let filtered = NaiveMoveGenerator.generate_moves(&board).iter().filter(|m| is_evasion(&board, checkers, m)).collect();
+    assert_eq!(sorted(pseudo.iter().collect()), sorted(filtered));
+    let evasions = sorted(board.generate_moves_of(GenerationMode::Evasions).iter().collect());
+    assert!(evasions.contains(&"e4d3".to_string()), "{:?}", evasions);
+}
+
+#[test]
+fn quiet_checks_include_discoveries_and_castling() {
+    let board = board_from_fen(CHECK_FENS[2]);
+    let checks = sorted(board.generate_moves_of(GenerationMode::QuietChecks).iter().collect());
+    // the bishop uncovers the e2 rook
+    assert!(checks.contains(&"e4d5".to_string()) && checks.contains(&"a1a8".to_string()), "{:?}", checks);
+    assert!(!checks.contains(&"e1c1".to_string()));
+
+    let board = board_from_fen(CHECK_FENS[3]);
+    let checks = sorted(board.generate_moves_of(GenerationMode::QuietChecks).iter().collect());
+    assert!(checks.contains(&"e1g1".to_string()), "{:?}", checks);
+}
@@ -0,0 +1,30 @@
+use crate::board::types::{Color, Piece, PieceKind};
+use crate::board::Board;
+use crate::evaluation::pst::{Scores, MAX_PHASE};
+use crate::evaluation::{Evaluator, SimpleEvaluator};
+use super::perft::{board_from_fen, walk, TRICKY_FENS};
+
+#[test]
+fn incremental_scores_match_recompute() {
+    for fen in TRICKY_FENS {
+        // the totals have to match a recompute after every make and unmake
+        walk(&mut board_from_fen(fen), 3, &|board, mv, _| {
+            assert_eq!(*board.scores(), Scores::compute(board), "{} with {:?}", board.to_fen(), mv);
+        });
+    }
+}
+
+#[test]
+fn start_position_scores() {
+    let board = board_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    let scores = board.scores();
+    assert_eq!((scores.material, scores.middlegame, scores.endgame), (0, 0, 0));
+    assert_eq!(scores.piece_count(Piece { color: Color::Black, kind: PieceKind::Pawn }), 8);
+    assert_eq!(scores.phase(), MAX_PHASE);
+    assert_eq!(SimpleEvaluator.evaluate(&board), 0);
+
+    // without pieces only the endgame tables count
+    let board = board_from_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1");
+    assert_eq!(board.scores().phase(), 0);
+    assert_eq!(board.scores().positional(), board.scores().endgame);
+}
@@ -0,0 +1,29 @@
+use crate::board::types::{Color, Piece, PieceKind, Square};
+use crate::board::Board;
+use super::perft::{board_from_fen, walk, TRICKY_FENS};
+
+#[test]
+fn mailbox_follows_make_and_unmake() {
+    // and Chess960 castling where the king and rook swap squares
+    for fen in TRICKY_FENS.into_iter().chain(["4k3/8/8/8/8/8/8/5KR1 w G - 0 1"]) {
+        walk(&mut board_from_fen(fen), 3, &|board, mv, undone| {
+            if let Err(err) = board.check_mailbox() {
+                panic!("{} {} {:?}: {}", board.to_fen(), if undone { "after undoing" } else { "after" }, mv, err);
+            }
+        });
+    }
+}
+
+#[test]
+fn mailbox_piece_at() {
+    let board = board_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    assert_eq!(board.piece_at(Square::from_notation("e4").unwrap()), Some(Piece { color: Color::White, kind: PieceKind::Pawn }));
+    assert_eq!(board.piece_at(Square::from_notation("d8").unwrap()), Some(Piece { color: Color::Black, kind: PieceKind::Queen }));
+    assert_eq!(board.piece_at(Square::from_notation("e2").unwrap()), None);
+    assert!(board.check_mailbox().is_ok());
+
+    // writing a bitboard directly leaves the mailbox behind
+    let mut board = board;
+    board.bitboards[1] |= 1 << 20;
+    assert_eq!(board.check_mailbox(), Err("e3 holds None in the mailbox but Some(Piece { color: White, kind: Knight }) on the bitboards".to_string()));
+}
@@ -0,0 +1,105 @@
+use crate::board::{BitboardBoard, Board};
+use crate::search::mate::{MateResult, MateSolver, MateTree};
+use super::perft::board_from_fen;
+
+/// Every defence must be answered and every line must end in mate within `moves`.
+fn assert_mates(board: &mut BitboardBoard, tree: &MateTree, moves: u8) {
+    assert!(moves > 0);
+    board.make_move(&tree.key);
+    let defences = board.generate_moves();
+    if defences.is_empty() {
+        assert!(board.is_in_check(board.side_to_move()));
+    } else {
+        assert_eq!(defences.len(), tree.replies.len());
+        for (reply, continuation) in &tree.replies {
+            assert!(defences.contains(reply));
+            board.make_move(reply);
+            assert_mates(board, continuation, moves - 1);
+            board.unmake_move(reply);
+        }
+    }
+    board.unmake_move(&tree.key);
+}
+
+fn solve(fen: &str, moves: u8, checks_only: bool) -> MateResult {
+    let mut board = board_from_fen(fen);
+    let result = MateSolver::new(checks_only).solve(&board, moves);
+    if let MateResult::Mate { moves, tree } = &result {
+        assert_eq!(tree.length(), *moves);
+        assert_mates(&mut board, tree, *moves);
+    }
+    result
+}
+
+#[test]
+fn finds_shortest_mates() {
+    let back_rank = "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1";
+    match solve(back_rank, 3, true) {
+        MateResult::Mate { moves, tree } => {
+            assert_eq!(moves, 1);
+            assert_eq!(tree.key.to_uci(), "d1d8");
+            assert!(tree.replies.is_empty());
+        }
+        result => panic!("{:?}", result),
+    }
+
+    // WAC.001: Qg6 and either Qh7# or Nxg6#
+    let result = solve("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1", 3, false);
+    assert!(matches!(result, MateResult::Mate { moves: 2, ref tree } if tree.key.to_uci() == "g3g6"));
+
+    // Black to move
+    let result = solve("6k1/8/8/8/8/8/5PPP/3r2K1 b - - 0 1", 1, true);
+    assert!(matches!(result, MateResult::Mate { moves: 1, .. }));
+
+    // nothing mates in zero moves
+    assert_eq!(solve(back_rank, 0, false), MateResult::NoMate { refutations: Vec::new() });
+}
+
+#[test]
+fn quiet_keys_need_all_moves() {
+    let fen = "kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1";
+    match solve(fen, 2, true) {
+        MateResult::NoMate { refutations } => {
+            assert_eq!(refutations.len(), 1);
+            assert_eq!(refutations[0].attack.to_uci(), "a1a7");
+            assert_eq!(refutations[0].defence.unwrap().to_uci(), "b8a7");
+        }
+        result => panic!("{:?}", result),
+    }
+    match solve(fen, 2, false) {
+        MateResult::Mate { moves, tree } => {
+            assert_eq!(moves, 2);
+            assert_eq!(tree.key.to_uci(), "a1a6");
+        }
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn refutes_escapes_and_stalemates() {
+    let fen = "7k/8/6K1/8/8/8/8/4NB2 w - - 0 1";
+    match solve(fen, 2, false) {
+        MateResult::NoMate { refutations } => {
+            // every move is a try, Bc4 takes the last squares away without check
+            assert_eq!(refutations.len(), board_from_fen(fen).generate_moves().len());
+            for refutation in refutations {
+                assert_eq!(refutation.defence.is_none(), refutation.attack.to_uci() == "f1c4");
+            }
+        }
+        result => panic!("{:?}", result),
+    }
+
+    // only checks are tried on the last move
+    match solve(fen, 1, false) {
+        MateResult::NoMate { refutations } => assert!(refutations.is_empty()),
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn node_limit_leaves_it_open() {
+    let board = board_from_fen("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+    let mut solver = MateSolver::new(false).with_node_limit(20);
+    assert_eq!(solver.solve(&board, 3), MateResult::Unknown);
+    assert!(solver.nodes() > 20 && solver.nodes() < 30);
+}
@@ -0,0 +1,117 @@
+use std::rc::Rc;
+use crate::board::{BitboardBoard, Board};
+use crate::evaluation::SimpleEvaluator;
+use crate::game::GameManager;
+use crate::search::Searcher;
+use crate::search::mcts::{win_probability, LeafValue, MctsSearcher, Selection};
+use super::perft::board_from_fen;
+
+fn best_uci(searcher: &MctsSearcher, board: &BitboardBoard) -> String {
+    searcher.search(board, &SimpleEvaluator).unwrap().to_uci()
+}
+
+#[test]
+fn win_probability_is_centred() {
+    assert_eq!(win_probability(0), 0.5);
+    assert!((win_probability(400) - 10.0 / 11.0).abs() < 1e-9);
+    assert!((win_probability(-250) + win_probability(250) - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn finds_material_and_mates() {
+    let queen = board_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+    let mate = board_from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1");
+    for selection in [Selection::Uct, Selection::Puct] {
+        let searcher = || MctsSearcher::new(2000).with_selection(selection).with_tree_reuse(false);
+        assert_eq!(best_uci(&searcher(), &queen), "d2d5");
+        assert_eq!(best_uci(&searcher(), &mate), "d1d8");
+    }
+    let rollouts = MctsSearcher::new(3000).with_leaf_value(LeafValue::Rollout(8)).with_seed(7);
+    assert_eq!(best_uci(&rollouts, &queen), "d2d5");
+}
+
+#[test]
+fn lines_are_ordered_by_visits() {
+    let board = BitboardBoard::new_startpos();
+    let searcher = MctsSearcher::new(500).with_exploration(2.0);
+    let lines = searcher.search_lines(&board, &SimpleEvaluator, 3, &mut |_| {});
+    assert_eq!(lines.len(), 3);
+    assert_eq!(searcher.visits(), 500);
+    for line in &lines {
+        assert_eq!(line.depth as usize, line.pv.len());
+        let mut board = board.clone();
+        for mv in &line.pv {
+            assert!(board.generate_moves().contains(mv));
+            board.make_move(mv);
+        }
+    }
+
+    // nothing to search once the game is over
+    let stalemate = board_from_fen("k7/P7/1K6/8/8/8/8/8 b - - 0 1");
+    assert!(searcher.search_lines(&stalemate, &SimpleEvaluator, 1, &mut |_| {}).is_empty());
+}
+
+#[test]
+fn seeded_rollouts_repeat() {
+    let board = board_from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let search = || {
+        let searcher = MctsSearcher::new(300).with_selection(Selection::Uct).with_leaf_value(LeafValue::Rollout(20)).with_seed(49);
+        searcher.search_lines(&board, &SimpleEvaluator, 2, &mut |_| {})
+    };
+    assert_eq!(search(), search());
+}
+
+#[test]
+fn tree_is_reused_after_two_plies() {
+    let mut board = BitboardBoard::new_startpos();
+    let searcher = MctsSearcher::new(2000);
+    let line = searcher.search_lines(&board, &SimpleEvaluator, 1, &mut |_| {}).remove(0);
+    assert_eq!(searcher.reused_visits(), 0);
+
+    // our move and the expected reply keep most of the tree
+    board.make_move(&line.pv[0]);
+    board.make_move(&line.pv[1]);
+    searcher.search(&board, &SimpleEvaluator);
+    assert!(searcher.reused_visits() > 0);
+    assert_eq!(searcher.visits(), searcher.reused_visits() + 2000);
+
+    // an unrelated position starts over, and so does a searcher told not to reuse
+    let other = board_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+    searcher.search(&other, &SimpleEvaluator);
+    assert_eq!(searcher.reused_visits(), 0);
+    let fresh = MctsSearcher::new(100).with_tree_reuse(false);
+    fresh.search(&other, &SimpleEvaluator);
+    fresh.search(&other, &SimpleEvaluator);
+    assert_eq!(fresh.reused_visits(), 0);
+}
+
+#[test]
+fn game_manager_plays_with_mcts() {
+    let mut game = GameManager::new();
+    game.set_searcher(Box::new(MctsSearcher::new(300)));
+    for _ in 0..6 {
+        let mv = game.find_best_move().unwrap();
+        assert!(game.get_board().generate_moves().contains(&mv));
+        game.apply_move(&mv);
+    }
+    assert_eq!(game.get_board().fullmove_number(), 4);
+}
+
+#[test]
+fn game_manager_keeps_the_tree_between_moves() {
+    let searcher = Rc::new(MctsSearcher::new(1000).with_seed(7));
+    let mut game = GameManager::new();
+    game.set_searcher(Box::new(searcher.clone()));
+    let line = game.find_best_lines().remove(0);
+    game.apply_move(&line.pv[0]);
+    game.apply_move(&line.pv[1]);
+    game.find_best_move().unwrap();
+    assert!(searcher.reused_visits() > 0);
+
+    // a new position starts over, even the same one
+    let fen = game.get_fen();
+    game.load_from_fen(&fen);
+    assert_eq!(searcher.visits(), 0);
+    game.find_best_move().unwrap();
+    assert_eq!(searcher.reused_visits(), 0);
+}
@@ -1,4 +1,26 @@
 // crate name
 
 mod pseudo_moves;
-
+mod search;
+mod polyglot;
+mod syzygy;
+mod syzygy_generator;
+mod arena;
+mod chess960;
+mod perft;
+mod compact_move;
+mod move_list;
+mod generation_modes;
+mod move_validation;
+mod attacks;
+mod incremental_scores;
+mod mailbox;
+mod null_move;
+mod board_differential;
+mod symmetry;
+mod serialization;
+mod epd;
+mod mate;
+mod proof_number;
+mod mcts;
+mod skill;
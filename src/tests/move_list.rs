@@ -0,0 +1,89 @@
+use crate::board::Board;
+use crate::board::types::{Color, Move, Piece, PieceKind, Square};
+use crate::move_generation::MoveList;
+use super::perft::{board_from_fen, positions};
+
+fn quiet(from: u8, to: u8) -> Move {
+    Move::new(Square(from), Square(to), Piece { color: Color::White, kind: PieceKind::Knight }, None, None)
+}
+
+fn scored(scores: &[i32]) -> MoveList {
+    let mut list = MoveList::new();
+    for (i, &score) in scores.iter().enumerate() {
+        list.push_scored(quiet(i as u8, 63), score);
+    }
+    list
+}
+
+fn origins(list: &MoveList) -> Vec<u8> {
+    list.iter().map(|m| m.from.0).collect()
+}
+
+#[test]
+fn move_list_push_and_iterate() {
+    let mut list = MoveList::new();
+    assert!(list.is_empty());
+    list.push(quiet(1, 18));
+    list.push_scored(quiet(6, 21), 5);
+    assert_eq!(list.len(), 2);
+    assert_eq!(list.get(1).map(|m| m.to), Some(Square(21)));
+    assert_eq!(list.iter_scored().map(|(_, s)| s).collect::<Vec<_>>(), vec![0, 5]);
+    assert_eq!(list.clone().into_iter().count(), 2);
+    assert!(list.contains(&quiet(1, 18)));
+}
+
+#[test]
+fn move_list_sort_is_stable_and_descending() {
+    let mut list = scored(&[1, 7, 3, 7, -2]);
+    list.sort();
+    assert_eq!(origins(&list), vec![1, 3, 2, 0, 4]);
+    assert_eq!(list.score(0), 7);
+}
+
+#[test]
+fn move_list_select_best_picks_in_score_order() {
+    let mut list = scored(&[2, 9, 4, 0]);
+    let mut picked = Vec::new();
+    let mut index = 0;
+    while let Some(mv) = list.select_best(index) {
+        picked.push(mv.from.0);
+        index += 1;
+    }
+    assert_eq!(picked, vec![1, 2, 0, 3]);
+}
+
+#[test]
+fn move_list_retain_keeps_scores() {
+    let mut list = scored(&[10, 20, 30, 40]);
+    list.retain(|m| m.from.0 % 2 == 1);
+    assert_eq!(origins(&list), vec![1, 3]);
+    assert_eq!((list.score(0), list.score(1)), (20, 40));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "move list is full")]
+fn move_list_overflow_is_caught() {
+    use crate::move_generation::move_list::MAX_MOVES;
+    let mut list = MoveList::new();
+    for _ in 0..=MAX_MOVES {
+        list.push(quiet(1, 18));
+    }
+}
+
+#[test]
+fn move_list_expands_its_compact_moves() {
+    // en passant, castling both ways and capturing promotions
+    let mut boards = positions();
+    boards.push(board_from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1"));
+    for board in &boards {
+        let moves = board.generate_moves();
+        let mut again = MoveList::with_en_passant(board.en_passant_square());
+        for (i, mv) in moves.iter().enumerate() {
+            assert_eq!(moves.compact(i), board.compact_move(&mv), "{}", board.to_fen());
+            assert_eq!(board.expand_move(moves.compact(i)), Some(mv), "{}", board.to_fen());
+            again.push(mv);
+        }
+        assert_eq!(again, moves);
+    }
+}
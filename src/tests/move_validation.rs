@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::{CompactMove, Square};
+use crate::move_generation::BitboardMoveGenerator;
+use crate::move_generation::naive_move_generator::NaiveMoveGenerator;
+use crate::utils::random::Random;
+use super::perft::{board_from_fen, TRICKY_FENS};
+
+const START_FENS: [&str; 2] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+];
+
+/// Positions reached by random playouts from a few varied starts.
+fn random_positions(rng: &mut Random) -> Vec<BitboardBoard> {
+    let mut positions = Vec::new();
+    for fen in START_FENS.into_iter().chain(TRICKY_FENS) {
+        for _ in 0..4 {
+            let mut board = board_from_fen(fen);
+            for _ in 0..30 {
+                let moves = board.generate_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                board.make_move(&moves.get(rng.next_below(moves.len() as u64) as usize).unwrap());
+                positions.push(board.clone());
+            }
+        }
+    }
+    positions
+}
+
+#[test]
+fn move_validation_matches_generation() {
+    let mut rng = Random::new(38);
+    let positions = random_positions(&mut rng);
+
+    // candidates: every move seen anywhere plus random encodings, which mostly make no sense
+    let mut candidates = HashSet::new();
+    for board in &positions {
+        for mv in NaiveMoveGenerator.generate_moves(board).iter() {
+            candidates.insert(board.compact_move(&mv));
+        }
+    }
+    for _ in 0..2000 {
+        candidates.insert(CompactMove(rng.next_u64() as u16));
+    }
+
+    for board in &positions {
+        let pseudo: HashSet<CompactMove> = NaiveMoveGenerator.generate_moves(board).iter().map(|m| board.compact_move(&m)).collect();
+        let legal: HashSet<CompactMove> = board.generate_moves().iter().map(|m| board.compact_move(&m)).collect();
+        let mut board = board.clone();
+        let fen = board.to_fen();
+        for &mv in &candidates {
+            assert_eq!(board.is_pseudo_legal(mv), pseudo.contains(&mv), "{} {} flag {}", fen, mv.to_uci(true), mv.flag());
+            assert_eq!(board.is_legal(mv), legal.contains(&mv), "{} {} flag {}", fen, mv.to_uci(true), mv.flag());
+        }
+        assert_eq!(board.to_fen(), fen);
+    }
+}
+
+#[test]
+fn move_validation_special_moves() {
+    let mut board = board_from_fen("r3k2r/1P6/8/3pP3/8/8/6P1/R3K2R w KQkq d6 0 1");
+    assert!(board.is_legal(CompactMove::new(Square(4), Square(7), CompactMove::KING_CASTLE)));
+    assert!(!board.is_legal(CompactMove::new(Square(4), Square(7), CompactMove::QUEEN_CASTLE)));
+    assert!(board.is_legal(CompactMove::new(Square(36), Square(43), CompactMove::EN_PASSANT)));
+    assert!(!board.is_legal(CompactMove::new(Square(36), Square(43), CompactMove::CAPTURE)));
+    assert!(board.is_legal(CompactMove::new(Square(14), Square(30), CompactMove::DOUBLE_PUSH)));
+    assert!(!board.is_legal(CompactMove::new(Square(14), Square(30), CompactMove::QUIET)));
+    assert!(board.is_legal(CompactMove::new(Square(49), Square(57), CompactMove::PROMOTION | 3)));
+    assert!(!board.is_legal(CompactMove::new(Square(49), Square(57), CompactMove::QUIET)));
+    assert!(!board.is_legal(CompactMove::NONE));
+}
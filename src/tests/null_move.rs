@@ -0,0 +1,40 @@
+use crate::board::Board;
+use crate::game::GameManager;
+use crate::search::AlphaBetaSearcher;
+use super::perft::board_from_fen;
+
+#[test]
+fn null_move_round_trip() {
+    let mut board = board_from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3");
+    let (fen, hash) = (board.to_fen(), board.hash());
+
+    assert!(board.make_null_move());
+    assert_eq!(board.to_fen(), "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 4");
+    assert_eq!(board.hash(), board_from_fen(&board.to_fen()).hash());
+
+    board.unmake_null_move();
+    assert_eq!(board.to_fen(), fen);
+    assert_eq!(board.hash(), hash);
+}
+
+#[test]
+fn null_move_refused_in_check() {
+    let mut board = board_from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1");
+    let fen = board.to_fen();
+    assert!(!board.make_null_move());
+    assert_eq!(board.to_fen(), fen);
+}
+
+#[test]
+fn threat_shows_the_opponents_best_move() {
+    let mut game = GameManager::new();
+    game.set_searcher(Box::new(AlphaBetaSearcher::new(2)));
+    game.load_from_fen("4k3/8/8/4p3/3N4/8/8/4K3 w - - 0 1");
+    let threat = game.find_threat().unwrap();
+    assert_eq!(threat.pv[0].to_uci(), "e5d4");
+    // the game itself is unchanged
+    assert_eq!(game.get_fen(), "4k3/8/8/4p3/3N4/8/8/4K3 w - - 0 1");
+
+    game.load_from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1");
+    assert!(game.find_threat().is_none());
+}
@@ -0,0 +1,138 @@
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::Move;
+use crate::book::polyglot_key;
+use crate::move_generation::GenerationMode;
+
+/// Positions from random playouts, one EPD record per line.
+pub(super) const POSITIONS: &str = include_str!("data/symmetry.epd");
+
+/// Kiwipete and perft positions 3 and 4, between them castling, en passant, pins and promotions.
+pub(super) const TRICKY_FENS: [&str; 3] = [
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+];
+
+pub(super) fn board_from_fen(fen: &str) -> BitboardBoard {
+    let mut board = BitboardBoard::new_empty(None);
+    board.load_fen(fen);
+    board
+}
+
+pub(super) fn positions() -> Vec<BitboardBoard> {
+    POSITIONS.lines().map(|epd| board_from_fen(&format!("{} 0 1", epd))).collect()
+}
+
+pub(super) fn perft<B: Board>(board: &mut B, depth: u32) -> u64 {
+    let moves = board.generate_legal_moves_of(GenerationMode::All);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves.iter().map(|mv| {
+        board.make_move(&mv);
+        let nodes = perft(board, depth - 1);
+        board.unmake_move(&mv);
+        nodes
+    }).sum()
+}
+
+/// Plays every line `depth` plies deep with make/unmake. `check` runs after each make and,
+/// told so by its last argument, after each unmake.
+pub(super) fn walk(board: &mut BitboardBoard, depth: u32, check: &dyn Fn(&BitboardBoard, &Move, bool)) {
+    if depth == 0 {
+        return;
+    }
+    for mv in board.generate_moves() {
+        board.make_move(&mv);
+        check(board, &mv, false);
+        walk(board, depth - 1, check);
+        board.unmake_move(&mv);
+        check(board, &mv, true);
+    }
+}
+
+/// Plays a move given in UCI, king takes rook castling on a Chess960 board.
+pub(super) fn play(board: &mut BitboardBoard, uci: &str) {
+    let mv = board.generate_moves().into_iter().find(|m| board.move_to_uci(m) == uci).unwrap();
+    board.make_move(&mv);
+}
+
+fn assert_perft(fen: &str, counts: &[u64]) {
+    let mut board = board_from_fen(fen);
+    for (depth, &count) in counts.iter().enumerate() {
+        assert_eq!(perft(&mut board, depth as u32 + 1), count, "{} depth {}", fen, depth + 1);
+    }
+}
+
+#[test]
+fn perft_start_position() {
+    assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902]);
+}
+
+#[test]
+fn perft_kiwipete() {
+    assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]);
+}
+
+#[test]
+fn perft_en_passant_and_pins() {
+    assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812]);
+}
+
+#[test]
+fn perft_promotion_captures_rook() {
+    // b2xa1 takes the rook white would castle with
+    assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]);
+    assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+}
+
+#[test]
+fn perft_rook_captures_on_home_squares() {
+    assert_perft("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", &[26, 568, 13744, 314346]);
+    assert_perft("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", &[26, 1141, 27826]);
+}
+
+#[test]
+fn captured_rook_loses_castling_right() {
+    let mut board = board_from_fen("r3k3/2r5/8/8/8/8/6B1/4K3 w q - 0 1");
+    play(&mut board, "g2a8");
+    assert_eq!(board.to_fen(), "B3k3/2r5/8/8/8/8/8/4K3 b - - 0 1");
+
+    // a rook coming back to a8 does not bring the right back
+    for uci in ["c7a7", "a8b7", "a7a8", "e1d1"] {
+        play(&mut board, uci);
+    }
+    assert!(board.generate_moves().iter().all(|m| m.castling.is_none()));
+}
+
+#[test]
+fn unmake_restores_en_passant_capture() {
+    let mut board = board_from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    let before = board.to_fen();
+    let mv = board.generate_moves().into_iter().find(|m| m.to_uci() == "e5d6").unwrap();
+    board.make_move(&mv);
+    assert_eq!(board.piece_at(crate::board::types::Square(35)), None);
+    board.unmake_move(&mv);
+    assert_eq!(board.to_fen(), before);
+}
+
+#[test]
+fn make_unmake_keep_side_counters_and_hash() {
+    let mut board = board_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 3 7");
+    let fen = board.to_fen();
+    let mut played = Vec::new();
+    for uci in ["b4c3", "e1c1", "a8b8", "d5e6", "c3b2", "c1b1", "a6e2"] {
+        let mv = board.generate_moves().into_iter().find(|m| m.to_uci() == uci).unwrap();
+        let side = board.side_to_move();
+        board.make_move(&mv);
+        assert_eq!(board.side_to_move(), side.opposite());
+        assert_eq!(board.hash(), polyglot_key(&board), "after {}", uci);
+        played.push(mv);
+    }
+    assert_eq!(board.fullmove_number, 11);
+    for mv in played.iter().rev() {
+        board.unmake_move(mv);
+        assert_eq!(board.hash(), polyglot_key(&board));
+    }
+    assert_eq!(board.to_fen(), fen);
+}
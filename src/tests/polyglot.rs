@@ -0,0 +1,122 @@
+use crate::board::{BitboardBoard, Board};
+use crate::book::polyglot::encode_move;
+use crate::book::{polyglot_key, BookEntry, BookSelection, PolyglotBook};
+use crate::game::GameManager;
+use crate::uci::UciEngine;
+use crate::utils::random::Random;
+use super::perft::{board_from_fen, play};
+
+fn entry(board: &BitboardBoard, uci: &str, weight: u16) -> BookEntry {
+    let mv = board.generate_moves().into_iter().find(|m| m.to_uci() == uci).unwrap();
+    BookEntry { key: polyglot_key(board), raw_move: encode_move(&mv), weight, learn: 0 }
+}
+
+#[test]
+fn polyglot_reference_keys() {
+    let cases = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
+        ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+        ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+        ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3", 0x652a607ca3f242c1),
+        ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4", 0x00fdd303c946bdd9),
+        ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+        ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+    ];
+    for (fen, key) in cases {
+        assert_eq!(polyglot_key(&board_from_fen(fen)), key, "{}", fen);
+    }
+}
+
+#[test]
+fn polyglot_key_follows_played_moves() {
+    let mut board = BitboardBoard::new_startpos();
+    for uci in ["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"] {
+        play(&mut board, uci);
+    }
+    assert_eq!(polyglot_key(&board), 0x00fdd303c946bdd9);
+}
+
+#[test]
+fn book_round_trips_through_bytes() {
+    let board = BitboardBoard::new_startpos();
+    let book = PolyglotBook::from_entries(vec![entry(&board, "e2e4", 10), entry(&board, "d2d4", 5)]);
+    let reloaded = PolyglotBook::from_bytes(&book.to_bytes());
+    assert_eq!(reloaded.len(), 2);
+    assert_eq!(reloaded.entries_for(polyglot_key(&board)).len(), 2);
+    assert!(reloaded.entries_for(0).is_empty());
+}
+
+#[test]
+fn book_lookup_finds_entries_among_others() {
+    let board = BitboardBoard::new_startpos();
+    let mut entries: Vec<BookEntry> = (0..100u64)
+        .map(|k| BookEntry { key: k.wrapping_mul(0x9E3779B97F4A7C15), raw_move: 0, weight: 1, learn: 0 })
+        .collect();
+    entries.push(entry(&board, "g1f3", 3));
+    let book = PolyglotBook::from_entries(entries);
+    let moves = book.book_moves(&board);
+    assert_eq!(moves.len(), 1);
+    assert_eq!(moves[0].0.to_uci(), "g1f3");
+}
+
+#[test]
+fn book_best_only_picks_highest_weight() {
+    let board = BitboardBoard::new_startpos();
+    let book = PolyglotBook::from_entries(vec![entry(&board, "d2d4", 5), entry(&board, "e2e4", 10), entry(&board, "c2c4", 1)]);
+    let mv = book.choose_move(&board, BookSelection::BestOnly, &mut Random::new(1)).unwrap();
+    assert_eq!(mv.to_uci(), "e2e4");
+}
+
+#[test]
+fn book_weighted_random_respects_weights() {
+    let board = BitboardBoard::new_startpos();
+    let book = PolyglotBook::from_entries(vec![entry(&board, "e2e4", 3), entry(&board, "d2d4", 1), entry(&board, "a2a3", 0)]);
+    let mut rng = Random::new(42);
+    let mut e4 = 0;
+    for _ in 0..400 {
+        match book.choose_move(&board, BookSelection::WeightedRandom, &mut rng).unwrap().to_uci().as_str() {
+            "e2e4" => e4 += 1,
+            "d2d4" => {}
+            other => panic!("zero weight move {} chosen", other),
+        }
+    }
+    assert!((250..350).contains(&e4), "e2e4 chosen {} times", e4);
+}
+
+#[test]
+fn book_castling_is_stored_as_king_takes_rook() {
+    let board = board_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let castle = entry(&board, "e1g1", 1);
+    assert_eq!(castle.raw_move, (4 << 6) | 7);
+    let book = PolyglotBook::from_entries(vec![castle]);
+    assert_eq!(book.book_moves(&board)[0].0.to_uci(), "e1g1");
+}
+
+#[test]
+fn game_manager_uses_book_up_to_depth() {
+    let mut game_manager = GameManager::new();
+    let mut board = BitboardBoard::new_startpos();
+    let mut entries = vec![entry(&board, "e2e4", 1)];
+    play(&mut board, "e2e4");
+    entries.push(entry(&board, "e7e5", 1));
+    game_manager.set_book(Some(PolyglotBook::from_entries(entries)));
+    game_manager.set_random_seed(7);
+
+    assert_eq!(game_manager.book_move().unwrap().to_uci(), "e2e4");
+    game_manager.make_move_from_uci("e2e4").unwrap();
+    game_manager.set_book_depth(0);
+    assert!(game_manager.book_move().is_none());
+    game_manager.set_book_depth(1);
+    assert_eq!(game_manager.find_best_move().unwrap().to_uci(), "e7e5");
+}
+
+#[test]
+fn uci_reports_book_errors_on_the_output() {
+    let mut engine = UciEngine::new();
+    let mut out = Vec::new();
+    engine.handle_command("setoption name BookFile value no/such/book.bin", &mut out);
+    let output = String::from_utf8(out).unwrap();
+    assert!(output.starts_with("info string could not open book no/such/book.bin"), "{}", output);
+}
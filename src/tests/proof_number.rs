@@ -0,0 +1,82 @@
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::Move;
+use crate::evaluation::SimpleEvaluator;
+use crate::search::{AlphaBetaSearcher, Searcher, MATE_SCORE};
+use crate::search::proof_number::{ProofNumberSearcher, ProofResult};
+use super::perft::board_from_fen;
+
+/// Plays the line and tells whether it ends with the side to move checkmated.
+fn ends_in_mate(board: &BitboardBoard, pv: &[Move]) -> bool {
+    let mut board = board.clone();
+    for mv in pv {
+        assert!(board.generate_moves().contains(mv));
+        board.make_move(mv);
+    }
+    board.generate_moves().is_empty() && board.is_in_check(board.side_to_move())
+}
+
+#[test]
+fn proves_mates_like_alpha_beta() {
+    let puzzles = [
+        ("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", 1),
+        ("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1", 2),
+        ("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", 2),
+        ("r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1", 3),
+    ];
+    for (fen, moves) in puzzles {
+        let board = board_from_fen(fen);
+        let searcher = ProofNumberSearcher::default();
+        let proof = searcher.prove(&board);
+        assert_eq!(proof.result, ProofResult::Win, "{}", fen);
+        assert_eq!(proof.pv.len(), 2 * moves - 1, "{}", fen);
+        assert!(ends_in_mate(&board, &proof.pv));
+        assert_eq!(searcher.nodes(), proof.nodes);
+
+        // alpha-beta finds the same mate once it looks deep enough, too slow for the longer one
+        if moves > 2 {
+            continue;
+        }
+        let alpha_beta = AlphaBetaSearcher::new(2 * moves as u8);
+        let line = &alpha_beta.search_lines(&board, &SimpleEvaluator, 1, &mut |_| {})[0];
+        assert_eq!(line.score, MATE_SCORE);
+        let pn_line = &Searcher::<BitboardBoard>::search_lines(&searcher, &board, &SimpleEvaluator, 1, &mut |_| {})[0];
+        assert_eq!(pn_line.score, line.score);
+        assert_eq!(pn_line.pv.len(), line.pv.len());
+    }
+}
+
+#[test]
+fn proves_losses_and_draws() {
+    // WAC.001 after Qg6, every defence is mated
+    let board = board_from_fen("2rr3k/pp3pp1/1nnqbNQp/3pN3/2pP4/2P5/PPB4P/R4RK1 b - - 1 1");
+    let proof = ProofNumberSearcher::default().prove(&board);
+    assert_eq!(proof.result, ProofResult::Loss);
+    assert_eq!(proof.pv.len(), 2);
+    assert!(ends_in_mate(&board, &proof.pv));
+
+    // stalemated
+    let board = board_from_fen("k7/P7/1K6/8/8/8/8/8 b - - 0 1");
+    let proof = ProofNumberSearcher::default().prove(&board);
+    assert_eq!(proof.result, ProofResult::Draw);
+    assert!(proof.pv.is_empty());
+
+    // taking the rook is the only move
+    let board = board_from_fen("8/8/8/8/8/8/1r6/K2k4 w - - 0 1");
+    let proof = ProofNumberSearcher::default().prove(&board);
+    assert_eq!(proof.result, ProofResult::Draw);
+    assert_eq!(proof.pv.len(), 1);
+}
+
+#[test]
+fn budget_and_ply_limit_leave_it_open() {
+    let board = board_from_fen("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+    let searcher = ProofNumberSearcher::new(50);
+    let proof = searcher.prove(&board);
+    assert_eq!(proof.result, ProofResult::Unknown);
+    assert!(!proof.pv.is_empty());
+
+    // KR vs K cannot be mated within 4 plies from here, and the cut off lines are no draw
+    let board = board_from_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1");
+    let proof = ProofNumberSearcher::default().with_max_plies(4).prove(&board);
+    assert_eq!(proof.result, ProofResult::Unknown);
+}
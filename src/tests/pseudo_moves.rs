@@ -1,12 +1,13 @@
 use crate::board::{BitboardBoard, Board};
 use crate::board::types::{Color, Square};
+use crate::move_generation::MoveList;
 use crate::move_generation::naive_move_generator::NaiveMoveGenerator;
 
 #[test]
 fn white_pawn_double_push() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/8/8/8/8/8/P7/8 w - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let pawn_square = Square::from_coords(0, 1).0;
     NaiveMoveGenerator.generate_pawn_moves(&board, pawn_square, Color::White, &mut moves);
     let mut move_destinations: Vec<u8> = moves.iter().map(|m| m.to.0).collect();
@@ -18,7 +19,7 @@ fn white_pawn_double_push() {
 fn black_pawn_double_push() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/p7/8/8/8/8/8/8 b - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let pawn_square = Square::from_coords(0, 6).0;
     NaiveMoveGenerator.generate_pawn_moves(&board, pawn_square, Color::Black, &mut moves);
     let mut move_destinations: Vec<String> = moves.iter().map(|m| m.to.to_notation()).collect();
@@ -32,7 +33,7 @@ fn black_pawn_double_push() {
 fn white_pawn_capture() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/8/8/p1p5/1P6/8/8/8 w - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let pawn_square = Square::from_coords(1, 3).0;
     NaiveMoveGenerator.generate_pawn_moves(&board, pawn_square, Color::White, &mut moves);
     let mut move_destinations: Vec<String> = moves.iter().map(|m| m.to.to_notation()).collect();
@@ -46,7 +47,7 @@ fn white_pawn_capture() {
 fn black_pawn_capture() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/8/8/1p6/P1P5/8/8/8 b - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let pawn_square = Square::from_coords(1, 4).0;
     NaiveMoveGenerator.generate_pawn_moves(&board, pawn_square, Color::Black, &mut moves);
     let mut move_destinations: Vec<String> = moves.iter().map(|m| m.to.to_notation()).collect();
@@ -60,7 +61,7 @@ fn black_pawn_capture() {
 fn knight_moves_center() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/8/8/3N4/8/8/8/8 w - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let knight_square = Square::from_coords(3, 4).0;
     NaiveMoveGenerator.generate_knight_moves(&board, knight_square, Color::White, &mut moves);
     let mut move_destinations: Vec<String> = moves.iter().map(|m| m.to.to_notation()).collect();
@@ -74,7 +75,7 @@ fn knight_moves_center() {
 fn knight_moves_corner() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("N7/8/8/8/8/8/8/8 w - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let knight_square = Square::from_coords(0, 7).0;
     NaiveMoveGenerator.generate_knight_moves(&board, knight_square, Color::White, &mut moves);
     let mut move_destinations: Vec<String> = moves.iter().map(|m| m.to.to_notation()).collect();
@@ -86,7 +87,7 @@ fn knight_moves_corner() {
 fn bishop_moves() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/8/8/3B4/8/8/8/8 w - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let bishop_square = Square::from_coords(3, 4).0;
     NaiveMoveGenerator.generate_bishop_moves(&board, bishop_square, Color::White, &mut moves);
     let mut move_destinations: Vec<String> = moves.iter().map(|m| m.to.to_notation()).collect();
@@ -100,7 +101,7 @@ fn bishop_moves() {
 fn rook_moves() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/8/8/3R4/8/8/8/8 w - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let rook_square = Square::from_coords(3, 4).0;
     NaiveMoveGenerator.generate_rook_moves(&board, rook_square, Color::White, &mut moves);
     let mut move_destinations: Vec<u8> = moves.iter().map(|m| m.to.0).collect();
@@ -112,7 +113,7 @@ fn rook_moves() {
 fn queen_moves() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/8/8/3Q4/8/8/8/8 w - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let queen_square = Square::from_coords(3, 4).0;
     NaiveMoveGenerator.generate_queen_moves(&board, queen_square, Color::White, &mut moves);
     let mut move_destinations: Vec<String> = moves.iter().map(|m| m.to.to_notation()).collect();
@@ -126,7 +127,7 @@ fn queen_moves() {
 fn king_moves() {
     let mut board = BitboardBoard::new_empty(None);
     board.load_fen("8/8/8/3K4/8/8/8/8 w - - 0 1");
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     let king_square = Square::from_coords(3, 4).0;
     NaiveMoveGenerator.generate_king_moves(&board, king_square, Color::White, &mut moves);
     let mut move_destinations: Vec<u8> = moves.iter().map(|m| m.to.0).collect();
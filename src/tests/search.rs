@@ -0,0 +1,165 @@
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::PieceKind;
+use crate::move_generation::{BitboardMoveGenerator, MoveList};
+use crate::move_generation::naive_move_generator::NaiveMoveGenerator;
+use crate::evaluation::SimpleEvaluator;
+use crate::search::{bench, AlphaBetaSearcher, Searcher, MATE_SCORE, MAX_SEARCH_DEPTH};
+use crate::uci::UciEngine;
+
+#[test]
+fn multipv_returns_distinct_root_moves() {
+    let board = BitboardBoard::new_startpos();
+    let lines = AlphaBetaSearcher::new(2).search_lines(&board, &SimpleEvaluator, 3, &mut |_| {});
+    assert_eq!(lines.len(), 3);
+    let mut best_moves: Vec<String> = lines.iter().map(|l| l.best_move().unwrap().to_uci()).collect();
+    best_moves.sort();
+    best_moves.dedup();
+    assert_eq!(best_moves.len(), 3);
+    assert!(lines.windows(2).all(|w| w[0].score >= w[1].score));
+}
+
+#[test]
+fn multipv_first_line_matches_single_pv() {
+    let mut board = BitboardBoard::new_empty(None);
+    board.load_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let searcher = AlphaBetaSearcher::new(2);
+    let lines = searcher.search_lines(&board, &SimpleEvaluator, 4, &mut |_| {});
+    let single = searcher.search_lines(&board, &SimpleEvaluator, 1, &mut |_| {});
+    assert_eq!(lines[0].score, single[0].score);
+}
+
+#[test]
+fn multipv_is_capped_by_legal_moves() {
+    let mut board = BitboardBoard::new_empty(None);
+    board.load_fen("7k/8/8/8/8/8/8/K7 w - - 0 1");
+    let lines = AlphaBetaSearcher::new(2).search_lines(&board, &SimpleEvaluator, 10, &mut |_| {});
+    assert_eq!(lines.len(), 3);
+}
+
+#[test]
+fn multipv_reports_every_iteration() {
+    let board = BitboardBoard::new_startpos();
+    let mut depths = Vec::new();
+    AlphaBetaSearcher::new(3).search_lines(&board, &SimpleEvaluator, 2, &mut |lines| {
+        assert_eq!(lines.len(), 2);
+        depths.push(lines[0].depth);
+    });
+    assert_eq!(depths, vec![1, 2, 3]);
+}
+
+#[test]
+fn multipv_finds_mate_line() {
+    let mut board = BitboardBoard::new_empty(None);
+    board.load_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let lines = AlphaBetaSearcher::new(2).search_lines(&board, &SimpleEvaluator, 2, &mut |_| {});
+    assert_eq!(lines[0].best_move().unwrap().to_uci(), "a1a8");
+    assert_eq!(lines[0].score, MATE_SCORE);
+    assert!(lines[1].score < MATE_SCORE);
+}
+
+#[test]
+fn uci_go_prints_all_lines() {
+    let mut engine = UciEngine::new();
+    let mut out = Vec::new();
+    engine.handle_command("setoption name MultiPV value 2", &mut out);
+    engine.handle_command("position startpos moves e2e4", &mut out);
+    engine.handle_command("go depth 2", &mut out);
+    let output = String::from_utf8(out).unwrap();
+    assert!(output.contains("info depth 2 multipv 1 "));
+    assert!(output.contains("info depth 2 multipv 2 "));
+    assert!(output.lines().last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+fn node_limit_keeps_last_finished_iteration() {
+    let board = BitboardBoard::new_startpos();
+    let mut depths = Vec::new();
+    let lines = AlphaBetaSearcher::new(MAX_SEARCH_DEPTH)
+        .with_node_limit(2000)
+        .search_lines(&board, &SimpleEvaluator, 1, &mut |lines| depths.push(lines[0].depth));
+    assert!(lines[0].best_move().is_some());
+    assert_eq!(lines[0].depth, *depths.last().unwrap());
+    assert!(lines[0].depth < MAX_SEARCH_DEPTH);
+}
+
+#[test]
+fn uci_go_accepts_node_limit() {
+    let mut engine = UciEngine::new();
+    let mut out = Vec::new();
+    engine.handle_command("position startpos", &mut out);
+    engine.handle_command("go nodes 500", &mut out);
+    let output = String::from_utf8(out).unwrap();
+    assert!(output.lines().last().unwrap().starts_with("bestmove "), "{}", output);
+}
+
+#[test]
+fn uci_position_ignores_malformed_fen() {
+    let mut engine = UciEngine::new();
+    let mut out = Vec::new();
+    // the only legal move is Kxb2
+    engine.handle_command("position fen k7/8/8/8/8/8/1q6/K7 w - - 0 1", &mut out);
+    for fen in [
+        "4k3/8/8/8/8/8/8/4K3 w - -",
+        "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+        "4k3/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K4 w - - 0 1",
+        "8/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K3 w - 1a 0 1",
+    ] {
+        out.clear();
+        engine.handle_command(&format!("position fen {} moves e1e2", fen), &mut out);
+        assert!(String::from_utf8_lossy(&out).starts_with("info string invalid fen"), "{}", fen);
+    }
+    out.clear();
+    engine.handle_command("go depth 1", &mut out);
+    let output = String::from_utf8(out).unwrap();
+    assert_eq!(output.lines().last(), Some("bestmove a1b2"), "{}", output);
+}
+
+#[test]
+fn legal_move_filter_restores_board() {
+    let mut board = BitboardBoard::new_empty(None);
+    board.load_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let (fen, hash) = (board.to_fen(), board.hash());
+    let expected = board.generate_moves();
+    assert_eq!(board.generate_legal_moves(), expected);
+    assert_eq!((board.to_fen(), board.hash()), (fen, hash));
+}
+
+#[test]
+fn bench_counts_nodes() {
+    let mut positions = 0;
+    let total = bench::run_bench(2, &mut |_, result| {
+        assert!(result.nodes > 0);
+        positions += 1;
+    });
+    assert_eq!(positions, bench::BENCH_POSITIONS.len());
+    assert_eq!(bench::run_bench(2, &mut |_, _| {}).nodes, total.nodes);
+}
+
+/// Naive generation without pawn moves, to tell it apart from the default generator.
+struct NoPawnGenerator;
+
+impl BitboardMoveGenerator for NoPawnGenerator {
+    fn generate_moves(&self, board: &BitboardBoard) -> MoveList {
+        let mut moves = NaiveMoveGenerator.generate_moves(board);
+        moves.retain(|m| m.piece.kind != PieceKind::Pawn);
+        moves
+    }
+
+    fn box_clone(&self) -> Box<dyn BitboardMoveGenerator> {
+        Box::new(NoPawnGenerator)
+    }
+}
+
+#[test]
+fn custom_generator_survives_clone() {
+    let mut board = BitboardBoard::new_empty(Some(Box::new(NoPawnGenerator)));
+    board.load_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert_eq!(board.generate_moves().len(), 4);
+    assert_eq!(board.clone().generate_moves().len(), 4);
+
+    // every line the search walks comes from the custom generator
+    let line = AlphaBetaSearcher::new(3).search_lines(&board, &SimpleEvaluator, 1, &mut |_| {}).remove(0);
+    assert!(line.pv.iter().all(|m| m.piece.kind != PieceKind::Pawn), "{:?}", line.pv);
+}
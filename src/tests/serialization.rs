@@ -0,0 +1,126 @@
+use std::io::ErrorKind;
+use crate::board::{BitboardBoard, Board};
+use crate::board::packed::{PackedBoard, PACKED_BOARD_SIZE};
+use crate::game::stream::{GameReader, GameWriter, PackedGame};
+use crate::utils::random::Random;
+use super::perft::{board_from_fen, POSITIONS};
+
+fn assert_round_trip(board: &BitboardBoard) {
+    let unpacked = PackedBoard::pack(board).unpack().unwrap();
+    assert_eq!(unpacked.to_shredder_fen(), board.to_shredder_fen());
+    assert_eq!(unpacked.hash(), board.hash());
+    assert_eq!(unpacked.chess960, board.chess960);
+}
+
+#[test]
+fn packed_board_round_trip() {
+    assert_eq!(size_of::<PackedBoard>(), PACKED_BOARD_SIZE);
+    for epd in POSITIONS.lines() {
+        let board = board_from_fen(&format!("{} 7 42", epd));
+        assert_round_trip(&board);
+        assert_round_trip(&board.mirror());
+    }
+    for index in [0, 518, 959] {
+        assert_round_trip(&BitboardBoard::new_chess960(index).unwrap());
+    }
+    // castling rooks that are not the outermost ones, and an en passant square
+    assert_round_trip(&board_from_fen("1r2k1r1/8/8/8/8/8/8/RR2K2R w BHg - 0 1"));
+    assert_round_trip(&board_from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3"));
+}
+
+#[test]
+fn packed_board_rejects_bad_bytes() {
+    let mut packed = PackedBoard::pack(&BitboardBoard::new_startpos());
+    packed.0[8] |= 0xF;
+    assert!(packed.unpack().is_none());
+
+    let mut packed = PackedBoard::pack(&BitboardBoard::new_startpos());
+    packed.0[0..8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(packed.unpack().is_none());
+
+    // the en passant pawn has to stand where a double push from the other side lands
+    let mut packed = PackedBoard::pack(&board_from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3"));
+    assert!(packed.unpack().is_some());
+    packed.0[24] ^= 1;
+    assert!(packed.unpack().is_none());
+
+    for fen in [
+        "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/p3K3 w - - 0 1",
+        "8/8/8/8/8/8/8/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/K3K3 w - - 0 1",
+    ] {
+        assert!(PackedBoard::pack(&board_from_fen(fen)).unpack().is_none(), "{}", fen);
+    }
+}
+
+fn random_games(rng: &mut Random) -> Vec<(BitboardBoard, PackedGame)> {
+    let mut games = Vec::new();
+    for (i, epd) in POSITIONS.lines().take(12).enumerate() {
+        let start = board_from_fen(&format!("{} 0 1", epd));
+        let mut board = start.clone();
+        let mut game = PackedGame::new(&start);
+        for ply in 0..60 {
+            let moves = board.generate_moves();
+            if moves.is_empty() {
+                break;
+            }
+            let mv = moves.get(rng.next_below(moves.len() as u64) as usize).unwrap();
+            // every other game carries scores, with some gaps
+            let score = (i % 2 == 0 && ply % 7 != 3).then(|| rng.next_below(2000) as i16 - 1000);
+            game.push(board.compact_move(&mv), score);
+            board.make_move(&mv);
+        }
+        games.push((board, game));
+    }
+    games
+}
+
+#[test]
+fn game_stream_round_trip() {
+    let mut rng = Random::new(45);
+    let games = random_games(&mut rng);
+
+    let mut writer = GameWriter::new(Vec::new());
+    for (_, game) in &games {
+        writer.write_game(game).unwrap();
+    }
+    let bytes = writer.into_inner();
+
+    let read: Vec<PackedGame> = GameReader::new(bytes.as_slice()).collect::<Result<_, _>>().unwrap();
+    assert_eq!(read.len(), games.len());
+    for ((end, game), read) in games.iter().zip(&read) {
+        assert_eq!(read, game);
+        let (start, moves) = read.replay().unwrap();
+        let mut board = start;
+        for mv in &moves {
+            board.make_move(mv);
+        }
+        assert_eq!(board.to_fen(), end.to_fen());
+    }
+
+    // a cut off game is an error, not the end of the stream
+    let mut reader = GameReader::new(&bytes[..bytes.len() - 1]);
+    let results: Vec<_> = reader.by_ref().collect();
+    assert_eq!(results.last().unwrap().as_ref().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn packed_game_from_moves() {
+    let start = BitboardBoard::new_startpos();
+    let mut board = start.clone();
+    let mut moves = Vec::new();
+    for uci in ["e2e4", "a7a6", "e4e5", "d7d5", "e5d6", "e8d7", "d6c7"] {
+        let mv = board.generate_moves().into_iter().find(|m| board.move_to_uci(m) == uci).unwrap();
+        board.make_move(&mv);
+        moves.push(mv);
+    }
+    let game = PackedGame::from_moves(&start, &moves);
+    assert!(game.moves[4].mv.is_en_passant());
+    assert_eq!(game.replay().unwrap().1, moves);
+
+    // an illegal move is caught on replay
+    let mut broken = game.clone();
+    broken.moves.swap(0, 1);
+    assert!(broken.replay().is_none());
+}
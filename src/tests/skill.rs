@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+use crate::board::{BitboardBoard, Board};
+use crate::evaluation::SimpleEvaluator;
+use crate::search::{AlphaBetaSearcher, Searcher};
+use crate::search::skill::{Skill, SkillLimitedSearcher, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+use crate::uci::UciEngine;
+use super::perft::board_from_fen;
+
+const MIDDLEGAME: &str = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+
+fn pick(level: u8, seed: u64, board: &BitboardBoard) -> String {
+    let searcher = SkillLimitedSearcher::new(AlphaBetaSearcher::new(3), Skill::new(level)).with_seed(seed);
+    searcher.search(board, &SimpleEvaluator).unwrap().to_uci()
+}
+
+#[test]
+fn levels_and_elo() {
+    assert_eq!(Skill::new(99).level(), MAX_SKILL_LEVEL);
+    assert!(Skill::default().is_full_strength());
+    assert_eq!(Skill::from_elo(0).level(), 0);
+    assert_eq!(Skill::from_elo(MIN_ELO).level(), 0);
+    // limiting strength never means full strength
+    assert_eq!(Skill::from_elo(MAX_ELO + 500).level(), MAX_SKILL_LEVEL - 1);
+    assert!(Skill::from_elo(1200).level() < Skill::from_elo(2000).level());
+    for level in 1..=MAX_SKILL_LEVEL {
+        assert!(Skill::new(level).depth() >= Skill::new(level - 1).depth());
+        assert!(Skill::new(level).nodes() > Skill::new(level - 1).nodes());
+    }
+}
+
+#[test]
+fn full_strength_is_plain_alpha_beta() {
+    let board = board_from_fen(MIDDLEGAME);
+    let plain = AlphaBetaSearcher::new(3).search_lines(&board, &SimpleEvaluator, 2, &mut |_| {});
+    let limited = SkillLimitedSearcher::new(AlphaBetaSearcher::new(3), Skill::default())
+        .search_lines(&board, &SimpleEvaluator, 2, &mut |_| {});
+    assert_eq!(plain, limited);
+}
+
+#[test]
+fn seeded_choices_repeat() {
+    let board = board_from_fen(MIDDLEGAME);
+    for level in [0, 5, 12] {
+        for seed in 0..5 {
+            assert_eq!(pick(level, seed, &board), pick(level, seed, &board));
+        }
+    }
+}
+
+#[test]
+fn weak_levels_vary_among_searched_moves() {
+    let board = board_from_fen(MIDDLEGAME);
+    let legal: Vec<String> = board.generate_moves().iter().map(|m| m.to_uci()).collect();
+    let weak: HashSet<String> = (0..20).map(|seed| pick(0, seed, &board)).collect();
+    assert!(weak.len() > 1);
+    assert!(weak.iter().all(|mv| legal.contains(mv)));
+
+    // stronger levels stray less often from their own best move
+    let strays = |level: u8| {
+        let skill = Skill::new(level);
+        let best = AlphaBetaSearcher::new(skill.depth().min(3)).with_node_limit(skill.nodes());
+        let best = best.search(&board, &SimpleEvaluator).unwrap().to_uci();
+        (0..20).filter(|&seed| pick(level, seed, &board) != best).count()
+    };
+    assert!(strays(16) < strays(0));
+}
+
+#[test]
+fn mates_are_not_given_away() {
+    let board = board_from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1");
+    for seed in 0..20 {
+        assert_eq!(pick(4, seed, &board), "d1d8");
+    }
+}
+
+#[test]
+fn reports_the_asked_lines_only() {
+    let board = board_from_fen(MIDDLEGAME);
+    let searcher = SkillLimitedSearcher::new(AlphaBetaSearcher::new(3), Skill::new(3)).with_seed(1);
+    let lines = searcher.search_lines(&board, &SimpleEvaluator, 1, &mut |lines| assert_eq!(lines.len(), 1));
+    assert_eq!(lines.len(), 1);
+    let lines = searcher.search_lines(&board, &SimpleEvaluator, 2, &mut |lines| assert_eq!(lines.len(), 2));
+    assert_eq!(lines.len(), 2);
+    assert_ne!(lines[0].best_move(), lines[1].best_move());
+}
+
+#[test]
+fn uci_strength_options() {
+    let play = |options: &[&str], seed: u64| {
+        let mut engine = UciEngine::new();
+        engine.set_random_seed(seed);
+        let mut out = Vec::new();
+        for option in options {
+            engine.handle_command(option, &mut out);
+        }
+        engine.handle_command(&format!("position fen {}", MIDDLEGAME), &mut out);
+        engine.handle_command("go depth 3", &mut out);
+        String::from_utf8(out).unwrap().lines().last().unwrap().to_string()
+    };
+
+    let mut out = Vec::new();
+    UciEngine::new().handle_command("uci", &mut out);
+    let output = String::from_utf8(out).unwrap();
+    assert!(output.contains("option name Skill Level type spin default 20 min 0 max 20"));
+    assert!(output.contains("option name UCI_LimitStrength type check default false"));
+    assert!(output.contains("option name UCI_Elo type spin"));
+
+    let skill = ["setoption name Skill Level value 2"];
+    let elo = ["setoption name UCI_LimitStrength value true", "setoption name UCI_Elo value 900"];
+    for options in [&skill[..], &elo[..]] {
+        let moves: HashSet<String> = (0..20).map(|seed| play(options, seed)).collect();
+        assert!(moves.iter().all(|mv| mv.starts_with("bestmove ")));
+        assert!(moves.len() > 1, "{:?}", moves);
+        assert_eq!(play(options, 3), play(options, 3));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::board::Board;
+use crate::evaluation::{Evaluator, SimpleEvaluator};
+use super::perft::{board_from_fen, perft, positions};
+
+#[test]
+fn mirror_round_trip() {
+    let board = board_from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 1");
+    let mirrored = board.mirror();
+    assert_eq!(mirrored.to_fen(), "r3k2r/8/8/8/3Pp3/8/8/R3K2R b Qk d3 0 1");
+    assert_eq!(mirrored.mirror().to_fen(), board.to_fen());
+    assert_eq!(board.flip_horizontal().to_fen(), "r2k3r/8/8/3Pp3/8/8/8/R2K3R w - e6 0 1");
+
+    for board in positions() {
+        assert_eq!(board.mirror().mirror().to_shredder_fen(), board.to_shredder_fen());
+        assert_eq!(board.mirror().mirror().hash(), board.hash());
+    }
+}
+
+#[test]
+fn evaluation_is_colour_symmetric() {
+    for board in positions() {
+        let mirrored = board.mirror();
+        assert_eq!(SimpleEvaluator.evaluate(&board), SimpleEvaluator.evaluate(&mirrored), "{}", board.to_fen());
+        assert_eq!(board.scores().material, -mirrored.scores().material);
+    }
+}
+
+#[test]
+fn piece_square_tables_face_the_opponent() {
+    // a pawn about to promote is worth more than one on its starting square, for either colour
+    let advanced = board_from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+    let home = board_from_fen("4k3/8/8/8/8/8/P7/4K3 w - - 0 1");
+    assert!(SimpleEvaluator.evaluate(&advanced) > SimpleEvaluator.evaluate(&home));
+    assert!(SimpleEvaluator.evaluate(&advanced.mirror()) > SimpleEvaluator.evaluate(&home.mirror()));
+}
+
+#[test]
+fn perft_is_symmetric() {
+    for (i, mut board) in positions().into_iter().enumerate() {
+        let depth = if i % 10 == 0 { 3 } else { 2 };
+        let nodes = perft(&mut board, depth);
+        assert_eq!(perft(&mut board.mirror(), depth), nodes, "{}", board.to_fen());
+        // without castling the position is also symmetric between the wings
+        let mut no_castling = board.flip_horizontal().flip_horizontal();
+        assert_eq!(perft(&mut board.flip_horizontal(), depth), perft(&mut no_castling, depth), "{}", board.to_fen());
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::{Color, PieceKind};
+use crate::evaluation::SimpleEvaluator;
+use crate::game::GameManager;
+use crate::search::{AlphaBetaSearcher, Searcher, TB_WIN_SCORE};
+use crate::tablebase::syzygy::{material_key, TableEntry, TableKind};
+use crate::tablebase::{SyzygyTablebase, Wdl};
+use crate::uci::UciEngine;
+use crate::utils::random::Random;
+use super::perft::board_from_fen;
+use super::syzygy_generator::{solve, write_tables, TABLES};
+
+/// A random legal position with the material of a table, `None` when the placement is
+/// illegal.
+fn random_position(name: &str, stm: Color, random: &mut Random) -> Option<BitboardBoard> {
+    let (white, black) = name.split_once('v').unwrap();
+    let mut squares = ['1'; 64];
+    for piece in white.chars().chain(black.chars().map(|c| c.to_ascii_lowercase())) {
+        let square = random.next_below(64) as usize;
+        if squares[square] != '1' || (piece.eq_ignore_ascii_case(&'p') && !(8..56).contains(&square)) {
+            return None;
+        }
+        squares[square] = piece;
+    }
+    let ranks: Vec<String> = squares.chunks(8).rev().map(|rank| rank.iter().collect()).collect();
+    let side = if stm == Color::White { "w" } else { "b" };
+    let board = board_from_fen(&format!("{} {} - - 0 1", ranks.join("/"), side));
+    (!board.is_in_check(stm.opposite())).then_some(board)
+}
+
+/// Checks a probe against the probes of every reply: the result is the best one over the
+/// moves, and the DTZ is one ply more than the reply it waits for, or one for a zeroing
+/// move or mate.
+fn assert_follows_from_replies(tablebase: &SyzygyTablebase, board: &BitboardBoard) {
+    let fen = board.to_fen();
+    let moves = board.generate_moves();
+    let mut best = None;
+    let mut fastest_win = i32::MAX;
+    let mut slowest_loss = 1;
+    for mv in moves.iter() {
+        let mut next = board.clone();
+        next.make_move(&mv);
+        let value = -(tablebase.probe_wdl(&next).unwrap() as i32);
+        let zeroing = mv.captures.is_some() || mv.piece.kind == PieceKind::Pawn;
+        let mated = next.is_in_check(next.side_to_move()) && next.generate_moves().is_empty();
+        let plies = if zeroing || mated { 1 } else { tablebase.probe_dtz(&next).unwrap().abs() + 1 };
+        if value == Wdl::Win as i32 {
+            fastest_win = fastest_win.min(plies);
+        }
+        slowest_loss = slowest_loss.max(plies);
+        best = best.max(Some(value));
+    }
+
+    let in_check = board.is_in_check(board.side_to_move());
+    let best = best.unwrap_or(if in_check { Wdl::Loss as i32 } else { Wdl::Draw as i32 });
+    assert_eq!(tablebase.probe_wdl(board).map(|wdl| wdl as i32), Some(best), "{}", fen);
+    let dtz = match best {
+        2 => fastest_win,
+        -2 => -slowest_loss,
+        _ => 0,
+    };
+    assert_eq!(tablebase.probe_dtz(board), Some(dtz), "{}", fen);
+}
+
+fn syzygy_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/syzygy")
+}
+
+fn bundled_tablebase() -> SyzygyTablebase {
+    SyzygyTablebase::open(syzygy_dir()).unwrap()
+}
+
+fn root_moves(tablebase: &SyzygyTablebase, fen: &str) -> Vec<String> {
+    let board = board_from_fen(fen);
+    let moves = tablebase.filter_root_moves(&board, &board.generate_moves()).unwrap();
+    let mut moves: Vec<String> = moves.iter().map(|m| m.to_uci()).collect();
+    moves.sort();
+    moves
+}
+
+#[test]
+fn syzygy_material_key_lists_white_first() {
+    assert_eq!(material_key(&board_from_fen("8/8/8/4k3/8/8/3R4/4K3 w - - 0 1")), "KRvK");
+    assert_eq!(material_key(&board_from_fen("8/8/8/3nk3/8/8/8/4K1Q1 b - - 0 1")), "KQvKN");
+    assert!(TableEntry::new(TableKind::Wdl, "KXvK", PathBuf::new()).is_none());
+}
+
+// Solving KQvKR takes about a minute in release mode, run with
+// `cargo test --release -- --ignored`.
+#[test]
+#[ignore]
+fn syzygy_bundled_tables_match_the_generator() {
+    let mut solved = HashMap::new();
+    for name in TABLES {
+        let solution = solve(name, &solved);
+        let (wdl, dtz) = write_tables(name, &solution);
+        assert!(fs::read(syzygy_dir().join(format!("{}.rtbw", name))).unwrap() == wdl, "{}.rtbw", name);
+        assert!(fs::read(syzygy_dir().join(format!("{}.rtbz", name))).unwrap() == dtz, "{}.rtbz", name);
+        solved.insert(name.to_string(), solution);
+    }
+
+    // the longest wins known for these endings: mate in 10 and 16 moves, and winning the
+    // rook in 31 moves
+    assert_eq!(solved["KQvK"].longest_win(Color::White), 19);
+    assert_eq!(solved["KRvK"].longest_win(Color::White), 31);
+    assert_eq!(solved["KQvKR"].longest_win(Color::White), 61);
+}
+
+#[test]
+fn syzygy_open_registers_tables() {
+    let tablebase = bundled_tablebase();
+    assert_eq!(tablebase.cardinality(), 4);
+    assert!(tablebase.covers(&board_from_fen("8/8/8/4k3/8/8/3Q4/4K3 w - - 0 1")));
+    assert!(tablebase.covers(&board_from_fen("8/8/8/4k3/3r4/8/3Q4/4K3 w - - 0 1")));
+    assert!(!tablebase.covers(&BitboardBoard::new_startpos()));
+}
+
+#[test]
+fn syzygy_probe_wdl_reads_reference_positions() {
+    let tablebase = bundled_tablebase();
+    let cases = [
+        ("8/8/8/4k3/8/8/3Q4/4K3 w - - 0 1", Wdl::Win),
+        ("8/8/8/4k3/8/8/3Q4/4K3 b - - 0 1", Wdl::Loss),
+        // the queen hangs
+        ("8/8/8/4k3/4Q3/8/8/K7 b - - 0 1", Wdl::Draw),
+        // stalemate
+        ("k7/2Q5/2K5/8/8/8/8/8 b - - 0 1", Wdl::Draw),
+        // colours swapped, the table is looked up from black's side
+        ("4k3/3q4/8/8/4K3/8/8/8 b - - 0 1", Wdl::Win),
+        ("4k3/3q4/8/8/4K3/8/8/8 w - - 0 1", Wdl::Loss),
+        ("8/8/8/4k3/8/8/8/4K3 w - - 0 1", Wdl::Draw),
+        ("8/8/8/4k3/8/8/8/R3K3 b - - 0 1", Wdl::Loss),
+        ("8/8/8/4k3/8/8/8/4KB2 w - - 0 1", Wdl::Draw),
+        ("8/8/8/4k3/8/8/8/4KN2 w - - 0 1", Wdl::Draw),
+        // the pawn queens, or is stalemated in front of its king
+        ("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1", Wdl::Win),
+        ("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1", Wdl::Draw),
+        // a rook pawn can't drive the king out of the corner
+        ("k7/8/8/8/8/8/P7/K7 w - - 0 1", Wdl::Draw),
+        // the white king holds a key square of the pawn
+        ("4k3/8/4K3/8/4P3/8/8/8 b - - 0 1", Wdl::Loss),
+        ("8/4p3/8/4k3/8/4K3/8/8 w - - 0 1", Wdl::Loss),
+        // the rook takes the queen
+        ("4k3/8/8/8/4Q3/8/K7/4r3 b - - 0 1", Wdl::Win),
+        ("4k3/8/8/8/4r3/8/4Q3/4K3 w - - 0 1", Wdl::Win),
+        ("4k3/4q3/8/4R3/8/8/8/4K3 b - - 0 1", Wdl::Win),
+    ];
+    for (fen, wdl) in cases {
+        assert_eq!(tablebase.probe_wdl(&board_from_fen(fen)), Some(wdl), "{}", fen);
+    }
+}
+
+#[test]
+fn syzygy_probe_dtz_reads_reference_positions() {
+    let tablebase = bundled_tablebase();
+    let cases = [
+        ("k7/7Q/2K5/8/8/8/8/8 w - - 0 1", 1),
+        ("k7/1Q6/2K5/8/8/8/8/8 b - - 0 1", -1),
+        // the longest wins of KQvK, KRvK and KQvKR
+        ("7K/6Q1/8/8/8/3k4/8/8 w - - 0 1", 19),
+        ("7K/8/8/8/8/8/2k5/1R6 w - - 0 1", 31),
+        ("7K/8/8/8/8/8/8/1R1k4 b - - 0 1", -32),
+        ("8/8/8/8/7Q/2k5/8/3r3K w - - 0 1", 61),
+        ("8/8/2k4K/7Q/8/8/8/3r4 b - - 0 1", -62),
+        ("8/8/8/k7/8/8/K4P2/8 w - - 0 1", 19),
+        ("4k3/8/4K3/8/4P3/8/8/8 w - - 0 1", 1),
+        ("4k3/8/8/8/4r3/8/4Q3/4K3 w - - 0 1", 1),
+    ];
+    for (fen, dtz) in cases {
+        assert_eq!(tablebase.probe_dtz(&board_from_fen(fen)), Some(dtz), "{}", fen);
+    }
+}
+
+#[test]
+fn syzygy_probes_follow_from_the_replies() {
+    let tablebase = bundled_tablebase();
+    let mut random = Random::new(7);
+    for name in TABLES {
+        for stm in [Color::White, Color::Black] {
+            let mut checked = 0;
+            while checked < 12 {
+                if let Some(board) = random_position(name, stm, &mut random) {
+                    assert_follows_from_replies(&tablebase, &board);
+                    checked += 1;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn syzygy_filter_root_moves_keeps_fastest_win() {
+    let tablebase = bundled_tablebase();
+    assert_eq!(root_moves(&tablebase, "k7/7Q/2K5/8/8/8/8/8 w - - 0 1"), ["h7b7"]);
+    assert_eq!(root_moves(&tablebase, "k7/8/1K6/8/8/8/8/7R w - - 0 1"), ["h1h8"]);
+    // both king moves keep the pawn's path and win equally fast
+    assert_eq!(root_moves(&tablebase, "4k3/4P3/4K3/8/8/8/8/8 w - - 0 1"), ["e6d6", "e6f6"]);
+    assert_eq!(root_moves(&tablebase, "4k3/8/8/8/4r3/8/4Q3/4K3 w - - 0 1"), ["e2e4"]);
+}
+
+#[test]
+fn syzygy_search_uses_tablebase() {
+    let tablebase = Arc::new(bundled_tablebase());
+    let board = board_from_fen("k7/7Q/2K5/8/8/8/8/8 w - - 0 1");
+    let searcher = AlphaBetaSearcher::new(2).with_tablebase(Some(tablebase));
+    let lines = searcher.search_lines(&board, &SimpleEvaluator, 1, &mut |_| {});
+    assert_eq!(lines[0].best_move().unwrap().to_uci(), "h7b7");
+    assert!(lines[0].score >= TB_WIN_SCORE);
+
+    // a drawn position scores zero instead of the material balance
+    let board = board_from_fen("8/8/8/4k3/4Q3/8/8/K7 b - - 0 1");
+    let lines = searcher.search_lines(&board, &SimpleEvaluator, 1, &mut |_| {});
+    assert_eq!(lines[0].score, 0);
+}
+
+#[test]
+fn syzygy_uci_option_loads_tables() {
+    let mut engine = UciEngine::new();
+    let mut out = Vec::new();
+    engine.handle_command(&format!("setoption name SyzygyPath value {}", syzygy_dir().display()), &mut out);
+    engine.handle_command("position fen k7/7Q/2K5/8/8/8/8/8 w - - 0 1", &mut out);
+    engine.handle_command("go depth 1", &mut out);
+    let output = String::from_utf8(out).unwrap();
+    assert!(output.contains("bestmove h7b7"), "{}", output);
+}
+
+#[test]
+fn syzygy_uci_option_reports_errors_on_the_output() {
+    let mut engine = UciEngine::new();
+    let mut out = Vec::new();
+    engine.handle_command("setoption name SyzygyPath value no/such/dir", &mut out);
+    let output = String::from_utf8(out).unwrap();
+    assert!(output.starts_with("info string could not open tablebases no/such/dir"), "{}", output);
+}
+
+#[test]
+fn syzygy_loading_keeps_the_searcher() {
+    let mut game_manager = GameManager::new();
+    game_manager.set_searcher(Box::new(AlphaBetaSearcher::new(1)));
+    game_manager.load_tablebase(syzygy_dir()).unwrap();
+    assert!(game_manager.get_tablebase().is_some_and(|tablebase| tablebase.cardinality() > 0));
+    assert_eq!(game_manager.find_best_lines()[0].depth, 1);
+}
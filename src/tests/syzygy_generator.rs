@@ -0,0 +1,858 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use crate::board::{BitboardBoard, Board};
+use crate::board::types::{Color, Piece, PieceKind};
+use crate::tablebase::encoding::encoding_tables;
+use crate::tablebase::syzygy::{piece_code, TableEntry, TableKind, DTZ_MAGIC, FLAG_LOSS_PLIES, FLAG_MAPPED, FLAG_SINGLE_VALUE, FLAG_STM, FLAG_WIN_PLIES, WDL_MAGIC};
+
+/// The tables bundled under `data/syzygy`, each one after the tables its captures and
+/// promotions lead to.
+pub(super) const TABLES: [&str; 6] = ["KBvK", "KNvK", "KQvK", "KRvK", "KPvK", "KQvKR"];
+
+// results from the side to move's point of view while solving
+const BROKEN: i8 = i8::MIN;
+const UNKNOWN: i8 = 1;
+const WIN: i8 = 2;
+const LOSS: i8 = -2;
+
+const DIAGONALS: [(i8, i8); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+const LINES: [(i8, i8); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const KING_STEPS: [(i8, i8); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+const KNIGHT_STEPS: [(i8, i8); 8] = [(-1, -2), (1, -2), (-2, -1), (2, -1), (-2, 1), (2, 1), (-1, 2), (1, 2)];
+const PROMOTIONS: [PieceKind; 4] = [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight];
+const NAME_ORDER: [PieceKind; 6] = [PieceKind::King, PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight, PieceKind::Pawn];
+
+const BLOCK_SIZE_LOG: u8 = 6;
+const SPAN_LOG: u8 = 10;
+// the prober keeps a symbol's length in a byte and block lengths in 16 bits
+const MAX_SYMBOL_VALUES: usize = 256;
+const MAX_BLOCK_VALUES: usize = 1 << 15;
+const MAX_PAIRS: usize = 200;
+const MIN_PAIR_COUNT: u32 = 8;
+const MAX_CODE_LENGTH: u8 = 32;
+
+fn step(square: u8, (file_step, rank_step): (i8, i8)) -> Option<u8> {
+    let file = (square % 8) as i8 + file_step;
+    let rank = (square / 8) as i8 + rank_step;
+    ((0..8).contains(&file) && (0..8).contains(&rank)).then(|| (rank * 8 + file) as u8)
+}
+
+fn forward(color: Color) -> i8 {
+    if color == Color::White { 1 } else { -1 }
+}
+
+/// Calls `visit` with every square `piece` on `from` attacks, sliders stop at the first
+/// occupied square.
+fn attacks(piece: Piece, from: u8, occupied: u64, visit: &mut impl FnMut(u8)) {
+    let pawn = [(-1, forward(piece.color)), (1, forward(piece.color))];
+    let (directions, slides): (&[(i8, i8)], bool) = match piece.kind {
+        PieceKind::Pawn => (&pawn, false),
+        PieceKind::Knight => (&KNIGHT_STEPS, false),
+        PieceKind::Bishop => (&DIAGONALS, true),
+        PieceKind::Rook => (&LINES, true),
+        PieceKind::Queen => (&KING_STEPS, true),
+        PieceKind::King => (&KING_STEPS, false),
+    };
+    for &direction in directions {
+        let mut square = from;
+        while let Some(next) = step(square, direction) {
+            visit(next);
+            if !slides || occupied & (1 << next) != 0 {
+                break;
+            }
+            square = next;
+        }
+    }
+}
+
+/// Pieces of a table name in naming order, white first: "KQvKR" is K, Q, k, r.
+fn table_pieces(name: &str) -> Vec<Piece> {
+    let (white, black) = name.split_once('v').unwrap();
+    white.chars().map(|c| Piece::from_fen_char(c).unwrap())
+        .chain(black.chars().map(|c| Piece::from_fen_char(c.to_ascii_lowercase()).unwrap()))
+        .collect()
+}
+
+fn table_name(pieces: &[Piece]) -> String {
+    let side = |color: Color| -> String {
+        NAME_ORDER.iter()
+            .flat_map(|&kind| pieces.iter().filter(move |p| p.color == color && p.kind == kind))
+            .map(|p| p.to_fen_char().to_ascii_uppercase())
+            .collect()
+    };
+    format!("{}v{}", side(Color::White), side(Color::Black))
+}
+
+/// A move as seen from the position it is played in.
+#[derive(Clone, Copy)]
+enum Reply {
+    /// A quiet move to another position of the same table, pawn pushes reset the counter.
+    Inside { zeroing: bool },
+    /// A capture or promotion, with the result for the side to move after it.
+    Outside(i8),
+}
+
+/// Where a capture or promotion leads: the solved table (none for two bare kings), which
+/// piece here each of its pieces is, and whether it stores the colours swapped.
+struct Exit<'a> {
+    captured: Option<usize>,
+    promoted: Option<(usize, PieceKind)>,
+    solution: Option<&'a Solution>,
+    sources: Vec<usize>,
+    mirrored: bool,
+}
+
+impl<'a> Exit<'a> {
+    fn new(pieces: &[Piece], solved: &'a HashMap<String, Solution>, captured: Option<usize>, promoted: Option<(usize, PieceKind)>) -> Self {
+        let mut remaining: Vec<(usize, Piece)> = pieces.iter().copied().enumerate().filter(|&(i, _)| Some(i) != captured).collect();
+        for (i, piece) in remaining.iter_mut() {
+            if let Some((_, kind)) = promoted.filter(|&(at, _)| at == *i) {
+                piece.kind = kind;
+            }
+        }
+        let mut exit = Self { captured, promoted, solution: None, sources: Vec::new(), mirrored: false };
+        if remaining.len() == 2 {
+            return exit;
+        }
+
+        let material: Vec<Piece> = remaining.iter().map(|&(_, p)| p).collect();
+        let swapped: Vec<Piece> = material.iter().map(|p| Piece { color: p.color.opposite(), kind: p.kind }).collect();
+        let solution = match solved.get(&table_name(&material)) {
+            Some(solution) => solution,
+            None => {
+                exit.mirrored = true;
+                let name = table_name(&swapped);
+                solved.get(&name).unwrap_or_else(|| panic!("{} must be solved first", name))
+            }
+        };
+        let material = if exit.mirrored { swapped } else { material };
+        let mut used = vec![false; material.len()];
+        for piece in &solution.pieces {
+            let at = (0..material.len()).find(|&j| !used[j] && material[j] == *piece).unwrap();
+            used[at] = true;
+            exit.sources.push(remaining[at].0);
+        }
+        exit.solution = Some(solution);
+        exit
+    }
+}
+
+/// Every placement of a table's pieces with either side to move, numbered as
+/// `stm * 64^n + sum(square_i * 64^i)`.
+struct Solver<'a> {
+    pieces: Vec<Piece>,
+    exits: Vec<Exit<'a>>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(name: &str, solved: &'a HashMap<String, Solution>) -> Self {
+        let pieces = table_pieces(name);
+        let captures = (0..pieces.len()).filter(|&i| pieces[i].kind != PieceKind::King).map(Some);
+        let promotions = (0..pieces.len())
+            .filter(|&i| pieces[i].kind == PieceKind::Pawn)
+            .flat_map(|i| PROMOTIONS.map(|kind| Some((i, kind))));
+        let mut exits = Vec::new();
+        for captured in std::iter::once(None).chain(captures) {
+            for promoted in std::iter::once(None).chain(promotions.clone()) {
+                let same_piece = captured.is_some_and(|c| promoted.is_some_and(|(p, _)| p == c));
+                if (captured.is_some() || promoted.is_some()) && !same_piece {
+                    exits.push(Exit::new(&pieces, solved, captured, promoted));
+                }
+            }
+        }
+        Self { pieces, exits }
+    }
+
+    fn states(&self) -> usize {
+        2 << (6 * self.pieces.len())
+    }
+
+    fn decode(&self, state: usize) -> ([u8; 4], Color) {
+        let mut squares = [0u8; 4];
+        for (i, square) in squares.iter_mut().enumerate().take(self.pieces.len()) {
+            *square = (state >> (6 * i) & 63) as u8;
+        }
+        let stm = if state >> (6 * self.pieces.len()) == 0 { Color::White } else { Color::Black };
+        (squares, stm)
+    }
+
+    fn encode(&self, squares: &[u8; 4], stm: Color) -> usize {
+        let n = self.pieces.len();
+        let placement = squares[..n].iter().enumerate().map(|(i, &sq)| (sq as usize) << (6 * i)).sum::<usize>();
+        ((stm == Color::Black) as usize) << (6 * n) | placement
+    }
+
+    fn occupied(&self, squares: &[u8; 4], skip: Option<usize>) -> u64 {
+        (0..self.pieces.len()).filter(|&i| Some(i) != skip).fold(0, |bb, i| bb | 1 << squares[i])
+    }
+
+    fn king(&self, squares: &[u8; 4], color: Color) -> u8 {
+        let i = self.pieces.iter().position(|p| p.color == color && p.kind == PieceKind::King).unwrap();
+        squares[i]
+    }
+
+    /// True when a piece of `by`, other than the captured `skip`, attacks `target`.
+    fn attacked(&self, squares: &[u8; 4], skip: Option<usize>, target: u8, by: Color) -> bool {
+        let occupied = self.occupied(squares, skip);
+        let mut hit = false;
+        for (i, &piece) in self.pieces.iter().enumerate() {
+            if piece.color == by && Some(i) != skip {
+                attacks(piece, squares[i], occupied, &mut |sq| hit |= sq == target);
+            }
+        }
+        hit
+    }
+
+    fn is_legal(&self, squares: &[u8; 4], stm: Color) -> bool {
+        let n = self.pieces.len();
+        let distinct = self.occupied(squares, None).count_ones() as usize == n;
+        let pawns_fit = (0..n).all(|i| self.pieces[i].kind != PieceKind::Pawn || (1..7).contains(&(squares[i] / 8)));
+        distinct && pawns_fit && !self.attacked(squares, None, self.king(squares, stm.opposite()), stm)
+    }
+
+    fn in_check(&self, squares: &[u8; 4], stm: Color) -> bool {
+        self.attacked(squares, None, self.king(squares, stm), stm.opposite())
+    }
+
+    /// Result for `stm` after a capture or promotion, looked up in the tables solved before.
+    fn outside(&self, squares: &[u8; 4], captured: Option<usize>, promoted: Option<(usize, PieceKind)>, stm: Color) -> i8 {
+        let exit = self.exits.iter().find(|e| e.captured == captured && e.promoted == promoted).unwrap();
+        let Some(solution) = exit.solution else { return 0 };
+        let (flip, stm) = if exit.mirrored { (56, stm.opposite()) } else { (0, stm) };
+        let placement: usize = exit.sources.iter().enumerate().map(|(slot, &i)| ((squares[i] ^ flip) as usize) << (6 * slot)).sum();
+        solution.wdl[((stm == Color::Black) as usize) << (6 * exit.sources.len()) | placement]
+    }
+
+    fn replies(&self, squares: &[u8; 4], stm: Color, visit: &mut impl FnMut(Reply)) {
+        let n = self.pieces.len();
+        let occupied = self.occupied(squares, None);
+        let occupant = |sq: u8| (0..n).find(|&i| squares[i] == sq);
+
+        for i in 0..n {
+            let piece = self.pieces[i];
+            if piece.color != stm {
+                continue;
+            }
+            let (mut targets, mut count) = ([0u8; 28], 0);
+            let mut add = |sq: u8| {
+                targets[count] = sq;
+                count += 1;
+            };
+            if piece.kind == PieceKind::Pawn {
+                attacks(piece, squares[i], occupied, &mut |sq| {
+                    if occupied & 1 << sq != 0 {
+                        add(sq);
+                    }
+                });
+                let start_rank = if stm == Color::White { 1 } else { 6 };
+                if let Some(one) = step(squares[i], (0, forward(stm))).filter(|&sq| occupied & 1 << sq == 0) {
+                    add(one);
+                    if let Some(two) = step(one, (0, forward(stm))).filter(|&sq| occupied & 1 << sq == 0 && squares[i] / 8 == start_rank) {
+                        add(two);
+                    }
+                }
+            } else {
+                attacks(piece, squares[i], occupied, &mut add);
+            }
+
+            for &to in &targets[..count] {
+                let captured = occupant(to);
+                if captured.is_some_and(|c| self.pieces[c].color == stm || self.pieces[c].kind == PieceKind::King) {
+                    continue;
+                }
+                let mut next = *squares;
+                next[i] = to;
+                if self.attacked(&next, captured, self.king(&next, stm), stm.opposite()) {
+                    continue;
+                }
+
+                if piece.kind == PieceKind::Pawn && (to / 8 == 0 || to / 8 == 7) {
+                    for kind in PROMOTIONS {
+                        visit(Reply::Outside(self.outside(&next, captured, Some((i, kind)), stm.opposite())));
+                    }
+                } else if captured.is_some() {
+                    visit(Reply::Outside(self.outside(&next, captured, None, stm.opposite())));
+                } else {
+                    visit(Reply::Inside { zeroing: piece.kind == PieceKind::Pawn });
+                }
+            }
+        }
+    }
+
+    /// Calls `visit` with every placement a quiet move leads from into `state`, and whether
+    /// that move is a pawn push. Pawn pushes are only undone when `pawns` is set, and the
+    /// placements still need to be checked for legality.
+    fn unmoves(&self, state: usize, pawns: bool, visit: &mut impl FnMut(usize, bool)) {
+        let (squares, stm) = self.decode(state);
+        let mover = stm.opposite();
+        let occupied = self.occupied(&squares, None);
+        let empty = |sq: u8| occupied & 1 << sq == 0;
+
+        for (i, &piece) in self.pieces.iter().enumerate() {
+            if piece.color != mover {
+                continue;
+            }
+            let (mut origins, mut count) = ([0u8; 28], 0);
+            let mut add = |sq: u8| {
+                if empty(sq) {
+                    origins[count] = sq;
+                    count += 1;
+                }
+            };
+            let pawn = piece.kind == PieceKind::Pawn;
+            if pawn {
+                if !pawns {
+                    continue;
+                }
+                let back = (0, -forward(mover));
+                let start_rank = if mover == Color::White { 1 } else { 6 };
+                if let Some(one) = step(squares[i], back).filter(|&sq| empty(sq) && (1..7).contains(&(sq / 8))) {
+                    add(one);
+                    if let Some(two) = step(one, back).filter(|&sq| sq / 8 == start_rank) {
+                        add(two);
+                    }
+                }
+            } else {
+                attacks(piece, squares[i], occupied, &mut add);
+            }
+            for &from in &origins[..count] {
+                let mut previous = squares;
+                previous[i] = from;
+                visit(self.encode(&previous, mover), pawn);
+            }
+        }
+    }
+}
+
+/// Result and distance to zeroing of every position of one table.
+pub(super) struct Solution {
+    pieces: Vec<Piece>,
+    /// Win (2), draw or loss (-2) for the side to move, `BROKEN` for illegal placements.
+    wdl: Vec<i8>,
+    /// Plies to the next zeroing move or mate with best play, signed like `wdl`.
+    dtz: Vec<i8>,
+    /// Wins by a capture, promotion or pawn push, which the prober finds without the DTZ table.
+    zeroing_wins: Vec<bool>,
+}
+
+impl Solution {
+    /// The longest win in plies for `stm`, the table's longest distance to zeroing.
+    pub(super) fn longest_win(&self, stm: Color) -> i32 {
+        let half = self.wdl.len() / 2;
+        let range = if stm == Color::White { 0..half } else { half..self.wdl.len() };
+        self.dtz[range].iter().map(|&d| d as i32).max().unwrap_or(0)
+    }
+
+    fn board(&self, state: usize) -> BitboardBoard {
+        let n = self.pieces.len();
+        let mut squares = ['1'; 64];
+        for (i, piece) in self.pieces.iter().enumerate() {
+            squares[state >> (6 * i) & 63] = piece.to_fen_char();
+        }
+        let ranks: Vec<String> = squares.chunks(8).rev().map(|rank| rank.iter().collect()).collect();
+        let side = if state >> (6 * n) == 0 { "w" } else { "b" };
+        let mut board = BitboardBoard::new_empty(None);
+        board.load_fen(&format!("{} {} - - 0 1", ranks.join("/"), side));
+        board
+    }
+}
+
+/// Solves a table by retrograde analysis, WDL first and then DTZ level by level.
+/// Captures and promotions are looked up in `solved`.
+pub(super) fn solve(name: &str, solved: &HashMap<String, Solution>) -> Solution {
+    let solver = Solver::new(name, solved);
+    let states = solver.states();
+    let mut wdl = vec![BROKEN; states];
+    // best result over the moves leaving the table, in-table moves not yet known to lose
+    // and the ones among them that don't reset the counter
+    let mut outside = vec![LOSS; states];
+    let mut pending = vec![0u8; states];
+    let mut quiet = vec![0u8; states];
+    let mut mated = vec![false; states];
+    let mut queue = Vec::new();
+
+    for state in 0..states {
+        let (squares, stm) = solver.decode(state);
+        if !solver.is_legal(&squares, stm) {
+            continue;
+        }
+        let (mut any, mut inside, mut best) = (false, 0, LOSS);
+        solver.replies(&squares, stm, &mut |reply| {
+            any = true;
+            match reply {
+                Reply::Inside { zeroing } => {
+                    inside += 1;
+                    quiet[state] += !zeroing as u8;
+                }
+                Reply::Outside(value) => best = best.max(-value),
+            }
+        });
+        outside[state] = best;
+
+        wdl[state] = if !any {
+            mated[state] = solver.in_check(&squares, stm);
+            if mated[state] { LOSS } else { 0 }
+        } else if best == WIN || inside == 0 {
+            best
+        } else {
+            pending[state] = inside;
+            UNKNOWN
+        };
+        if wdl[state] == WIN || wdl[state] == LOSS {
+            queue.push(state);
+        }
+    }
+
+    while let Some(state) = queue.pop() {
+        let lost = wdl[state] == LOSS;
+        solver.unmoves(state, true, &mut |parent, _| {
+            if wdl[parent] != UNKNOWN {
+                return;
+            }
+            if lost {
+                wdl[parent] = WIN;
+                queue.push(parent);
+            } else {
+                pending[parent] -= 1;
+                if pending[parent] == 0 {
+                    wdl[parent] = outside[parent];
+                    if wdl[parent] == LOSS {
+                        queue.push(parent);
+                    }
+                }
+            }
+        });
+    }
+    // positions that can keep moving inside the table without losing are draws
+    for value in wdl.iter_mut().filter(|v| **v == UNKNOWN) {
+        *value = 0;
+    }
+
+    // a win takes one ply when it zeroes or mates right away, a loss one ply when it has
+    // no quiet moves left
+    let mut dtz = vec![0i8; states];
+    let mut zeroing_wins = vec![false; states];
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); 2];
+    for state in 0..states {
+        if wdl[state] == WIN && outside[state] == WIN {
+            zeroing_wins[state] = true;
+            if dtz[state] == 0 {
+                dtz[state] = 1;
+                levels[1].push(state);
+            }
+        } else if wdl[state] == LOSS {
+            pending[state] = quiet[state];
+            if pending[state] == 0 {
+                dtz[state] = -1;
+                levels[1].push(state);
+            }
+            solver.unmoves(state, true, &mut |parent, pawn| {
+                if (pawn || mated[state]) && wdl[parent] == WIN {
+                    zeroing_wins[parent] |= pawn;
+                    if dtz[parent] == 0 {
+                        dtz[parent] = 1;
+                        levels[1].push(parent);
+                    }
+                }
+            });
+        }
+    }
+
+    let mut level = 1;
+    while level < levels.len() {
+        assert!(level < 100, "{} has results the fifty-move rule would change", name);
+        let current = std::mem::take(&mut levels[level]);
+        let next = level as i8 + 1;
+        let mut reached = Vec::new();
+        for state in current {
+            let lost = wdl[state] == LOSS;
+            solver.unmoves(state, false, &mut |parent, _| {
+                if wdl[parent] == BROKEN {
+                    return;
+                }
+                if lost && dtz[parent] == 0 {
+                    assert_eq!(wdl[parent], WIN);
+                    dtz[parent] = next;
+                    reached.push(parent);
+                } else if !lost && wdl[parent] == LOSS {
+                    pending[parent] -= 1;
+                    if pending[parent] == 0 {
+                        dtz[parent] = -next;
+                        reached.push(parent);
+                    }
+                }
+            });
+        }
+        if !reached.is_empty() {
+            levels.push(reached);
+        }
+        level += 1;
+    }
+    assert!((0..states).all(|s| (wdl[s] != WIN && wdl[s] != LOSS) || dtz[s] != 0), "{} left positions unsolved", name);
+
+    Solution { pieces: solver.pieces, wdl, dtz, zeroing_wins }
+}
+
+/// Values by side to move, pawn file and index, `None` where no position is stored.
+type ByIndex<T> = Vec<Vec<Vec<Option<T>>>>;
+
+/// Piece order and index sizes of a table's files, as written to its header.
+struct Layout {
+    /// Indices into `Solution::pieces`, the leading piece first.
+    order: Vec<usize>,
+    files: usize,
+    /// Multiplier position of the leading group, the most significant one here.
+    lead_order: u8,
+    sizes: Vec<usize>,
+}
+
+impl Layout {
+    fn new(pieces: &[Piece]) -> Self {
+        let tables = encoding_tables();
+        let pawn = pieces.iter().position(|p| p.kind == PieceKind::Pawn);
+        let mut order: Vec<usize> = (0..pieces.len()).collect();
+        if let Some(pawn) = pawn {
+            order.retain(|&i| i != pawn);
+            order.insert(0, pawn);
+        }
+
+        // the leading pawn, or the first three unique pieces, then runs of equal pieces
+        let first_len = if pawn.is_some() { 1 } else { 3 };
+        let mut groups = vec![first_len];
+        for k in first_len..order.len() {
+            if k > first_len && pieces[order[k]] == pieces[order[k - 1]] {
+                *groups.last_mut().unwrap() += 1;
+            } else {
+                groups.push(1);
+            }
+        }
+
+        let files = if pawn.is_some() { 4 } else { 1 };
+        let sizes = (0..files).map(|file| {
+            let lead = if pawn.is_some() { tables.lead_pawns_size[1][file] } else { 31332 };
+            let mut free = 64 - first_len;
+            groups[1..].iter().fold(lead, |size, &len| {
+                let size = size * tables.binomial[len][free];
+                free -= len;
+                size
+            }) as usize
+        }).collect();
+        Self { order, files, lead_order: groups.len() as u8 - 1, sizes }
+    }
+
+    fn by_index<T: Clone>(&self) -> ByIndex<T> {
+        (0..2).map(|_| self.sizes.iter().map(|&n| vec![None; n]).collect()).collect()
+    }
+
+    fn header(&self, pieces: &[Piece]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for _ in 0..self.files {
+            out.push(self.lead_order | (self.lead_order << 4));
+            out.extend(self.order.iter().map(|&i| piece_code(pieces[i]) | (piece_code(pieces[i]) << 4)));
+        }
+        out.resize(out.len() + (out.len() + 5) % 2, 0);
+        out
+    }
+
+    /// Placements that cover every index: the leading piece in the a1-d1-d4 triangle, or
+    /// the pawn on files a to d.
+    fn covers(&self, state: usize) -> bool {
+        let lead = (state >> (6 * self.order[0]) & 63) as u8;
+        if self.files == 4 {
+            lead % 8 < 4
+        } else {
+            lead % 8 < 4 && lead / 8 <= lead % 8
+        }
+    }
+}
+
+/// One side (and pawn file) of a table after recursive pairing and Huffman coding.
+#[derive(Clone, Default)]
+struct Pairs {
+    sizes: Vec<u8>,
+    sparse_index: Vec<u8>,
+    block_lengths: Vec<u8>,
+    blocks: Vec<u8>,
+}
+
+impl Pairs {
+    fn len(&self) -> usize {
+        self.sizes.len() + self.sparse_index.len() + self.block_lengths.len() + self.blocks.len()
+    }
+}
+
+/// Replaces the most frequent pair of adjacent symbols by a new one, round after round.
+/// Returns each symbol's children, `None` for the leaf values.
+fn pair_symbols(sequence: &mut Vec<u16>, leaves: usize) -> Vec<Option<(u16, u16)>> {
+    let mut symbols: Vec<Option<(u16, u16)>> = vec![None; leaves];
+    let mut lengths = vec![1usize; leaves];
+    while symbols.len() < leaves + MAX_PAIRS {
+        let count = symbols.len();
+        let mut counts = vec![0u32; count * count];
+        for pair in sequence.windows(2) {
+            counts[pair[0] as usize * count + pair[1] as usize] += 1;
+        }
+        let best = (0..counts.len())
+            .filter(|&at| lengths[at / count] + lengths[at % count] <= MAX_SYMBOL_VALUES)
+            .max_by_key(|&at| (counts[at], Reverse(at)));
+        let Some(best) = best.filter(|&at| counts[at] >= MIN_PAIR_COUNT) else { break };
+
+        let (left, right) = ((best / count) as u16, (best % count) as u16);
+        let symbol = count as u16;
+        let mut paired = Vec::with_capacity(sequence.len());
+        let mut i = 0;
+        while i < sequence.len() {
+            if i + 1 < sequence.len() && sequence[i] == left && sequence[i + 1] == right {
+                paired.push(symbol);
+                i += 2;
+            } else {
+                paired.push(sequence[i]);
+                i += 1;
+            }
+        }
+        *sequence = paired;
+        symbols.push(Some((left, right)));
+        lengths.push(lengths[left as usize] + lengths[right as usize]);
+    }
+    symbols
+}
+
+/// Huffman code lengths, every symbol gets a code even when it is no longer used.
+fn code_lengths(frequencies: &[u64]) -> Vec<u8> {
+    let mut frequencies: Vec<u64> = frequencies.iter().map(|&f| f + 1).collect();
+    loop {
+        let mut parent = vec![usize::MAX; frequencies.len()];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = frequencies.iter().copied().zip(0..).map(Reverse).collect();
+        while heap.len() > 1 {
+            let Reverse((a, i)) = heap.pop().unwrap();
+            let Reverse((b, j)) = heap.pop().unwrap();
+            let node = parent.len();
+            parent.push(usize::MAX);
+            parent[i] = node;
+            parent[j] = node;
+            heap.push(Reverse((a + b, node)));
+        }
+        let lengths: Vec<u8> = (0..frequencies.len()).map(|mut node| {
+            let mut length = 0;
+            while parent[node] != usize::MAX {
+                node = parent[node];
+                length += 1;
+            }
+            length
+        }).collect();
+        if lengths.iter().all(|&l| l <= MAX_CODE_LENGTH) {
+            return lengths;
+        }
+        for f in frequencies.iter_mut() {
+            *f = *f / 2 + 1;
+        }
+    }
+}
+
+/// Compresses one side of a table the way the prober reads it back.
+fn compress(values: &[u16], flags: u8) -> Pairs {
+    if values.iter().all(|&v| v == values[0]) {
+        return Pairs { sizes: vec![flags | FLAG_SINGLE_VALUE, values[0] as u8], ..Default::default() };
+    }
+
+    let leaves = *values.iter().max().unwrap() as usize + 1;
+    let mut sequence = values.to_vec();
+    let symbols = pair_symbols(&mut sequence, leaves);
+    let mut value_counts = vec![1usize; symbols.len()];
+    for (sym, children) in symbols.iter().enumerate() {
+        if let Some((left, right)) = children {
+            value_counts[sym] = value_counts[*left as usize] + value_counts[*right as usize];
+        }
+    }
+    let mut frequencies = vec![0u64; symbols.len()];
+    for &sym in &sequence {
+        frequencies[sym as usize] += 1;
+    }
+    let lengths = code_lengths(&frequencies);
+
+    // canonical codes: symbols are renumbered from the longest code to the shortest
+    let mut by_length: Vec<usize> = (0..symbols.len()).collect();
+    by_length.sort_by_key(|&s| (Reverse(lengths[s]), s));
+    let mut renumbered = vec![0u16; symbols.len()];
+    for (id, &sym) in by_length.iter().enumerate() {
+        renumbered[sym] = id as u16;
+    }
+    let min_len = *lengths.iter().min().unwrap();
+    let max_len = *lengths.iter().max().unwrap();
+    let base_len = (max_len - min_len) as usize + 1;
+    let mut count = vec![0u64; base_len];
+    for &l in &lengths {
+        count[(l - min_len) as usize] += 1;
+    }
+    let mut lowest = vec![0u64; base_len];
+    let mut base = vec![0u64; base_len];
+    for i in (0..base_len - 1).rev() {
+        lowest[i] = lowest[i + 1] + count[i + 1];
+        base[i] = (base[i + 1] + count[i + 1]) / 2;
+    }
+    let code = |sym: usize| {
+        let i = (lengths[sym] - min_len) as usize;
+        (base[i] + renumbered[sym] as u64 - lowest[i], lengths[sym] as u32)
+    };
+
+    let mut sizes = vec![flags, BLOCK_SIZE_LOG, SPAN_LOG, 0];
+    let mut blocks = Vec::new();
+    let mut block_values = Vec::new();
+    let capacity = 8usize << BLOCK_SIZE_LOG;
+    let (mut bits, mut in_block) = (0usize, 0usize);
+    let mut block = vec![0u8; 1 << BLOCK_SIZE_LOG];
+    for &sym in &sequence {
+        let (code, length) = code(sym as usize);
+        if bits + length as usize > capacity || in_block + value_counts[sym as usize] > MAX_BLOCK_VALUES {
+            blocks.append(&mut block);
+            block = vec![0u8; 1 << BLOCK_SIZE_LOG];
+            block_values.push(in_block);
+            (bits, in_block) = (0, 0);
+        }
+        for bit in (0..length).rev() {
+            if code >> bit & 1 == 1 {
+                block[bits / 8] |= 0x80 >> (bits % 8);
+            }
+            bits += 1;
+        }
+        in_block += value_counts[sym as usize];
+    }
+    blocks.append(&mut block);
+    block_values.push(in_block);
+
+    sizes.extend_from_slice(&(block_values.len() as u32).to_le_bytes());
+    sizes.push(max_len);
+    sizes.push(min_len);
+    for &l in &lowest {
+        sizes.extend_from_slice(&(l as u16).to_le_bytes());
+    }
+    sizes.extend_from_slice(&(symbols.len() as u16).to_le_bytes());
+    for &sym in &by_length {
+        let (left, right) = match symbols[sym] {
+            Some((left, right)) => (renumbered[left as usize], renumbered[right as usize]),
+            None => (sym as u16, 0xFFF),
+        };
+        sizes.extend_from_slice(&[left as u8, (left >> 8) as u8 | (right << 4) as u8, (right >> 4) as u8]);
+    }
+    if symbols.len() & 1 == 1 {
+        sizes.push(0);
+    }
+
+    // each sparse entry points at the middle of its span, past the end it stays in the last block
+    let starts: Vec<usize> = block_values.iter().scan(0, |start, &n| { *start += n; Some(*start - n) }).collect();
+    let span = 1usize << SPAN_LOG;
+    let mut sparse_index = Vec::new();
+    for k in 0..values.len().div_ceil(span) {
+        let center = k * span + span / 2;
+        let block = starts.partition_point(|&start| start <= center) - 1;
+        sparse_index.extend_from_slice(&(block as u32).to_le_bytes());
+        sparse_index.extend_from_slice(&((center - starts[block]) as u16).to_le_bytes());
+    }
+    let block_lengths = block_values.iter().flat_map(|&n| (n as u16 - 1).to_le_bytes()).collect();
+
+    Pairs { sizes, sparse_index, block_lengths, blocks }
+}
+
+/// Values that no position reaches repeat the one before, which compresses best.
+fn fill_dont_cares(values: &[Option<u16>]) -> Vec<u16> {
+    let mut previous = 0;
+    values.iter().map(|v| {
+        previous = v.unwrap_or(previous);
+        previous
+    }).collect()
+}
+
+/// The DTZ side of one pawn file, stored in moves unless a result needs plies, with the
+/// distances of wins and losses mapped to small symbols.
+fn dtz_pairs(entries: &[Option<(i8, i8)>], side: usize) -> (Pairs, Vec<u8>) {
+    let distances = |class: i8| entries.iter().flatten().filter(move |e| e.0 == class).map(|e| e.1.unsigned_abs() - 1);
+    let mut flags = FLAG_MAPPED | if side == 1 { FLAG_STM } else { 0 };
+    if distances(WIN).any(|d| d % 2 == 1) {
+        flags |= FLAG_WIN_PLIES;
+    }
+    if distances(LOSS).any(|d| d % 2 == 1) {
+        flags |= FLAG_LOSS_PLIES;
+    }
+    let stored = |class: i8, d: u8| if flags & (if class == WIN { FLAG_WIN_PLIES } else { FLAG_LOSS_PLIES }) != 0 { d } else { d / 2 };
+
+    let mut map = Vec::new();
+    let mut classes = Vec::new();
+    for class in [WIN, LOSS] {
+        let mut values: Vec<u8> = distances(class).map(|d| stored(class, d)).collect();
+        values.sort();
+        values.dedup();
+        map.push(values.len() as u8);
+        map.extend_from_slice(&values);
+        classes.push(values);
+    }
+    map.extend_from_slice(&[0, 0]);
+
+    let symbols: Vec<Option<u16>> = entries.iter().map(|e| {
+        let (class, distance) = e.filter(|e| e.0 != 0)?;
+        let values = &classes[if class == WIN { 0 } else { 1 }];
+        Some(values.binary_search(&stored(class, distance.unsigned_abs() - 1)).unwrap() as u16)
+    }).collect();
+    (compress(&fill_dont_cares(&symbols), flags), map)
+}
+
+fn write_table(magic: [u8; 4], layout: &Layout, header: &[u8], pairs: &[Pairs], map: &[u8]) -> Vec<u8> {
+    // every bundled table has different material on both sides
+    let mut out = magic.to_vec();
+    out.push(0b01 | if layout.files > 1 { 0b10 } else { 0 });
+    out.extend_from_slice(header);
+    for p in pairs {
+        out.extend_from_slice(&p.sizes);
+    }
+    if magic == DTZ_MAGIC {
+        out.extend_from_slice(map);
+        out.resize(out.len() + out.len() % 2, 0);
+    }
+    for p in pairs {
+        out.extend_from_slice(&p.sparse_index);
+    }
+    for p in pairs {
+        out.extend_from_slice(&p.block_lengths);
+    }
+    for p in pairs.iter().filter(|p| !p.blocks.is_empty()) {
+        out.resize(out.len().next_multiple_of(64), 0);
+        out.extend_from_slice(&p.blocks);
+    }
+    out
+}
+
+/// Writes a solved table as (rtbw, rtbz) bytes. The DTZ table keeps whichever side to move
+/// compresses smaller per pawn file, and the index of each position comes from the prober.
+pub(super) fn write_tables(name: &str, solution: &Solution) -> (Vec<u8>, Vec<u8>) {
+    let layout = Layout::new(&solution.pieces);
+    let header = layout.header(&solution.pieces);
+    let single_valued = vec![Pairs { sizes: vec![FLAG_SINGLE_VALUE, 0], ..Default::default() }; 2 * layout.files];
+    let entry = TableEntry::from_bytes(TableKind::Wdl, name, write_table(WDL_MAGIC, &layout, &header, &single_valued, &[])).unwrap();
+
+    // for DTZ the result and the distance
+    let mut wdl: ByIndex<u16> = layout.by_index();
+    let mut dtz: ByIndex<(i8, i8)> = layout.by_index();
+    for state in 0..solution.wdl.len() {
+        if solution.wdl[state] == BROKEN || !layout.covers(state) {
+            continue;
+        }
+        let (side, file, idx) = entry.index(&solution.board(state)).unwrap();
+        let value = (solution.wdl[state] + 2) as u16;
+        let stored = wdl[side][file][idx as usize].replace(value);
+        assert!(stored.is_none_or(|v| v == value), "{} stores two results at index {}", name, idx);
+        if !solution.zeroing_wins[state] {
+            dtz[side][file][idx as usize] = Some((solution.wdl[state], solution.dtz[state]));
+        }
+    }
+
+    let wdl_pairs: Vec<Pairs> = (0..layout.files)
+        .flat_map(|file| (0..2).map(move |side| (side, file)))
+        .map(|(side, file)| compress(&fill_dont_cares(&wdl[side][file]), 0))
+        .collect();
+    let (dtz_pairs, maps): (Vec<Pairs>, Vec<Vec<u8>>) = (0..layout.files).map(|file| {
+        let white = dtz_pairs(&dtz[0][file], 0);
+        let black = dtz_pairs(&dtz[1][file], 1);
+        if black.0.len() < white.0.len() { black } else { white }
+    }).unzip();
+
+    (write_table(WDL_MAGIC, &layout, &header, &wdl_pairs, &[]), write_table(DTZ_MAGIC, &layout, &header, &dtz_pairs, &maps.concat()))
+}
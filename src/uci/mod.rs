@@ -0,0 +1,226 @@
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+use crate::board::Board;
+use crate::board::bitboard::START_FEN;
+use crate::book::BookSelection;
+use crate::game::{GameManager, DEFAULT_BOOK_DEPTH};
+use crate::search::{AlphaBetaSearcher, SearchLine, DEFAULT_SEARCH_DEPTH, MATE_SCORE, MAX_SEARCH_DEPTH};
+use crate::search::skill::{Skill, SkillLimitedSearcher, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+use crate::utils::random::Random;
+
+const ENGINE_NAME: &str = "ChessEngineRust";
+const MAX_MULTI_PV: usize = 64;
+const DEFAULT_ELO: u32 = 1500;
+
+pub struct UciEngine {
+    game_manager: GameManager,
+    skill_level: u8,
+    limit_strength: bool,
+    elo: u32,
+    rng: Random,
+}
+
+impl Default for UciEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UciEngine {
+    pub fn new() -> Self {
+        Self {
+            game_manager: GameManager::new(),
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: DEFAULT_ELO,
+            rng: Random::from_time(),
+        }
+    }
+
+    /// Seeds the book and the move choice below full strength, for reproducible games.
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.game_manager.set_random_seed(seed);
+        self.rng = Random::new(seed);
+    }
+
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        let mut stdout = io::stdout();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else { break };
+            if !self.handle_command(line.trim(), &mut stdout) {
+                break;
+            }
+            stdout.flush().unwrap();
+        }
+    }
+
+    /// Handles a single UCI command, returns false when the engine should quit.
+    pub fn handle_command(&mut self, line: &str, out: &mut dyn Write) -> bool {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let Some(&command) = parts.first() else { return true };
+
+        match command {
+            "uci" => {
+                writeln!(out, "id name {}", ENGINE_NAME).unwrap();
+                writeln!(out, "option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV).unwrap();
+                writeln!(out, "option name BookFile type string default <empty>").unwrap();
+                writeln!(out, "option name BookDepth type spin default {} min 0 max 200", DEFAULT_BOOK_DEPTH).unwrap();
+                writeln!(out, "option name BestBookMove type check default false").unwrap();
+                writeln!(out, "option name SyzygyPath type string default <empty>").unwrap();
+                writeln!(out, "option name UCI_Chess960 type check default false").unwrap();
+                writeln!(out, "option name Skill Level type spin default {} min 0 max {}", MAX_SKILL_LEVEL, MAX_SKILL_LEVEL).unwrap();
+                writeln!(out, "option name UCI_LimitStrength type check default false").unwrap();
+                writeln!(out, "option name UCI_Elo type spin default {} min {} max {}", DEFAULT_ELO, MIN_ELO, MAX_ELO).unwrap();
+                writeln!(out, "uciok").unwrap();
+            }
+            "isready" => writeln!(out, "readyok").unwrap(),
+            "ucinewgame" => self.game_manager.load_from_fen(START_FEN),
+            "setoption" => self.set_option(&parts[1..], out),
+            "position" => self.set_position(&parts[1..], out),
+            "go" => self.go(&parts[1..], out),
+            "quit" => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn set_option(&mut self, args: &[&str], out: &mut dyn Write) {
+        // setoption name <id> [value <x>]
+        if args.first() != Some(&"name") {
+            return;
+        }
+        let value_index = args.iter().position(|&a| a == "value").unwrap_or(args.len());
+        let name = args[1..value_index].join(" ");
+        let value = args.get(value_index + 1..).unwrap_or_default().join(" ");
+
+        match name.to_ascii_lowercase().as_str() {
+            "multipv" => {
+                if let Ok(multi_pv) = value.parse::<usize>() {
+                    self.game_manager.set_multi_pv(multi_pv.min(MAX_MULTI_PV));
+                }
+            }
+            "bookfile" => {
+                if value.is_empty() || value == "<empty>" {
+                    self.game_manager.set_book(None);
+                } else if let Err(err) = self.game_manager.load_book(&value) {
+                    writeln!(out, "info string could not open book {}: {}", value, err).unwrap();
+                }
+            }
+            "syzygypath" => {
+                if !value.is_empty() && value != "<empty>"
+                    && let Err(err) = self.game_manager.load_tablebase(&value) {
+                    writeln!(out, "info string could not open tablebases {}: {}", value, err).unwrap();
+                }
+            }
+            "uci_chess960" => self.game_manager.set_chess960(value == "true"),
+            "bookdepth" => {
+                if let Ok(depth) = value.parse::<u16>() {
+                    self.game_manager.set_book_depth(depth);
+                }
+            }
+            "skill level" => {
+                if let Ok(level) = value.parse::<u8>() {
+                    self.skill_level = level.min(MAX_SKILL_LEVEL);
+                }
+            }
+            "uci_limitstrength" => self.limit_strength = value == "true",
+            "uci_elo" => {
+                if let Ok(elo) = value.parse::<u32>() {
+                    self.elo = elo.clamp(MIN_ELO, MAX_ELO);
+                }
+            }
+            "bestbookmove" => {
+                let selection = if value == "true" { BookSelection::BestOnly } else { BookSelection::WeightedRandom };
+                self.game_manager.set_book_selection(selection);
+            }
+            _ => {}
+        }
+    }
+
+    fn set_position(&mut self, args: &[&str], out: &mut dyn Write) {
+        let moves_index = args.iter().position(|&a| a == "moves").unwrap_or(args.len());
+        match args.first() {
+            Some(&"startpos") => self.game_manager.load_from_fen(START_FEN),
+            Some(&"fen") => {
+                let fen = args[1..moves_index].join(" ");
+                // a malformed position is ignored, the previous one stays
+                if let Err(err) = self.game_manager.try_load_from_fen(&fen) {
+                    writeln!(out, "info string invalid fen {}: {}", fen, err).unwrap();
+                    return;
+                }
+            }
+            _ => return,
+        }
+
+        for mv in args.iter().skip(moves_index + 1) {
+            if self.game_manager.make_move_from_uci(mv).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn go(&mut self, args: &[&str], out: &mut dyn Write) {
+        if let Some(mv) = self.game_manager.book_move() {
+            writeln!(out, "bestmove {}", self.game_manager.get_board().move_to_uci(&mv)).unwrap();
+            return;
+        }
+
+        let value = |name: &str| args.iter()
+            .position(|&a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .and_then(|v| v.parse::<u64>().ok());
+        let nodes = value("nodes");
+        let movetime = value("movetime");
+        let default_depth = if nodes.is_some() || movetime.is_some() { MAX_SEARCH_DEPTH } else { DEFAULT_SEARCH_DEPTH };
+        let depth = value("depth").map_or(default_depth, |d| d.min(MAX_SEARCH_DEPTH as u64) as u8);
+
+        let mut searcher = AlphaBetaSearcher::new(depth).with_tablebase(self.game_manager.get_tablebase());
+        if let Some(nodes) = nodes {
+            searcher = searcher.with_node_limit(nodes);
+        }
+        if let Some(movetime) = movetime {
+            searcher = searcher.with_time_limit(Duration::from_millis(movetime));
+        }
+        // UCI_LimitStrength takes precedence over the skill level
+        let skill = if self.limit_strength { Skill::from_elo(self.elo) } else { Skill::new(self.skill_level) };
+        if skill.is_full_strength() {
+            self.game_manager.set_searcher(Box::new(searcher));
+        } else {
+            let seed = self.rng.next_u64();
+            self.game_manager.set_searcher(Box::new(SkillLimitedSearcher::new(searcher, skill).with_seed(seed)));
+        }
+
+        let chess960 = self.game_manager.get_board().chess960;
+        let lines = self.game_manager.find_best_lines_with(&mut |lines| {
+            for (i, line) in lines.iter().enumerate() {
+                writeln!(out, "{}", format_info(line, i + 1, chess960)).unwrap();
+            }
+        });
+
+        match lines.first().and_then(SearchLine::best_move) {
+            Some(mv) => writeln!(out, "bestmove {}", self.game_manager.get_board().move_to_uci(&mv)).unwrap(),
+            None => writeln!(out, "bestmove 0000").unwrap(),
+        }
+    }
+}
+
+pub fn format_score(score: i32, pv_length: usize) -> String {
+    if score.abs() >= MATE_SCORE - 1000 {
+        let moves = pv_length.div_ceil(2) as i32;
+        format!("mate {}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+fn format_info(line: &SearchLine, multi_pv: usize, chess960: bool) -> String {
+    let pv: Vec<String> = line.pv.iter().map(|m| if chess960 { m.to_uci_960() } else { m.to_uci() }).collect();
+    format!(
+        "info depth {} multipv {} score {} pv {}",
+        line.depth,
+        multi_pv,
+        format_score(line.score, line.pv.len()),
+        pv.join(" ")
+    )
+}
@@ -1,8 +1,8 @@
 pub mod utils{
-    use crate::board::{BitboardBoard, Board};
+    use crate::board::Board;
     use crate::board::types::{Color, Piece, PieceKind, Square};
 
-    pub fn print_board_state(board: &BitboardBoard) {
+    pub fn print_board_state<B: Board>(board: &B) {
         let mut board_string = String::new();
 
         for rank in (0..8).rev() {
@@ -22,8 +22,6 @@ pub mod utils{
         print!("{}", board_string);
     }
 
-
-
     pub fn map_piece_to_char(piece: &Piece) -> &str {
         match (piece.kind, piece.color) {
             (PieceKind::Pawn,   Color::White) => "P",
@@ -1 +1,2 @@
 pub mod bitboards;
+pub mod random;